@@ -1,3 +1,6 @@
 fn main() {
+    // Only the app needs Tauri's config and capabilities; the parser library
+    // builds without them
+    #[cfg(feature = "app")]
     tauri_build::build()
 }
@@ -0,0 +1,145 @@
+use crate::event::{LogEvent, KIND_NAMES};
+use crate::settings;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Drives the tray/overlay activity indicator.
+pub const ACTIVITY_EVENT: &str = "activity";
+
+const ACTIVITY_KINDS_KEY: &str = "activityKinds";
+
+/// Kinds that count as activity until the user picks their own.
+const DEFAULT_KINDS: [&str; 3] = ["actor_death", "vehicle_destruction", "damage_tick"];
+
+/// At most one `activity` event is sent per interval; the indicator only
+/// needs to know something happened, not how much.
+const ACTIVITY_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Activity {
+    /// Kind of the first matching event in the batch.
+    pub kind: String,
+    /// Matching events in the batch.
+    pub count: usize,
+}
+
+/// The kinds that count as activity, and when the last pulse went out.
+pub struct ActivityFilter {
+    kinds: HashSet<String>,
+    last_pulse: Option<Instant>,
+}
+
+impl Default for ActivityFilter {
+    fn default() -> Self {
+        ActivityFilter {
+            kinds: DEFAULT_KINDS.iter().map(|kind| kind.to_string()).collect(),
+            last_pulse: None,
+        }
+    }
+}
+
+impl ActivityFilter {
+    /// The pulse for a batch, if it has matching events and the last pulse
+    /// was long enough ago.
+    fn pulse(&mut self, events: &[LogEvent], now: Instant) -> Option<Activity> {
+        let mut matching = events
+            .iter()
+            .filter(|event| self.kinds.contains(event.kind_name()));
+        let first = matching.next()?;
+        if self
+            .last_pulse
+            .is_some_and(|last| now.duration_since(last) < ACTIVITY_INTERVAL)
+        {
+            return None;
+        }
+        let activity = Activity {
+            kind: first.kind_name().to_string(),
+            count: 1 + matching.count(),
+        };
+        self.last_pulse = Some(now);
+        Some(activity)
+    }
+}
+
+#[derive(Default)]
+pub struct ActivityState(Mutex<ActivityFilter>);
+
+/// Load the persisted kind selection. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    if let Ok(Some(kinds)) = settings::get::<Vec<String>>(app, ACTIVITY_KINDS_KEY) {
+        app.state::<ActivityState>().0.lock().unwrap().kinds = kinds.into_iter().collect();
+    }
+}
+
+/// Send an `activity` event if the batch has anything notable in it.
+pub fn observe_batch(app: &AppHandle, events: &[LogEvent]) {
+    let pulse = app
+        .state::<ActivityState>()
+        .0
+        .lock()
+        .unwrap()
+        .pulse(events, Instant::now());
+    if let Some(activity) = pulse {
+        if let Err(e) = app.emit(ACTIVITY_EVENT, activity) {
+            tracing::warn!("Failed to emit {}: {}", ACTIVITY_EVENT, e);
+        }
+    }
+}
+
+/// Choose which event kinds pulse the activity indicator. An empty list
+/// turns it off.
+#[tauri::command]
+pub fn set_activity_kinds(
+    app: AppHandle,
+    state: State<'_, ActivityState>,
+    kinds: Vec<String>,
+) -> Result<(), String> {
+    if let Some(unknown) = kinds
+        .iter()
+        .find(|kind| !KIND_NAMES.contains(&kind.as_str()))
+    {
+        return Err(format!("Unknown event kind: {}", unknown));
+    }
+    settings::set(&app, ACTIVITY_KINDS_KEY, &kinds)?;
+    state.0.lock().unwrap().kinds = kinds.into_iter().collect();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_activity_kinds(state: State<'_, ActivityState>) -> Vec<String> {
+    let mut kinds: Vec<String> = state.0.lock().unwrap().kinds.iter().cloned().collect();
+    kinds.sort();
+    kinds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{EventKind, TestDeath};
+
+    #[test]
+    fn pulses_only_for_matching_kinds_and_at_most_once_per_interval() {
+        let quit = LogEvent::test(EventKind::SystemQuit);
+        let death = LogEvent::test(TestDeath::new("Bob", "Alice").into());
+        let mut filter = ActivityFilter::default();
+        let start = Instant::now();
+
+        assert_eq!(filter.pulse(std::slice::from_ref(&quit), start), None);
+        assert_eq!(
+            filter.pulse(&[quit, death.clone(), death.clone()], start),
+            Some(Activity {
+                kind: "actor_death".to_string(),
+                count: 2,
+            })
+        );
+        let deaths = [death];
+        assert_eq!(filter.pulse(&deaths, start + ACTIVITY_INTERVAL / 2), None);
+        assert!(filter.pulse(&deaths, start + ACTIVITY_INTERVAL).is_some());
+
+        filter.kinds = ["system_quit".to_string()].into_iter().collect();
+        assert_eq!(filter.pulse(&deaths, start + 2 * ACTIVITY_INTERVAL), None);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::buffer::{self, EventBuffer};
+use crate::event::{event_fingerprint, LogEvent};
+use crate::export;
+use crate::session;
+use chrono::DateTime;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveResult {
+    /// Events moved out of the live feed.
+    pub archived: usize,
+    /// Events left in the live feed.
+    pub kept: usize,
+    /// The archive files written to, one per UTC day.
+    pub files: Vec<PathBuf>,
+}
+
+fn archive_file(dir: &Path, timestamp_ms: i64) -> PathBuf {
+    let day = DateTime::from_timestamp_millis(timestamp_ms).map_or_else(
+        || "unknown".to_string(),
+        |at| at.format("%Y-%m-%d").to_string(),
+    );
+    dir.join(format!("picologs-archive-{}.jsonl", day))
+}
+
+/// Fingerprints of the events already in an archive file. Lines that don't
+/// parse are left in the file but can't be matched.
+fn archived_fingerprints(path: &Path) -> io::Result<HashSet<String>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str::<LogEvent>(line).ok())
+        .map(|event| event_fingerprint(&event))
+        .collect())
+}
+
+/// Add events to an archive file, skipping any it already has. The whole
+/// file is written to a temporary copy and renamed over the original, so
+/// it's never left half-written.
+fn append_to_archive(path: &Path, events: &[&LogEvent]) -> io::Result<()> {
+    let mut seen = archived_fingerprints(path)?;
+    let temp = path.with_extension("jsonl.tmp");
+    let mut out = BufWriter::new(File::create(&temp)?);
+    if path.exists() {
+        io::copy(&mut File::open(path)?, &mut out)?;
+    }
+    let new = events
+        .iter()
+        .copied()
+        .filter(|event| seen.insert(event_fingerprint(event)));
+    export::write_jsonl(&mut out, new)?;
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    fs::rename(&temp, path)
+}
+
+/// Write every timed event from before `before_ms` to dated archive files in
+/// `dir`. Returns the fingerprints of the archived events and the files.
+/// Archiving the same events again adds nothing, so a run interrupted after
+/// writing can safely be repeated.
+fn archive_events(
+    events: &[LogEvent],
+    before_ms: i64,
+    dir: &Path,
+) -> io::Result<(HashSet<String>, Vec<PathBuf>)> {
+    let mut by_file: BTreeMap<PathBuf, Vec<&LogEvent>> = BTreeMap::new();
+    for event in events {
+        // Untimed events can't be placed before or after the cutoff
+        if let Some(timestamp_ms) = event.timestamp_ms.filter(|&at| at < before_ms) {
+            by_file
+                .entry(archive_file(dir, timestamp_ms))
+                .or_default()
+                .push(event);
+        }
+    }
+    if by_file.is_empty() {
+        return Ok((HashSet::new(), Vec::new()));
+    }
+
+    fs::create_dir_all(dir)?;
+    let mut fingerprints = HashSet::new();
+    for (path, events) in &by_file {
+        append_to_archive(path, events)?;
+        fingerprints.extend(events.iter().map(|event| event_fingerprint(event)));
+    }
+    Ok((fingerprints, by_file.into_keys().collect()))
+}
+
+/// Move events from before `before_ms` out of the live feed into one JSONL
+/// file per UTC day in `dest_dir`, which `load_history_into_buffer` can read
+/// back. Events only leave the feed once their archive files are safely
+/// written, and the saved session is updated to match straight after.
+#[tauri::command]
+pub async fn archive_history(
+    app: AppHandle,
+    before_ms: i64,
+    dest_dir: String,
+) -> Result<ArchiveResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let buffer = app.state::<EventBuffer>();
+        let events = buffer.snapshot(None);
+        let (fingerprints, files) =
+            archive_events(&events, before_ms, Path::new(&dest_dir)).map_err(|e| e.to_string())?;
+        if fingerprints.is_empty() {
+            return Ok(ArchiveResult {
+                archived: 0,
+                kept: events.len(),
+                files,
+            });
+        }
+
+        let archived = buffer.remove(&fingerprints);
+        session::save_now(&app)?;
+        buffer::reemit(&app, &buffer)?;
+        tracing::info!("Archived {} events to {}", archived, dest_dir);
+        Ok(ArchiveResult {
+            archived,
+            kept: buffer.snapshot(None).len(),
+            files,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventKind;
+
+    #[test]
+    fn old_events_go_to_dated_files_once() {
+        let dir = std::env::temp_dir().join(format!("picologs-archive-{}", std::process::id()));
+        let day = 24 * 60 * 60 * 1000;
+        // 2024-06-07 and 2024-06-08 UTC
+        let first = 1_717_718_400_000;
+        let events = vec![
+            LogEvent::test(EventKind::SystemQuit).at(first + 1),
+            LogEvent::test(EventKind::SystemQuit).at(first + day + 1),
+            LogEvent::test(EventKind::SystemQuit).at(None),
+            LogEvent::test(EventKind::SystemQuit).at(first + 2 * day),
+        ];
+
+        let (fingerprints, files) = archive_events(&events, first + 2 * day, &dir).unwrap();
+        assert_eq!(fingerprints.len(), 2);
+        assert_eq!(
+            files,
+            [
+                dir.join("picologs-archive-2024-06-07.jsonl"),
+                dir.join("picologs-archive-2024-06-08.jsonl"),
+            ]
+        );
+
+        // A repeat, as after a crash before the feed was trimmed, adds nothing
+        archive_events(&events, first + 2 * day, &dir).unwrap();
+        let archived: Vec<LogEvent> = fs::read_to_string(&files[0])
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(archived, [events[0].clone()]);
+        assert!(!dir.join("picologs-archive-2024-06-07.jsonl.tmp").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use crate::watcher::{self, WatchRegistry};
+use crate::ENVIRONMENTS;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+const SCAN_INTERVAL: Duration = Duration::from_secs(2);
+pub const AUTO_WATCH_TARGET_EVENT: &str = "auto-watch-target";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoWatchTarget {
+    pub path: String,
+    pub environment: Option<String>,
+}
+
+struct AutoWatch {
+    root: PathBuf,
+    stop: AtomicBool,
+    /// Path of the log currently being tailed on this root's behalf, and
+    /// whether this root started the watch on it (a watch started by hand is
+    /// left alone, then and when the root moves on).
+    target: Mutex<Option<(String, bool)>>,
+}
+
+#[derive(Default)]
+pub struct AutoWatchState {
+    current: Mutex<Option<Arc<AutoWatch>>>,
+}
+
+/// Candidate Game.log paths under an install root. Accepts either the
+/// `StarCitizen` folder itself or the `Roberts Space Industries` folder above it.
+fn candidate_logs(root: &Path) -> Vec<PathBuf> {
+    [root.to_path_buf(), root.join("StarCitizen")]
+        .iter()
+        .flat_map(|base| {
+            ENVIRONMENTS
+                .iter()
+                .map(|env| base.join(env).join("Game.log"))
+        })
+        .collect()
+}
+
+/// The most recently written Game.log under `root`, if any exist.
+pub fn newest_log(root: &Path) -> Option<PathBuf> {
+    candidate_logs(root)
+        .into_iter()
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        .max_by_key(|(_, modified)| *modified)
+        .map(|(path, _)| path)
+}
+
+fn environment_of(path: &Path) -> Option<String> {
+    path.parent()
+        .and_then(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+/// Switch the tailer to the newest log under the root if it isn't already on it.
+fn follow(app: &AppHandle, auto: &AutoWatch) {
+    let Some(newest) = newest_log(&auto.root) else {
+        return;
+    };
+    let newest_str = newest.to_string_lossy().to_string();
+    let mut target = auto.target.lock().unwrap();
+    if target.as_ref().map(|(path, _)| path.as_str()) == Some(newest_str.as_str()) {
+        return;
+    }
+
+    let registry = app.state::<WatchRegistry>();
+    if let Some((previous, true)) = target.take() {
+        watcher::stop(app, &registry, &previous);
+    }
+    let owned = match watcher::launch(app, &registry, newest_str.clone(), 0, None, false) {
+        Ok((_, already_active)) => !already_active,
+        Err(e) => {
+            tracing::warn!("Failed to start watching {}: {}", newest_str, e);
+            return;
+        }
+    };
+
+    let chosen = AutoWatchTarget {
+        path: newest_str.clone(),
+        environment: environment_of(&newest),
+    };
+    *target = Some((newest_str, owned));
+    let _ = app.emit(AUTO_WATCH_TARGET_EVENT, &chosen);
+}
+
+fn stop_current(app: &AppHandle, state: &AutoWatchState) -> bool {
+    let Some(previous) = state.current.lock().unwrap().take() else {
+        return false;
+    };
+    previous.stop.store(true, Ordering::Relaxed);
+    if let Some((target, true)) = previous.target.lock().unwrap().take() {
+        watcher::stop(app, &app.state::<WatchRegistry>(), &target);
+    }
+    true
+}
+
+/// Follow whichever environment under an install root is logging, switching
+/// the tailer as soon as a different environment's Game.log is written more
+/// recently. Returns the log picked on the first scan, if there was one.
+#[tauri::command]
+pub fn watch_install_root(
+    app: AppHandle,
+    state: State<'_, AutoWatchState>,
+    root: String,
+) -> Result<Option<String>, String> {
+    let root = PathBuf::from(root);
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+
+    stop_current(&app, &state);
+    let auto = Arc::new(AutoWatch {
+        root,
+        stop: AtomicBool::new(false),
+        target: Mutex::new(None),
+    });
+    *state.current.lock().unwrap() = Some(auto.clone());
+
+    follow(&app, &auto);
+    let initial = auto
+        .target
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|(path, _)| path.clone());
+
+    thread::spawn(move || loop {
+        thread::sleep(SCAN_INTERVAL);
+        if auto.stop.load(Ordering::Relaxed) {
+            break;
+        }
+        follow(&app, &auto);
+    });
+
+    Ok(initial)
+}
+
+/// Stop following the install root, along with the log it was tailing if it
+/// started that watch.
+#[tauri::command]
+pub fn stop_install_root_watch(app: AppHandle, state: State<'_, AutoWatchState>) -> bool {
+    stop_current(&app, &state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    #[test]
+    fn picks_the_most_recently_written_environment() {
+        let root = std::env::temp_dir().join(format!("picologs-autowatch-{}", std::process::id()));
+        for env in ["LIVE", "PTU"] {
+            fs::create_dir_all(root.join(env)).unwrap();
+        }
+        assert_eq!(newest_log(&root), None);
+
+        let live = root.join("LIVE").join("Game.log");
+        let ptu = root.join("PTU").join("Game.log");
+        fs::write(&live, "live").unwrap();
+        fs::write(&ptu, "ptu").unwrap();
+
+        let old = SystemTime::now() - Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&live)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        assert_eq!(newest_log(&root), Some(ptu.clone()));
+        assert_eq!(environment_of(&ptu).as_deref(), Some("PTU"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,69 @@
+use crate::event::{event_fingerprint, LogEvent};
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+const BOOKMARKS_KEY: &str = "bookmarks";
+const MAX_BOOKMARKS: usize = 200;
+
+/// A pinned event. The event itself is copied in so the bookmark still renders
+/// after the live feed has moved on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub fingerprint: String,
+    pub event: LogEvent,
+    pub created_at_ms: i64,
+}
+
+fn load(app: &AppHandle) -> Result<Vec<Bookmark>, String> {
+    Ok(settings::get(app, BOOKMARKS_KEY)?.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn bookmark_event(app: AppHandle, event: LogEvent) -> Result<Bookmark, String> {
+    let mut bookmarks = load(&app)?;
+    let fingerprint = event_fingerprint(&event);
+
+    // Bookmarking the same event twice is a no-op rather than a duplicate entry
+    if let Some(existing) = bookmarks.iter().find(|b| b.fingerprint == fingerprint) {
+        return Ok(existing.clone());
+    }
+
+    if bookmarks.len() >= MAX_BOOKMARKS {
+        return Err(format!(
+            "Bookmark limit reached ({}). Remove a bookmark before adding another.",
+            MAX_BOOKMARKS
+        ));
+    }
+
+    let bookmark = Bookmark {
+        fingerprint,
+        event,
+        created_at_ms: chrono::Utc::now().timestamp_millis(),
+    };
+    bookmarks.push(bookmark.clone());
+    settings::set(&app, BOOKMARKS_KEY, &bookmarks)?;
+
+    Ok(bookmark)
+}
+
+/// Returns whether a bookmark with that fingerprint existed.
+#[tauri::command]
+pub fn remove_bookmark(app: AppHandle, fingerprint: String) -> Result<bool, String> {
+    let mut bookmarks = load(&app)?;
+    let before = bookmarks.len();
+    bookmarks.retain(|b| b.fingerprint != fingerprint);
+
+    if bookmarks.len() == before {
+        return Ok(false);
+    }
+
+    settings::set(&app, BOOKMARKS_KEY, &bookmarks)?;
+    Ok(true)
+}
+
+/// Bookmarks in the order they were added.
+#[tauri::command]
+pub fn list_bookmarks(app: AppHandle) -> Result<Vec<Bookmark>, String> {
+    load(&app)
+}
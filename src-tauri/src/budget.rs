@@ -0,0 +1,189 @@
+use crate::settings;
+use serde::Serialize;
+use std::sync::{Condvar, Mutex};
+use tauri::{AppHandle, Manager, State};
+
+const PARSE_CONCURRENCY_KEY: &str = "parseConcurrency";
+
+/// Which queue work waits in. Live work starts before any waiting
+/// background work, so tailing keeps up while a big file is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// A live tail's poll.
+    Live,
+    /// Whole-file parses and a watch's first catch-up read.
+    Background,
+}
+
+#[derive(Debug)]
+struct Slots {
+    limit: usize,
+    running: usize,
+    live_waiting: usize,
+    background_waiting: usize,
+}
+
+impl Slots {
+    fn admits(&self, priority: Priority) -> bool {
+        self.running < self.limit && (priority == Priority::Live || self.live_waiting == 0)
+    }
+
+    fn waiting(&mut self, priority: Priority) -> &mut usize {
+        match priority {
+            Priority::Live => &mut self.live_waiting,
+            Priority::Background => &mut self.background_waiting,
+        }
+    }
+}
+
+/// Bounds how many parses run at once. Work over the budget queues until a
+/// slot frees up.
+pub struct WorkBudget {
+    slots: Mutex<Slots>,
+    freed: Condvar,
+}
+
+impl Default for WorkBudget {
+    fn default() -> Self {
+        WorkBudget::new(default_concurrency())
+    }
+}
+
+fn default_concurrency() -> usize {
+    std::thread::available_parallelism().map_or(2, |n| n.get())
+}
+
+/// Held while the work runs; the slot is given back when it's dropped.
+pub struct Permit<'a>(&'a WorkBudget);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.0.slots.lock().unwrap().running -= 1;
+        self.0.freed.notify_all();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetStatus {
+    pub limit: usize,
+    pub running: usize,
+    /// Work waiting for a slot, live and background together.
+    pub queued: usize,
+}
+
+impl WorkBudget {
+    fn new(limit: usize) -> Self {
+        WorkBudget {
+            slots: Mutex::new(Slots {
+                limit: limit.max(1),
+                running: 0,
+                live_waiting: 0,
+                background_waiting: 0,
+            }),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Wait for a slot.
+    pub fn acquire(&self, priority: Priority) -> Permit<'_> {
+        let mut slots = self.slots.lock().unwrap();
+        *slots.waiting(priority) += 1;
+        while !slots.admits(priority) {
+            slots = self.freed.wait(slots).unwrap();
+        }
+        *slots.waiting(priority) -= 1;
+        slots.running += 1;
+        Permit(self)
+    }
+
+    fn set_limit(&self, limit: usize) {
+        self.slots.lock().unwrap().limit = limit;
+        // A higher limit may let queued work start now
+        self.freed.notify_all();
+    }
+
+    pub fn status(&self) -> BudgetStatus {
+        let slots = self.slots.lock().unwrap();
+        BudgetStatus {
+            limit: slots.limit,
+            running: slots.running,
+            queued: slots.live_waiting + slots.background_waiting,
+        }
+    }
+}
+
+/// Load the persisted budget. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    if let Ok(Some(limit)) = settings::get::<usize>(app, PARSE_CONCURRENCY_KEY) {
+        app.state::<WorkBudget>().set_limit(limit.max(1));
+    }
+}
+
+/// Allow at most `n` parses and catch-up reads at once. Work already running
+/// isn't interrupted when the budget shrinks.
+#[tauri::command]
+pub fn set_parse_concurrency(
+    app: AppHandle,
+    budget: State<'_, WorkBudget>,
+    n: usize,
+) -> Result<(), String> {
+    if n == 0 {
+        return Err("At least one parse must be allowed to run".to_string());
+    }
+    settings::set(&app, PARSE_CONCURRENCY_KEY, &n)?;
+    budget.set_limit(n);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_parse_budget(budget: State<'_, WorkBudget>) -> BudgetStatus {
+    budget.status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    fn wait_for_queue(budget: &WorkBudget, queued: usize) {
+        while budget.status().queued < queued {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn queued_live_work_starts_before_background_work() {
+        let budget = Arc::new(WorkBudget::new(1));
+        let held = budget.acquire(Priority::Background);
+        let (started, order) = std::sync::mpsc::channel();
+
+        let mut waiters = Vec::new();
+        for (queued, priority) in [(1, Priority::Background), (2, Priority::Live)] {
+            let waiting = budget.clone();
+            let started = started.clone();
+            waiters.push(thread::spawn(move || {
+                let _permit = waiting.acquire(priority);
+                started.send(priority).unwrap();
+            }));
+            wait_for_queue(&budget, queued);
+        }
+        assert_eq!(
+            budget.status(),
+            BudgetStatus {
+                limit: 1,
+                running: 1,
+                queued: 2,
+            }
+        );
+
+        drop(held);
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        let order: Vec<_> = order.try_iter().collect();
+        assert_eq!(order, [Priority::Live, Priority::Background]);
+        assert_eq!(budget.status().running, 0);
+    }
+}
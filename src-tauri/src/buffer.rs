@@ -0,0 +1,222 @@
+use crate::catalog;
+use crate::event::{event_fingerprint, LogEvent};
+use crate::search::{self, SearchQuery};
+use crate::windows;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+
+/// How many of the most recent events are kept in memory.
+const MAX_EVENTS: usize = 5_000;
+
+/// Events per `log-batch` message when replaying, to keep each IPC payload
+/// small.
+const REPLAY_CHUNK: usize = 250;
+
+/// Sent before the whole buffer is re-emitted after loading history, so
+/// windows drop what they have instead of appending duplicates.
+pub const BUFFER_RELOADED_EVENT: &str = "buffer-reloaded";
+
+/// The most recent events from every watch, oldest first.
+#[derive(Default)]
+pub struct EventBuffer(Mutex<VecDeque<LogEvent>>);
+
+impl EventBuffer {
+    pub fn push_batch(&self, events: &[LogEvent]) {
+        let mut buffer = self.0.lock().unwrap();
+        buffer.extend(events.iter().cloned());
+        let excess = buffer.len().saturating_sub(MAX_EVENTS);
+        buffer.drain(..excess);
+    }
+
+    /// Merge earlier events into the buffer, skipping any already in it, and
+    /// keep it in chronological order. An untimed event sorts with the event
+    /// before it. Returns how many events were added; the oldest are evicted
+    /// past capacity as usual.
+    pub fn merge_history(&self, events: Vec<LogEvent>) -> usize {
+        let mut buffer = self.0.lock().unwrap();
+        let mut seen: HashSet<String> = buffer.iter().map(event_fingerprint).collect();
+        let added: Vec<LogEvent> = events
+            .into_iter()
+            .filter(|event| seen.insert(event_fingerprint(event)))
+            .collect();
+        let count = added.len();
+
+        let mut merged: Vec<(i64, LogEvent)> = Vec::with_capacity(buffer.len() + count);
+        for events in [added, buffer.drain(..).collect()] {
+            let mut last = i64::MIN;
+            for event in events {
+                last = event.timestamp_ms.unwrap_or(last);
+                merged.push((last, event));
+            }
+        }
+        // Stable, so the live events keep their order among equal timestamps
+        merged.sort_by_key(|(at, _)| *at);
+
+        buffer.extend(merged.into_iter().map(|(_, event)| event));
+        let excess = buffer.len().saturating_sub(MAX_EVENTS);
+        buffer.drain(..excess);
+        count
+    }
+
+    /// Re-run the name resolvers over every buffered event.
+    pub fn reresolve_names(&self) {
+        for event in self.0.lock().unwrap().iter_mut() {
+            catalog::resolve_names(&mut event.kind);
+        }
+    }
+
+    /// Drop the buffered events with these fingerprints. Returns how many
+    /// were dropped.
+    pub fn remove(&self, fingerprints: &HashSet<String>) -> usize {
+        let mut buffer = self.0.lock().unwrap();
+        let before = buffer.len();
+        buffer.retain(|event| !fingerprints.contains(&event_fingerprint(event)));
+        before - buffer.len()
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    /// A copy of the buffered events, optionally limited to some kinds.
+    pub fn snapshot(&self, kinds: Option<&[String]>) -> Vec<LogEvent> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.matches_kinds(kinds))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Push the buffered feed again as `log-batch` events to the window that
+/// asked, e.g. after a reload or when a new window opens. The window's own
+/// filter still applies on top of `kinds`. Returns how many events matched.
+#[tauri::command]
+pub fn replay_buffer(
+    app: AppHandle,
+    window: Window,
+    buffer: State<'_, EventBuffer>,
+    kinds: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let events = buffer.snapshot(kinds.as_deref());
+    for chunk in events.chunks(REPLAY_CHUNK) {
+        windows::emit_batch_to(&app, window.label(), chunk).map_err(|e| e.to_string())?;
+    }
+    Ok(events.len())
+}
+
+/// Send every window the whole buffer from the start, preceded by
+/// `buffer-reloaded`.
+pub fn reemit(app: &AppHandle, buffer: &EventBuffer) -> Result<(), String> {
+    let events = buffer.snapshot(None);
+    app.emit(BUFFER_RELOADED_EVENT, events.len())
+        .map_err(|e| e.to_string())?;
+    for chunk in events.chunks(REPLAY_CHUNK) {
+        windows::emit_batch(app, chunk);
+    }
+    Ok(())
+}
+
+/// Re-resolve the buffered feed's friendly names, e.g. after the catalog
+/// changed, and re-send it.
+pub fn refresh_names(app: &AppHandle) -> Result<(), String> {
+    let buffer = app.state::<EventBuffer>();
+    buffer.reresolve_names();
+    reemit(app, &buffer)
+}
+
+/// Merge a previous session's events (e.g. from an imported JSONL file) into
+/// the live feed, then send every window the merged feed from the start,
+/// preceded by `buffer-reloaded`. Returns how many events were new.
+#[tauri::command]
+pub fn load_history_into_buffer(
+    app: AppHandle,
+    buffer: State<'_, EventBuffer>,
+    events: Vec<LogEvent>,
+) -> Result<usize, String> {
+    let added = buffer.merge_history(events);
+    reemit(&app, &buffer)?;
+    Ok(added)
+}
+
+/// Search the buffered events. Results are in chronological order.
+#[tauri::command]
+pub fn search_events(buffer: State<'_, EventBuffer>, query: SearchQuery) -> Vec<LogEvent> {
+    search::search_events(buffer.snapshot(None), &query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventKind;
+
+    fn event(timestamp_ms: i64, kind: EventKind) -> LogEvent {
+        LogEvent::test(kind).at(timestamp_ms)
+    }
+
+    #[test]
+    fn keeps_only_the_newest_events() {
+        let buffer = EventBuffer::default();
+        let events: Vec<_> = (0..MAX_EVENTS as i64 + 10)
+            .map(|i| event(i, EventKind::SystemQuit))
+            .collect();
+        buffer.push_batch(&events);
+
+        let snapshot = buffer.snapshot(None);
+        assert_eq!(snapshot.len(), MAX_EVENTS);
+        assert_eq!(snapshot[0].timestamp_ms, Some(10));
+    }
+
+    #[test]
+    fn merging_history_skips_events_already_buffered() {
+        let location = |timestamp_ms, location: &str| {
+            event(
+                timestamp_ms,
+                EventKind::LocationChange {
+                    player: "Pilot".into(),
+                    location: location.into(),
+                },
+            )
+        };
+        let buffer = EventBuffer::default();
+        buffer.push_batch(&[location(20, "Orison"), location(30, "Lorville")]);
+
+        let added = buffer.merge_history(vec![
+            location(10, "Area18"),
+            // Already live
+            location(20, "Orison"),
+            location(25, "New Babbage"),
+            location(25, "New Babbage"),
+        ]);
+        assert_eq!(added, 2);
+
+        let times: Vec<_> = buffer
+            .snapshot(None)
+            .iter()
+            .map(|event| event.timestamp_ms.unwrap())
+            .collect();
+        assert_eq!(times, [10, 20, 25, 30]);
+    }
+
+    #[test]
+    fn snapshot_filters_by_kind() {
+        let buffer = EventBuffer::default();
+        buffer.push_batch(&[
+            event(1, EventKind::SystemQuit),
+            event(
+                2,
+                EventKind::LocationChange {
+                    player: "Pilot".into(),
+                    location: "Area18".into(),
+                },
+            ),
+        ]);
+
+        let quits = buffer.snapshot(Some(&["system_quit".to_string()]));
+        assert_eq!(quits.len(), 1);
+        assert_eq!(quits[0].timestamp_ms, Some(1));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::event::{EventKind, LogEvent};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// A run of combat events each within `window_ms` of the one before it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Burst {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub event_count: usize,
+    /// Everyone named as a victim, killer, driver or cause, sorted.
+    pub participants: Vec<String>,
+}
+
+fn combat_participants(kind: &EventKind) -> Option<Vec<&str>> {
+    match kind {
+        EventKind::ActorDeath { victim, killer, .. } => Some(vec![victim, killer]),
+        EventKind::VehicleDestruction { driver, cause, .. } => {
+            Some(driver.iter().chain(cause).map(String::as_str).collect())
+        }
+        _ => None,
+    }
+}
+
+struct Open<'a> {
+    start_ms: i64,
+    end_ms: i64,
+    event_count: usize,
+    participants: BTreeSet<&'a str>,
+}
+
+impl Open<'_> {
+    fn close(self, min_events: usize, bursts: &mut Vec<Burst>) {
+        if self.event_count >= min_events {
+            bursts.push(Burst {
+                start_ms: self.start_ms,
+                end_ms: self.end_ms,
+                event_count: self.event_count,
+                participants: self.participants.into_iter().map(String::from).collect(),
+            });
+        }
+    }
+}
+
+/// Group combat events into bursts. Events without a timestamp can't be placed
+/// on the timeline and are skipped. The input doesn't need to be sorted.
+pub fn detect_bursts(events: &[LogEvent], window_ms: u64, min_events: usize) -> Vec<Burst> {
+    let mut combat: Vec<(i64, Vec<&str>)> = events
+        .iter()
+        .filter_map(|event| Some((event.timestamp_ms?, combat_participants(&event.kind)?)))
+        .collect();
+    combat.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let mut bursts = Vec::new();
+    let mut open: Option<Open> = None;
+
+    for (timestamp, participants) in combat {
+        let participants = participants.into_iter().filter(|name| *name != "unknown");
+        match &mut open {
+            Some(burst) if timestamp.abs_diff(burst.end_ms) <= window_ms => {
+                burst.end_ms = timestamp;
+                burst.event_count += 1;
+                burst.participants.extend(participants);
+            }
+            _ => {
+                if let Some(finished) = open.take() {
+                    finished.close(min_events, &mut bursts);
+                }
+                open = Some(Open {
+                    start_ms: timestamp,
+                    end_ms: timestamp,
+                    event_count: 1,
+                    participants: participants.collect(),
+                });
+            }
+        }
+    }
+    if let Some(finished) = open {
+        finished.close(min_events, &mut bursts);
+    }
+
+    bursts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TestDeath;
+
+    fn kill(timestamp_ms: Option<i64>, victim: &str, killer: &str) -> LogEvent {
+        LogEvent::test(TestDeath::new(victim, killer).into()).at(timestamp_ms)
+    }
+
+    #[test]
+    fn gap_equal_to_the_window_stays_in_the_burst() {
+        let events = vec![
+            kill(Some(1_000), "A", "B"),
+            kill(Some(2_000), "C", "B"),
+            kill(Some(3_001), "D", "E"),
+        ];
+
+        let bursts = detect_bursts(&events, 1_000, 2);
+        assert_eq!(
+            bursts,
+            vec![Burst {
+                start_ms: 1_000,
+                end_ms: 2_000,
+                event_count: 2,
+                participants: vec!["A".into(), "B".into(), "C".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn bursts_below_min_events_and_untimed_events_are_dropped() {
+        let events = vec![
+            kill(Some(5_000), "A", "B"),
+            kill(None, "C", "D"),
+            kill(Some(1_000), "E", "unknown"),
+            kill(Some(1_500), "F", "G"),
+        ];
+
+        let bursts = detect_bursts(&events, 1_000, 2);
+        assert_eq!(bursts.len(), 1);
+        assert_eq!(bursts[0].start_ms, 1_000);
+        assert_eq!(bursts[0].participants, vec!["E", "F", "G"]);
+
+        assert_eq!(detect_bursts(&events, 1_000, 1).len(), 2);
+        assert!(detect_bursts(&[], 1_000, 1).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use super::{parse_catalog, CatalogUpdateResult};
+use crate::buffer;
+use crate::settings;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_http::reqwest;
+
+const OVERRIDES_KEY: &str = "catalogOverrides";
+
+pub const CATALOG_UPDATED_EVENT: &str = "catalog-updated";
+/// The last downloaded catalog, in the app data dir, loaded on startup.
+const CACHE_FILE: &str = "catalog.json";
+/// Refuse catalogs bigger than this; the bundled one is well under 1 MB.
+const MAX_CATALOG_BYTES: usize = 16 * 1024 * 1024;
+
+fn cache_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(CACHE_FILE))
+}
+
+/// Load the user's overrides and the last downloaded catalog, if it's newer
+/// than the bundled one. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let overrides: BTreeMap<String, String> = settings::get(app, OVERRIDES_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    super::set_overrides(overrides);
+
+    let Ok(path) = cache_path(app) else {
+        return;
+    };
+    let Ok(bytes) = fs::read(&path) else {
+        return;
+    };
+    match parse_catalog(&bytes, super::version()) {
+        Ok(file) => {
+            let result = super::install(file);
+            tracing::info!("Loaded cached catalog version {}", result.version);
+        }
+        Err(e) => tracing::warn!("Ignoring cached catalog {}: {}", path.display(), e),
+    }
+}
+
+/// Download a catalog (`{"version": n, "entries": {...}}`) and swap it in if
+/// it's valid and newer than the one in use, caching it for the next launch.
+/// Emits `catalog-updated`; buffered events keep their old names until
+/// they're re-resolved.
+#[tauri::command]
+pub async fn update_catalog(app: AppHandle, url: String) -> Result<CatalogUpdateResult, String> {
+    let mut response = reqwest::get(&url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        if bytes.len() + chunk.len() > MAX_CATALOG_BYTES {
+            return Err(format!(
+                "Catalog is larger than {} bytes",
+                MAX_CATALOG_BYTES
+            ));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let file = parse_catalog(&bytes, super::version())?;
+    let path = cache_path(&app)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    fs::write(&path, &bytes).map_err(|e| e.to_string())?;
+
+    let result = super::install(file);
+    tracing::info!(
+        "Catalog updated from {} to version {}",
+        result.previous_version,
+        result.version
+    );
+
+    if let Err(e) = app.emit(CATALOG_UPDATED_EVENT, &result) {
+        tracing::warn!("Failed to emit {}: {}", CATALOG_UPDATED_EVENT, e);
+    }
+    Ok(result)
+}
+
+/// Persist the overrides and re-resolve the buffered feed with them.
+fn save_overrides(app: &AppHandle) -> Result<(), String> {
+    settings::set(app, OVERRIDES_KEY, &super::overrides())?;
+    buffer::refresh_names(app)
+}
+
+#[tauri::command]
+pub fn get_catalog_overrides() -> BTreeMap<String, String> {
+    super::overrides()
+}
+
+/// Name an entity or weapon class, e.g. `AEGS_Gladius`, ahead of the bundled
+/// catalog. Ids and suffixes are trimmed off when matching, as for the catalog.
+#[tauri::command]
+pub fn set_catalog_override(app: AppHandle, raw: String, friendly: String) -> Result<(), String> {
+    super::set_override(&raw, &friendly)?;
+    save_overrides(&app)
+}
+
+/// Returns whether there was an override for `raw`.
+#[tauri::command]
+pub fn clear_catalog_override(app: AppHandle, raw: String) -> Result<bool, String> {
+    if !super::clear_override(&raw)? {
+        return Ok(false);
+    }
+    save_overrides(&app)?;
+    Ok(true)
+}
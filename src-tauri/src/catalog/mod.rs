@@ -0,0 +1,296 @@
+//! Friendly names for vehicle and weapon identifiers. Nothing here needs the
+//! app running; loading and saving catalogs and overrides is left to
+//! `commands`.
+//!
+//! ```
+//! use picologs_lib::catalog;
+//!
+//! assert_eq!(
+//!     catalog::vehicle_name("RSI_Aurora_ES_2729096379834").as_deref(),
+//!     Some("Aurora ES")
+//! );
+//! ```
+
+#[cfg(feature = "app")]
+pub(crate) mod commands;
+
+use crate::event::{EventKind, LogEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{LazyLock, RwLock};
+
+/// The same ship catalog the frontend renders vehicle cards from, keyed by the
+/// lowercased entity class (e.g. `aegs_gladius`).
+static FLEET_JSON: &str = include_str!("../../../src/libs/fleet.json");
+
+#[derive(Deserialize)]
+struct FleetEntry {
+    name: String,
+}
+
+/// A downloadable catalog: a version that only goes up, and the same entries
+/// as the bundled `fleet.json`.
+#[derive(Deserialize)]
+pub struct CatalogFile {
+    version: u64,
+    entries: HashMap<String, FleetEntry>,
+}
+
+/// Names by lowercased class, and the version they came from. The bundled
+/// catalog is version 0.
+#[derive(Default)]
+struct Catalog {
+    version: u64,
+    names: HashMap<String, String>,
+}
+
+impl Catalog {
+    fn from_entries(version: u64, entries: HashMap<String, FleetEntry>) -> Self {
+        Catalog {
+            version,
+            names: entries
+                .into_iter()
+                .map(|(class, entry)| (class.to_lowercase(), entry.name))
+                .collect(),
+        }
+    }
+}
+
+static VEHICLE_NAMES: LazyLock<RwLock<Catalog>> = LazyLock::new(|| {
+    let catalog = match serde_json::from_str::<HashMap<String, FleetEntry>>(FLEET_JSON) {
+        Ok(fleet) => Catalog::from_entries(0, fleet),
+        Err(e) => {
+            tracing::warn!("Failed to load the vehicle catalog: {}", e);
+            Catalog::default()
+        }
+    };
+    RwLock::new(catalog)
+});
+
+/// Names the user has set, keyed by lowercased identifier. Looked up before the
+/// bundled catalog at every step of the lookup.
+static OVERRIDES: LazyLock<RwLock<BTreeMap<String, String>>> = LazyLock::new(Default::default);
+
+/// Friendly name for a vehicle entity such as `AEGS_Gladius_12345`. The
+/// numeric entity id is dropped, then trailing parts are trimmed until the
+/// class matches a catalog entry, the same way the vehicle cards look it up.
+pub fn vehicle_name(raw: &str) -> Option<String> {
+    let mut parts: Vec<&str> = raw.split('_').collect();
+    if parts.len() > 1 && parts.last().is_some_and(|p| p.parse::<u64>().is_ok()) {
+        parts.pop();
+    }
+
+    let overrides = OVERRIDES.read().unwrap();
+    let catalog = VEHICLE_NAMES.read().unwrap();
+    while !parts.is_empty() {
+        let class = parts.join("_").to_lowercase();
+        if let Some(name) = overrides.get(&class).or_else(|| catalog.names.get(&class)) {
+            return Some(name.clone());
+        }
+        parts.pop();
+    }
+    None
+}
+
+/// Friendly name for the `using '...'` part of a kill. There is no bundled
+/// catalog of personal weapons yet, so this only resolves ships and vehicles
+/// (rammings and ship-mounted kills report the vehicle entity here) and any
+/// weapon the user has named with an override.
+pub fn weapon_name(raw: &str) -> Option<String> {
+    if raw == "unknown" {
+        return None;
+    }
+    vehicle_name(raw)
+}
+
+/// Refresh the friendly-name fields of an event from its raw identifiers.
+pub fn resolve_names(kind: &mut EventKind) {
+    match kind {
+        EventKind::ActorDeath {
+            weapon: Some(weapon),
+            weapon_name,
+            ..
+        } => *weapon_name = self::weapon_name(weapon),
+        EventKind::VehicleDestruction {
+            vehicle,
+            vehicle_name,
+            ..
+        }
+        | EventKind::VehicleControl {
+            vehicle,
+            vehicle_name,
+            ..
+        } => *vehicle_name = self::vehicle_name(vehicle),
+        EventKind::LoadoutChange {
+            item, item_name, ..
+        } => *item_name = self::weapon_name(item),
+        _ => {}
+    }
+}
+
+/// Re-run the resolvers over already parsed events, e.g. after a catalog
+/// update. Events without the raw identifier to resolve from are left as they
+/// are.
+pub fn reresolve_names(mut events: Vec<LogEvent>) -> Vec<LogEvent> {
+    for event in &mut events {
+        resolve_names(&mut event.kind);
+    }
+    events
+}
+
+/// Trim and lowercase an override key, the way lookups normalize classes.
+fn override_key(raw: &str) -> Result<String, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err("The raw name is empty".to_string());
+    }
+    Ok(raw.to_lowercase())
+}
+
+/// Parse and check a downloaded catalog: it must be newer than `current` and
+/// every entry needs a name.
+pub fn parse_catalog(bytes: &[u8], current: u64) -> Result<CatalogFile, String> {
+    let file: CatalogFile =
+        serde_json::from_slice(bytes).map_err(|e| format!("Malformed catalog: {}", e))?;
+    if file.version <= current {
+        return Err(format!(
+            "Catalog version {} is not newer than {}",
+            file.version, current
+        ));
+    }
+    if file.entries.is_empty() {
+        return Err("Catalog has no entries".to_string());
+    }
+    if let Some(class) = file
+        .entries
+        .iter()
+        .find(|(class, entry)| class.trim().is_empty() || entry.name.trim().is_empty())
+        .map(|(class, _)| class)
+    {
+        return Err(format!("Catalog entry {:?} has no name", class));
+    }
+    Ok(file)
+}
+
+/// The version of the catalog in use; 0 for the bundled one.
+pub fn version() -> u64 {
+    VEHICLE_NAMES.read().unwrap().version
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogUpdateResult {
+    pub version: u64,
+    pub previous_version: u64,
+    pub entries: usize,
+}
+
+/// Swap in a catalog checked by `parse_catalog`.
+pub fn install(file: CatalogFile) -> CatalogUpdateResult {
+    let catalog = Catalog::from_entries(file.version, file.entries);
+    let mut current = VEHICLE_NAMES.write().unwrap();
+    let result = CatalogUpdateResult {
+        version: catalog.version,
+        previous_version: current.version,
+        entries: catalog.names.len(),
+    };
+    *current = catalog;
+    result
+}
+
+pub fn overrides() -> BTreeMap<String, String> {
+    OVERRIDES.read().unwrap().clone()
+}
+
+/// Replace every override, e.g. with the ones saved last session.
+pub fn set_overrides(overrides: BTreeMap<String, String>) {
+    *OVERRIDES.write().unwrap() = overrides;
+}
+
+/// Name an entity or weapon class ahead of the bundled catalog.
+pub fn set_override(raw: &str, friendly: &str) -> Result<(), String> {
+    let key = override_key(raw)?;
+    let friendly = friendly.trim();
+    if friendly.is_empty() {
+        return Err(format!("No name given for {}", raw.trim()));
+    }
+    OVERRIDES.write().unwrap().insert(key, friendly.to_string());
+    Ok(())
+}
+
+/// Returns whether there was an override for `raw`.
+pub fn clear_override(raw: &str) -> Result<bool, String> {
+    let key = override_key(raw)?;
+    Ok(OVERRIDES.write().unwrap().remove(&key).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_vehicle_entities_with_and_without_ids() {
+        assert_eq!(
+            vehicle_name("RSI_Aurora_ES_2729096379834").as_deref(),
+            Some("Aurora ES")
+        );
+        assert_eq!(vehicle_name("rsi_aurora_es").as_deref(), Some("Aurora ES"));
+        assert_eq!(vehicle_name("Not_A_Ship_123"), None);
+        assert_eq!(weapon_name("unknown"), None);
+    }
+
+    #[test]
+    fn rejects_malformed_and_older_catalogs() {
+        let catalog = br#"{"version": 3, "entries": {"AEGS_Gladius": {"name": "Gladius"}}}"#;
+        let file = parse_catalog(catalog, 2).unwrap();
+        assert_eq!(file.version, 3);
+        let names = Catalog::from_entries(file.version, file.entries).names;
+        assert_eq!(
+            names.get("aegs_gladius").map(String::as_str),
+            Some("Gladius")
+        );
+
+        assert!(parse_catalog(catalog, 3).is_err());
+        assert!(parse_catalog(b"{\"entries\": {}}", 0).is_err());
+        assert!(parse_catalog(br#"{"version": 1, "entries": {}}"#, 0).is_err());
+        assert!(parse_catalog(br#"{"version": 1, "entries": {"x": {"name": " "}}}"#, 0).is_err());
+    }
+
+    #[test]
+    fn overrides_come_before_the_catalog() {
+        let key = override_key("  RSI_Aurora_MR ").unwrap();
+        assert_eq!(key, "rsi_aurora_mr");
+        assert!(override_key("   ").is_err());
+
+        OVERRIDES
+            .write()
+            .unwrap()
+            .insert(key.clone(), "Aurora Mk I MR".into());
+        assert_eq!(
+            vehicle_name("RSI_Aurora_MR_2729096379834").as_deref(),
+            Some("Aurora Mk I MR")
+        );
+        OVERRIDES.write().unwrap().remove(&key);
+    }
+
+    #[test]
+    fn reresolving_refreshes_stale_names_but_keeps_raw_ids() {
+        let event = LogEvent::test(EventKind::VehicleControl {
+            vehicle: "RSI_Aurora_ES_123".into(),
+            vehicle_id: Some("123".into()),
+            vehicle_name: Some("Old Name".into()),
+        });
+
+        let refreshed = reresolve_names(vec![event]);
+        match &refreshed[0].kind {
+            EventKind::VehicleControl {
+                vehicle,
+                vehicle_name,
+                ..
+            } => {
+                assert_eq!(vehicle, "RSI_Aurora_ES_123");
+                assert_eq!(vehicle_name.as_deref(), Some("Aurora ES"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::event::EventKind;
+use serde::{Deserialize, Serialize};
+
+/// What a death was down to. Kills the log doesn't attribute to anyone are
+/// `Unknown` rather than being counted as PvP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CauseCategory {
+    Pvp,
+    Pve,
+    Environmental,
+    /// The victim is named as their own killer and the damage type doesn't
+    /// say the world did it.
+    Suicide,
+    Unknown,
+}
+
+/// Damage types (lowercased) that mean the world did it, not whoever the log
+/// names as the killer.
+const ENVIRONMENTAL_DAMAGE: [&str; 10] = [
+    "crash",
+    "collision",
+    "fall",
+    "falldamage",
+    "suffocation",
+    "drown",
+    "hazard",
+    "temperature",
+    "radiation",
+    "decompression",
+];
+
+/// Archetype prefixes the game names its AI with, as written in the log.
+const NPC_PREFIXES: [&str; 2] = ["PU_", "NPC_"];
+
+/// Entity ids (GEIDs) run to 12 digits and more; shorter number suffixes are
+/// common in handles.
+const MIN_GEID_DIGITS: usize = 12;
+
+/// Whether a killer name is one of the game's AI rather than a player handle.
+/// NPCs are named after their archetype (`PU_Human_Enemy_...`, `NPC_...`) or
+/// carry their entity id after an underscore; handles do neither.
+fn is_npc(name: &str) -> bool {
+    if NPC_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) {
+        return true;
+    }
+    name.rsplit_once('_').is_some_and(|(_, id)| {
+        id.len() >= MIN_GEID_DIGITS && id.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+/// Classify a death from its killer and damage type.
+pub fn classify_cause(victim: &str, killer: &str, damage_type: Option<&str>) -> CauseCategory {
+    let damage_type = damage_type.map(str::to_lowercase);
+    if damage_type
+        .as_deref()
+        .is_some_and(|damage_type| ENVIRONMENTAL_DAMAGE.contains(&damage_type))
+    {
+        return CauseCategory::Environmental;
+    }
+    if killer.is_empty() || killer.eq_ignore_ascii_case("unknown") {
+        return CauseCategory::Unknown;
+    }
+    if killer == victim {
+        return CauseCategory::Suicide;
+    }
+    if is_npc(killer) {
+        return CauseCategory::Pve;
+    }
+    CauseCategory::Pvp
+}
+
+/// Fill in `cause_category` on a death from its other fields.
+pub fn resolve_cause(kind: &mut EventKind) {
+    if let EventKind::ActorDeath {
+        victim,
+        killer,
+        damage_type,
+        cause_category,
+        ..
+    } = kind
+    {
+        *cause_category = Some(classify_cause(victim, killer, damage_type.as_deref()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_line;
+
+    fn cause_of(line: &str) -> Option<CauseCategory> {
+        match parse_line(line, false)?.kind {
+            EventKind::ActorDeath { cause_category, .. } => cause_category,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn classifies_deaths_from_log_lines() {
+        let fall = "<2024-06-07T12:34:56.789Z> [Notice] <Actor Death> CActor::Kill: 'Pilot' [200000000001] in zone 'Stanton_Crusader' killed by 'Pilot' [200000000001] using 'unknown' [Class unknown] with damage type 'Fall' from direction x: 0, y: 0, z: -1";
+        let suffocation = "<2024-06-07T12:34:56.789Z> [Notice] <Actor Death> CActor::Kill: 'Pilot' [200000000001] in zone 'OOC_Stanton_1_Hurston' killed by 'unknown' [0] using 'unknown' [Class unknown] with damage type 'Suffocation' from direction x: 0, y: 0, z: 0";
+        let npc = "<2024-06-07T12:34:56.789Z> [Notice] <Actor Death> CActor::Kill: 'Pilot' [200000000001] in zone 'Stanton_Crusader' killed by 'PU_Human_Enemy_GroundCombat_NPC_Pirate_Light_2729096379834' [2729096379834] using 'behr_rifle_ballistic_01_123' [Class behr_rifle_ballistic_01] with damage type 'Bullet' from direction x: 1, y: 0, z: 0";
+        let player = "<2024-06-07T12:34:56.789Z> [Notice] <Actor Death> CActor::Kill: 'Pilot' [200000000001] in zone 'Stanton_Crusader' killed by 'Enemy-Player' [200000000002] using 'behr_rifle_ballistic_01_123' [Class behr_rifle_ballistic_01] with damage type 'Bullet' from direction x: 1, y: 0, z: 0";
+        let unknown = "<2024-06-07T12:34:56.789Z> [Notice] <Actor Death> CActor::Kill: 'Pilot' [200000000001] in zone 'Stanton_Crusader' killed by 'unknown' [0] using 'unknown' [Class unknown] with damage type 'Bullet' from direction x: 1, y: 0, z: 0";
+
+        assert_eq!(cause_of(fall), Some(CauseCategory::Environmental));
+        assert_eq!(cause_of(suffocation), Some(CauseCategory::Environmental));
+        assert_eq!(cause_of(npc), Some(CauseCategory::Pve));
+        assert_eq!(cause_of(player), Some(CauseCategory::Pvp));
+        assert_eq!(cause_of(unknown), Some(CauseCategory::Unknown));
+    }
+
+    #[test]
+    fn self_kills_without_a_world_cause_are_suicides() {
+        assert_eq!(
+            classify_cause("Pilot", "Pilot", Some("Bullet")),
+            CauseCategory::Suicide
+        );
+        assert_eq!(
+            classify_cause("Pilot", "Pilot", None),
+            CauseCategory::Suicide
+        );
+        assert_eq!(
+            classify_cause("Pilot", "Pilot", Some("Collision")),
+            CauseCategory::Environmental
+        );
+    }
+
+    #[test]
+    fn only_archetype_names_and_entity_ids_count_as_npcs() {
+        for npc in [
+            "PU_Human_Enemy_GroundCombat_NPC_Pirate_Light_2729096379834",
+            "NPC_Security_Guard",
+            "Vanduul_Scythe_200000000123",
+        ] {
+            assert!(is_npc(npc), "{}", npc);
+        }
+        for handle in [
+            "Snpc_Hunter",
+            "GoodNPCGuy",
+            "Pilot_123456",
+            "npc_fan",
+            "Pu_Erh",
+        ] {
+            assert!(!is_npc(handle), "{}", handle);
+        }
+    }
+}
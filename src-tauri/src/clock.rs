@@ -0,0 +1,241 @@
+use crate::event::LogEvent;
+use crate::settings;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const CLOCK_SKEW_WARNING_EVENT: &str = "clock-skew-warning";
+
+const CLOCK_OFFSET_KEY: &str = "clockOffsetMs";
+
+/// How many live events the estimate is taken over, and how many it needs
+/// before it says anything.
+const MAX_SAMPLES: usize = 50;
+const MIN_SAMPLES: usize = 20;
+
+/// Live events are read within a few seconds of being written, so anything
+/// further out than this is the clock, not the polling.
+const SKEW_THRESHOLD_MS: i64 = 2 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClockSkewWarning {
+    /// How far ahead of the system clock event timestamps are; negative when
+    /// they're behind it.
+    pub skew_ms: i64,
+    /// Events the estimate was taken over.
+    pub samples: usize,
+    /// The `set_clock_offset` that would line them up.
+    pub suggested_offset_ms: i64,
+}
+
+/// Compares live events' timestamps with when they were read.
+#[derive(Debug, Default)]
+struct SkewMonitor {
+    /// Timestamp minus the time it was read, newest last.
+    differences: VecDeque<i64>,
+    /// The skew last warned about, so one skew is warned about once.
+    warned: Option<i64>,
+}
+
+impl SkewMonitor {
+    fn observe(&mut self, timestamp_ms: i64, read_at_ms: i64) {
+        if self.differences.len() == MAX_SAMPLES {
+            self.differences.pop_front();
+        }
+        self.differences.push_back(timestamp_ms - read_at_ms);
+    }
+
+    /// The median difference, once enough samples are in and most of them
+    /// are off by more than the threshold in the same direction.
+    fn skew(&self) -> Option<i64> {
+        if self.differences.len() < MIN_SAMPLES {
+            return None;
+        }
+        let ahead = self
+            .differences
+            .iter()
+            .filter(|&&d| d > SKEW_THRESHOLD_MS)
+            .count();
+        let behind = self
+            .differences
+            .iter()
+            .filter(|&&d| d < -SKEW_THRESHOLD_MS)
+            .count();
+        if ahead.max(behind) * 2 <= self.differences.len() {
+            return None;
+        }
+        let mut sorted: Vec<i64> = self.differences.iter().copied().collect();
+        sorted.sort_unstable();
+        Some(sorted[sorted.len() / 2])
+    }
+
+    /// The warning to send, if the clock is off and it hasn't already been
+    /// warned about.
+    fn warning(&mut self) -> Option<ClockSkewWarning> {
+        let skew = self.skew()?;
+        if self
+            .warned
+            .is_some_and(|warned| (warned - skew).abs() < SKEW_THRESHOLD_MS)
+        {
+            return None;
+        }
+        self.warned = Some(skew);
+        Some(ClockSkewWarning {
+            skew_ms: skew,
+            samples: self.differences.len(),
+            suggested_offset_ms: -skew,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct ClockState {
+    /// Added to event timestamps when they're shown or exported. Stored and
+    /// shared events keep the timestamp the log wrote.
+    offset_ms: AtomicI64,
+    monitor: Mutex<SkewMonitor>,
+}
+
+/// Load the persisted correction. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    if let Ok(Some(offset)) = settings::get::<i64>(app, CLOCK_OFFSET_KEY) {
+        app.state::<ClockState>()
+            .offset_ms
+            .store(offset, Ordering::Relaxed);
+    }
+}
+
+/// Check freshly tailed events against the system clock, emitting
+/// `clock-skew-warning` when most of the recent ones are far off it. Only
+/// live events say anything about the clock; a backfill is meant to be old.
+pub fn observe_live(app: &AppHandle, events: &[LogEvent]) {
+    let read_at = chrono::Utc::now().timestamp_millis();
+    let warning = {
+        let state = app.state::<ClockState>();
+        let mut monitor = state.monitor.lock().unwrap();
+        for timestamp_ms in events.iter().filter_map(|event| event.timestamp_ms) {
+            monitor.observe(timestamp_ms, read_at);
+        }
+        monitor.warning()
+    };
+    if let Some(warning) = warning {
+        tracing::warn!(
+            "Log timestamps are {} ms off the system clock",
+            warning.skew_ms
+        );
+        if let Err(e) = app.emit(CLOCK_SKEW_WARNING_EVENT, warning) {
+            tracing::warn!("Failed to emit {}: {}", CLOCK_SKEW_WARNING_EVENT, e);
+        }
+    }
+}
+
+/// Shift local event timestamps by the configured correction, if there is
+/// one; a room member's events come from their clock, not this one. Only for
+/// copies about to be displayed or exported: the buffer, archives,
+/// bookmarks and the room all keep raw UTC, so fingerprints keep matching.
+pub fn correct(app: &AppHandle, events: &mut [LogEvent]) {
+    shift(events, offset_ms(app));
+}
+
+/// The configured correction in milliseconds, 0 when there is none.
+pub fn offset_ms(app: &AppHandle) -> i64 {
+    app.state::<ClockState>().offset_ms.load(Ordering::Relaxed)
+}
+
+fn shift(events: &mut [LogEvent], offset_ms: i64) {
+    if offset_ms == 0 {
+        return;
+    }
+    for event in events.iter_mut().filter(|event| event.reporter.is_none()) {
+        if let Some(timestamp_ms) = event.timestamp_ms.as_mut() {
+            *timestamp_ms += offset_ms;
+        }
+    }
+}
+
+/// Shift displayed and exported timestamps by `ms`, e.g. the
+/// `suggested_offset_ms` of a `clock-skew-warning`. Stored events aren't
+/// touched, so changing it applies to everything already parsed. 0 turns the
+/// correction off.
+#[tauri::command]
+pub fn set_clock_offset(
+    app: AppHandle,
+    state: State<'_, ClockState>,
+    ms: i64,
+) -> Result<(), String> {
+    settings::set(&app, CLOCK_OFFSET_KEY, &ms)?;
+    state.offset_ms.store(ms, Ordering::Relaxed);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_clock_offset(state: State<'_, ClockState>) -> i64 {
+    state.offset_ms.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_717_763_696_789;
+
+    fn monitor_with(offsets: impl IntoIterator<Item = i64>) -> SkewMonitor {
+        let mut monitor = SkewMonitor::default();
+        for (i, offset) in offsets.into_iter().enumerate() {
+            let read_at = NOW + i as i64 * 1000;
+            // Tailed events are read shortly after they're written
+            monitor.observe(read_at + offset - 500, read_at);
+        }
+        monitor
+    }
+
+    #[test]
+    fn a_clock_an_hour_ahead_is_warned_about_once() {
+        let hour = 60 * 60 * 1000;
+        let mut monitor = monitor_with(std::iter::repeat_n(hour, MIN_SAMPLES - 1));
+        assert_eq!(monitor.warning(), None);
+
+        monitor.observe(NOW + hour, NOW);
+        let warning = monitor.warning().unwrap();
+        assert_eq!(warning.skew_ms, hour - 500);
+        assert_eq!(warning.suggested_offset_ms, 500 - hour);
+        assert_eq!(warning.samples, MIN_SAMPLES);
+        assert_eq!(monitor.warning(), None);
+    }
+
+    #[test]
+    fn a_clock_behind_is_warned_about_too() {
+        let mut monitor = monitor_with(std::iter::repeat_n(-10 * 60 * 1000, MIN_SAMPLES));
+        assert!(monitor.warning().unwrap().skew_ms < -SKEW_THRESHOLD_MS);
+    }
+
+    #[test]
+    fn shifting_leaves_untimed_and_shared_events_alone() {
+        let event =
+            |timestamp_ms| LogEvent::test(crate::event::EventKind::SystemQuit).at(timestamp_ms);
+        let mut shared = event(Some(NOW));
+        shared.reporter = Some("Wingman".into());
+        let mut events = [event(Some(NOW)), event(None), shared];
+        shift(&mut events, 0);
+        assert_eq!(events[0].timestamp_ms, Some(NOW));
+
+        shift(&mut events, -1_500);
+        assert_eq!(events[0].timestamp_ms, Some(NOW - 1_500));
+        assert_eq!(events[1].timestamp_ms, None);
+        assert_eq!(events[2].timestamp_ms, Some(NOW));
+    }
+
+    #[test]
+    fn an_accurate_clock_or_a_few_odd_events_arent_skew() {
+        assert_eq!(
+            monitor_with(std::iter::repeat_n(0, MAX_SAMPLES)).warning(),
+            None
+        );
+
+        let hour = 60 * 60 * 1000;
+        let mostly_fine = (0..MAX_SAMPLES as i64).map(|i| if i % 3 == 0 { hour } else { 0 });
+        assert_eq!(monitor_with(mostly_fine).warning(), None);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::cause;
+use crate::event::{event_fingerprint, EventKind, LogEvent};
+
+/// How far apart two reports of the same kill can be stamped. Each client
+/// stamps the line with its own clock, so the same death rarely matches exactly.
+const MATCH_WINDOW_MS: u64 = 2_000;
+
+fn is_known(value: &Option<String>) -> bool {
+    value
+        .as_deref()
+        .is_some_and(|value| !value.is_empty() && !value.eq_ignore_ascii_case("unknown"))
+}
+
+/// The first report's value wins unless it's missing or `unknown` and the
+/// second report knows better.
+fn pick(first: &mut Option<String>, second: Option<String>) {
+    if !is_known(first) && (is_known(&second) || first.is_none()) {
+        *first = second;
+    }
+}
+
+fn same_kill(a: &LogEvent, b: &LogEvent) -> bool {
+    let (
+        EventKind::ActorDeath {
+            victim: a_victim,
+            killer: a_killer,
+            ..
+        },
+        EventKind::ActorDeath {
+            victim: b_victim,
+            killer: b_killer,
+            ..
+        },
+    ) = (&a.kind, &b.kind)
+    else {
+        return false;
+    };
+    let (Some(a_ms), Some(b_ms)) = (a.timestamp_ms, b.timestamp_ms) else {
+        return false;
+    };
+    a_victim.eq_ignore_ascii_case(b_victim)
+        && a_killer.eq_ignore_ascii_case(b_killer)
+        && a_ms.abs_diff(b_ms) <= MATCH_WINDOW_MS
+}
+
+fn sources(event: &LogEvent) -> Vec<String> {
+    if event.sources.is_empty() {
+        vec![event_fingerprint(event)]
+    } else {
+        event.sources.clone()
+    }
+}
+
+/// Fold `other` into `merged`, which must be a report of the same kill.
+fn merge(merged: &mut LogEvent, other: LogEvent) {
+    for source in sources(&other) {
+        if !merged.sources.contains(&source) {
+            merged.sources.push(source);
+        }
+    }
+    merged.timestamp_ms = merged.timestamp_ms.min(other.timestamp_ms);
+    merged.severity = merged.severity.max(other.severity);
+
+    let (
+        EventKind::ActorDeath {
+            victim_geid,
+            zone,
+            killer_geid,
+            weapon,
+            weapon_name,
+            weapon_class,
+            damage_type,
+            assists,
+            ..
+        },
+        EventKind::ActorDeath {
+            victim_geid: other_victim_geid,
+            zone: other_zone,
+            killer_geid: other_killer_geid,
+            weapon: other_weapon,
+            weapon_name: other_weapon_name,
+            weapon_class: other_weapon_class,
+            damage_type: other_damage_type,
+            assists: other_assists,
+            ..
+        },
+    ) = (&mut merged.kind, other.kind)
+    else {
+        return;
+    };
+
+    // The catalog name belongs to whichever weapon is kept
+    if !is_known(weapon) && is_known(&other_weapon) {
+        *weapon = other_weapon;
+        *weapon_name = other_weapon_name;
+    } else {
+        pick(weapon, other_weapon);
+        pick(weapon_name, other_weapon_name);
+    }
+    pick(victim_geid, other_victim_geid);
+    pick(zone, other_zone);
+    pick(killer_geid, other_killer_geid);
+    pick(weapon_class, other_weapon_class);
+    pick(damage_type, other_damage_type);
+    for assist in other_assists {
+        if !assists.contains(&assist) {
+            assists.push(assist);
+        }
+    }
+    // Either side may have had the damage type that decides it
+    cause::resolve_cause(&mut merged.kind);
+}
+
+/// Merge reports of the same kill (same victim and killer, stamped within
+/// `MATCH_WINDOW_MS` of each other) into one event carrying every field either
+/// side knew, with `sources` listing the reports that went into it. Everything
+/// else passes through untouched. Merged kills take the place of their first
+/// report and keep its values where both reports know a field.
+pub fn correlate_kills(events: Vec<LogEvent>) -> Vec<LogEvent> {
+    let mut out: Vec<LogEvent> = Vec::with_capacity(events.len());
+    // Indices into `out` of kills that later reports may merge into
+    let mut kills: Vec<usize> = Vec::new();
+
+    for event in events {
+        if !matches!(event.kind, EventKind::ActorDeath { .. }) {
+            out.push(event);
+            continue;
+        }
+        match kills.iter().find(|&&i| same_kill(&out[i], &event)) {
+            Some(&i) => {
+                let merged = &mut out[i];
+                if merged.sources.is_empty() {
+                    merged.sources = sources(merged);
+                }
+                merge(merged, event);
+            }
+            None => {
+                kills.push(out.len());
+                out.push(event);
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TestDeath;
+    use crate::severity::Severity;
+
+    fn kill(timestamp_ms: i64, weapon: Option<&str>, zone: Option<&str>) -> LogEvent {
+        LogEvent::test(
+            TestDeath {
+                zone: zone.map(Into::into),
+                weapon: weapon.map(Into::into),
+                weapon_name: weapon.map(|w| format!("{} name", w)),
+                ..TestDeath::new("Victim", "Killer")
+            }
+            .into(),
+        )
+        .at(timestamp_ms)
+    }
+
+    fn fields(event: &LogEvent) -> (Option<&str>, Option<&str>, Option<&str>) {
+        let EventKind::ActorDeath {
+            weapon,
+            weapon_name,
+            zone,
+            ..
+        } = &event.kind
+        else {
+            panic!("not a kill");
+        };
+        (weapon.as_deref(), weapon_name.as_deref(), zone.as_deref())
+    }
+
+    #[test]
+    fn merges_both_sides_of_a_kill() {
+        let killer_side = kill(1_000, Some("Gun"), None);
+        let mut victim_side = kill(1_800, Some("unknown"), Some("Hangar"));
+        victim_side.severity = Some(Severity::Critical);
+        let expected_sources = vec![
+            event_fingerprint(&killer_side),
+            event_fingerprint(&victim_side),
+        ];
+
+        let merged = correlate_kills(vec![victim_side, killer_side]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(
+            fields(&merged[0]),
+            (Some("Gun"), Some("Gun name"), Some("Hangar"))
+        );
+        assert_eq!(merged[0].timestamp_ms, Some(1_000));
+        assert_eq!(merged[0].severity, Some(Severity::Critical));
+
+        let mut sources = merged[0].sources.clone();
+        sources.sort();
+        let mut expected = expected_sources;
+        expected.sort();
+        assert_eq!(sources, expected);
+    }
+
+    #[test]
+    fn first_report_wins_when_both_know_a_field() {
+        let merged = correlate_kills(vec![
+            kill(1_000, Some("Gun"), Some("Hangar")),
+            kill(1_000, Some("Knife"), Some("Bar")),
+        ]);
+        assert_eq!(
+            fields(&merged[0]),
+            (Some("Gun"), Some("Gun name"), Some("Hangar"))
+        );
+    }
+
+    #[test]
+    fn kills_far_apart_stay_separate() {
+        let events = vec![kill(1_000, None, None), kill(10_000, None, None)];
+        let merged = correlate_kills(events.clone());
+        assert_eq!(merged, events);
+        assert!(merged.iter().all(|event| event.sources.is_empty()));
+    }
+}
@@ -0,0 +1,171 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, State};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+const LOG_FILE: &str = "picologs.log";
+
+/// How many entries the in-app diagnostics panel keeps before dropping the
+/// oldest.
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagEntry {
+    pub timestamp_ms: i64,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+type Ring = Arc<Mutex<VecDeque<DiagEntry>>>;
+
+/// Recent diagnostics, shared between the tracing layer that fills it and the
+/// commands that read it.
+#[derive(Default)]
+pub struct Diagnostics(Ring);
+
+impl Diagnostics {
+    /// How many of the kept entries are at `level`, e.g. `warn`.
+    pub fn count_at(&self, level: &str) -> usize {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| entry.level == level)
+            .count()
+    }
+}
+
+/// Renders an event the way the fmt layer does: the message, then any other
+/// fields as `name=value`.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        match (self.message.is_empty(), self.fields.is_empty()) {
+            (_, true) => self.message,
+            (true, false) => self.fields,
+            (false, false) => format!("{} {}", self.message, self.fields),
+        }
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+            return;
+        }
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={:?}", field.name(), value);
+    }
+}
+
+struct RingLayer(Ring);
+
+impl<S: Subscriber> Layer<S> for RingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let mut ring = self.0.lock().unwrap();
+        if ring.len() == MAX_ENTRIES {
+            ring.pop_front();
+        }
+        ring.push_back(DiagEntry {
+            timestamp_ms: chrono::Utc::now().timestamp_millis(),
+            level: event.metadata().level().as_str().to_lowercase(),
+            target: event.metadata().target().to_string(),
+            message: visitor.finish(),
+        });
+    }
+}
+
+/// Install the global tracing subscriber: stderr, the log file in the app's
+/// log dir, and the in-memory ring behind `get_diagnostics`. Called first
+/// thing in `setup` so everything after it is captured.
+pub fn init(app: &AppHandle) {
+    let ring = app.state::<Diagnostics>().0.clone();
+    let level = if cfg!(debug_assertions) {
+        Level::DEBUG
+    } else {
+        Level::INFO
+    };
+
+    let file = app.path().app_log_dir().ok().and_then(|dir| {
+        fs::create_dir_all(&dir).ok()?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))
+            .ok()
+    });
+    let file_layer = file.map(|file| {
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(Mutex::new(file))
+    });
+
+    let installed = tracing_subscriber::registry()
+        .with(LevelFilter::from_level(level))
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .with(RingLayer(ring))
+        .try_init();
+    if let Err(e) = installed {
+        eprintln!("Failed to install the tracing subscriber: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn get_diagnostics(state: State<'_, Diagnostics>) -> Vec<DiagEntry> {
+    state.0.lock().unwrap().iter().cloned().collect()
+}
+
+#[tauri::command]
+pub fn clear_diagnostics(state: State<'_, Diagnostics>) {
+    state.0.lock().unwrap().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_keeps_the_newest_entries() {
+        let ring = Ring::default();
+        let subscriber = tracing_subscriber::registry().with(RingLayer(ring.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            for i in 0..MAX_ENTRIES + 2 {
+                tracing::warn!(path = "Game.log", "poll {} failed", i);
+            }
+        });
+
+        let ring = ring.lock().unwrap();
+        assert_eq!(ring.len(), MAX_ENTRIES);
+        let newest = ring.back().unwrap();
+        assert_eq!(newest.level, "warn");
+        assert_eq!(
+            newest.message,
+            format!("poll {} failed path=\"Game.log\"", MAX_ENTRIES + 1)
+        );
+        assert_eq!(
+            ring.front().unwrap().message,
+            "poll 2 failed path=\"Game.log\""
+        );
+    }
+}
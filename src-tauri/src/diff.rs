@@ -0,0 +1,77 @@
+use crate::event::{event_fingerprint, LogEvent};
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Debug, Default, Serialize)]
+pub struct EventDiff {
+    pub only_in_a: Vec<LogEvent>,
+    pub only_in_b: Vec<LogEvent>,
+    /// Events present in both inputs, taken from `a` and kept in `a`'s order.
+    pub shared: Vec<LogEvent>,
+}
+
+/// Split two event lists into what each has that the other doesn't, matching
+/// by `event_fingerprint`. Each bucket keeps the order of its source list.
+pub fn diff_events(a: Vec<LogEvent>, b: Vec<LogEvent>) -> EventDiff {
+    let a_prints: HashSet<String> = a.iter().map(event_fingerprint).collect();
+    let b_prints: HashSet<String> = b.iter().map(event_fingerprint).collect();
+    let mut diff = EventDiff::default();
+
+    for event in a {
+        if b_prints.contains(&event_fingerprint(&event)) {
+            diff.shared.push(event);
+        } else {
+            diff.only_in_a.push(event);
+        }
+    }
+
+    diff.only_in_b = b
+        .into_iter()
+        .filter(|event| !a_prints.contains(&event_fingerprint(event)))
+        .collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TestDeath;
+
+    fn kill(timestamp_ms: i64, victim: &str) -> LogEvent {
+        LogEvent::test(
+            TestDeath {
+                damage_type: Some("Ballistic".into()),
+                ..TestDeath::new(victim, "Killer")
+            }
+            .into(),
+        )
+        .at(timestamp_ms)
+    }
+
+    #[test]
+    fn splits_partially_overlapping_inputs() {
+        let a = vec![
+            kill(1, "A1"),
+            kill(2, "Both1"),
+            kill(3, "A2"),
+            kill(4, "Both2"),
+        ];
+        let b = vec![kill(4, "Both2"), kill(5, "B1"), kill(2, "Both1")];
+
+        let diff = diff_events(a, b);
+
+        assert_eq!(diff.only_in_a, vec![kill(1, "A1"), kill(3, "A2")]);
+        assert_eq!(diff.only_in_b, vec![kill(5, "B1")]);
+        assert_eq!(diff.shared, vec![kill(2, "Both1"), kill(4, "Both2")]);
+    }
+
+    #[test]
+    fn same_kill_at_different_times_is_not_shared() {
+        let diff = diff_events(vec![kill(1, "Victim")], vec![kill(2, "Victim")]);
+
+        assert_eq!(diff.only_in_a.len(), 1);
+        assert_eq!(diff.only_in_b.len(), 1);
+        assert!(diff.shared.is_empty());
+    }
+}
@@ -0,0 +1,345 @@
+use crate::cause::CauseCategory;
+use crate::fnv::Fnv1a;
+use crate::severity::Severity;
+use serde::{Deserialize, Serialize};
+
+/// A single parsed Game.log event.
+///
+/// `timestamp_ms` is milliseconds since the Unix epoch (UTC) taken from the
+/// `<...>` prefix of the log line. It is optional because a handful of lines
+/// are written without one. `severity` is filled in when the event is emitted
+/// and doesn't take part in the fingerprint. `sources` is only set on events
+/// merged from several reports, and lists the fingerprints of those reports.
+/// `reporter` is the handle of the room member who forwarded the event, and
+/// is unset on local events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEvent {
+    pub timestamp_ms: Option<i64>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reporter: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventKind {
+    /// The local player's character was loaded (`AccountLoginCharacterStatus_Character`).
+    PlayerIdentified {
+        handle: String,
+        geid: Option<String>,
+    },
+    ActorDeath {
+        victim: String,
+        victim_geid: Option<String>,
+        zone: Option<String>,
+        killer: String,
+        killer_geid: Option<String>,
+        weapon: Option<String>,
+        /// Catalog name for `weapon`, when it is one we know.
+        #[serde(default)]
+        weapon_name: Option<String>,
+        weapon_class: Option<String>,
+        damage_type: Option<String>,
+        /// Whether a player, an NPC or the environment did it; derived from
+        /// `killer` and `damage_type`.
+        #[serde(default)]
+        cause_category: Option<CauseCategory>,
+        /// Other players who hit the victim shortly before the kill. Only
+        /// known with damage ticks turned on; empty otherwise.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        assists: Vec<String>,
+        /// The interior (hangar, station, elevator) the victim was last seen
+        /// entering, which is more precise than `zone`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        interior: Option<String>,
+        /// Experimental: what the victim had equipped, by slot. Only filled in
+        /// when the victim is the local player, as named by an earlier
+        /// `PlayerIdentified` in the same session. It's built from the
+        /// guessed `LoadoutChange` lines, so expect it empty on real logs.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        carrying: Vec<String>,
+    },
+    VehicleDestruction {
+        vehicle: String,
+        vehicle_id: Option<String>,
+        /// Catalog name for `vehicle`, when it is one we know.
+        #[serde(default)]
+        vehicle_name: Option<String>,
+        zone: Option<String>,
+        driver: Option<String>,
+        cause: Option<String>,
+        destroy_level_from: Option<String>,
+        destroy_level_to: Option<String>,
+        damage_type: Option<String>,
+    },
+    VehicleControl {
+        vehicle: String,
+        vehicle_id: Option<String>,
+        #[serde(default)]
+        vehicle_name: Option<String>,
+    },
+    LocationChange {
+        player: String,
+        location: String,
+    },
+    /// Joined a game server (`<Join PU>`). `shard` is the raw identifier,
+    /// e.g. `pub_euw1b_9335932_110`, and `region` the part naming where it runs.
+    ShardConnected {
+        shard: String,
+        region: Option<String>,
+    },
+    SystemQuit,
+    /// Experimental: the game reported a crash or unhandled exception.
+    /// `context` holds the log lines leading up to it, for pasting into a bug
+    /// report. The line it's read from is a guess that no crashed session's
+    /// log has confirmed (see `parser::CRASH`), so this may never be emitted
+    /// and has no dedicated event of its own.
+    GameCrash {
+        summary: String,
+        #[serde(default)]
+        context: Vec<String>,
+    },
+    /// A single hit landing (`[OnHandleHit]`). Fights write these by the
+    /// hundred, so they're only parsed when damage ticks are turned on.
+    /// `target` may be a player or a vehicle.
+    DamageTick {
+        source: String,
+        target: String,
+        /// Experimental: read from a guessed `damage:` field (see
+        /// `parser::DAMAGE_AMOUNT`), so expect `None` on real logs.
+        amount: Option<f64>,
+        /// Experimental, like `amount`: read from a guessed `damageType:`
+        /// field.
+        damage_type: Option<String>,
+    },
+    /// `player` entered an interior zone (`OnEntityEnterZone`): a hangar,
+    /// a station, an elevator car. `from` is the one they were last seen
+    /// entering, if any. Only emitted when location transitions are turned on.
+    LocationTransition {
+        player: String,
+        from: Option<String>,
+        to: String,
+    },
+    /// Experimental: `player` equipped `item` into `slot`
+    /// (`AttachmentReceived`), e.g. a rifle into `wep_stocked_2`. Only emitted
+    /// when loadout changes are turned on. The line format is a guess (see
+    /// `parser::ATTACHMENT`), so this may never be emitted on real logs.
+    LoadoutChange {
+        player: String,
+        slot: String,
+        item: String,
+        #[serde(default)]
+        item_name: Option<String>,
+    },
+}
+
+/// Every value `EventKind::name` can return, in declaration order.
+pub const KIND_NAMES: [&str; 11] = [
+    "player_identified",
+    "actor_death",
+    "vehicle_destruction",
+    "vehicle_control",
+    "location_change",
+    "shard_connected",
+    "system_quit",
+    "game_crash",
+    "damage_tick",
+    "location_transition",
+    "loadout_change",
+];
+
+impl EventKind {
+    /// The snake_case name used for the serialized `kind` tag and for kind filters.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::PlayerIdentified { .. } => "player_identified",
+            EventKind::ActorDeath { .. } => "actor_death",
+            EventKind::VehicleDestruction { .. } => "vehicle_destruction",
+            EventKind::VehicleControl { .. } => "vehicle_control",
+            EventKind::LocationChange { .. } => "location_change",
+            EventKind::ShardConnected { .. } => "shard_connected",
+            EventKind::SystemQuit => "system_quit",
+            EventKind::GameCrash { .. } => "game_crash",
+            EventKind::DamageTick { .. } => "damage_tick",
+            EventKind::LocationTransition { .. } => "location_transition",
+            EventKind::LoadoutChange { .. } => "loadout_change",
+        }
+    }
+
+    /// Every player the event names, in field order. May repeat a name (e.g.
+    /// a suicide) and may include the game's `unknown` placeholder.
+    pub fn players(&self) -> Vec<&str> {
+        match self {
+            EventKind::PlayerIdentified { handle, .. } => vec![handle],
+            EventKind::ActorDeath { victim, killer, .. } => vec![victim, killer],
+            EventKind::VehicleDestruction { driver, cause, .. } => {
+                driver.iter().chain(cause).map(String::as_str).collect()
+            }
+            EventKind::LocationChange { player, .. } => vec![player],
+            EventKind::DamageTick { source, target, .. } => vec![source, target],
+            EventKind::LocationTransition { player, .. }
+            | EventKind::LoadoutChange { player, .. } => vec![player],
+            _ => Vec::new(),
+        }
+    }
+
+    // Fields that identify "the same event" regardless of which client logged it.
+    fn identity(&self) -> Vec<&str> {
+        match self {
+            EventKind::PlayerIdentified { handle, geid } => {
+                vec![handle, geid.as_deref().unwrap_or_default()]
+            }
+            EventKind::ActorDeath {
+                victim,
+                killer,
+                damage_type,
+                ..
+            } => vec![victim, killer, damage_type.as_deref().unwrap_or_default()],
+            EventKind::VehicleDestruction {
+                vehicle,
+                vehicle_id,
+                destroy_level_to,
+                ..
+            } => vec![
+                vehicle,
+                vehicle_id.as_deref().unwrap_or_default(),
+                destroy_level_to.as_deref().unwrap_or_default(),
+            ],
+            EventKind::VehicleControl {
+                vehicle,
+                vehicle_id,
+                ..
+            } => vec![vehicle, vehicle_id.as_deref().unwrap_or_default()],
+            EventKind::LocationChange { player, location } => vec![player, location],
+            EventKind::ShardConnected { shard, .. } => vec![shard],
+            EventKind::SystemQuit => Vec::new(),
+            EventKind::GameCrash { summary, .. } => vec![summary],
+            EventKind::DamageTick {
+                source,
+                target,
+                damage_type,
+                ..
+            } => vec![source, target, damage_type.as_deref().unwrap_or_default()],
+            EventKind::LocationTransition { player, to, .. } => vec![player, to],
+            EventKind::LoadoutChange {
+                player, slot, item, ..
+            } => vec![player, slot, item],
+        }
+    }
+}
+
+impl LogEvent {
+    pub fn kind_name(&self) -> &'static str {
+        self.kind.name()
+    }
+
+    /// Whether this event passes an optional kind filter. `None` lets
+    /// everything through.
+    pub fn matches_kinds(&self, kinds: Option<&[String]>) -> bool {
+        kinds.is_none_or(|kinds| kinds.iter().any(|kind| kind == self.kind_name()))
+    }
+}
+
+#[cfg(test)]
+impl LogEvent {
+    /// An untimed local event of `kind` with nothing else set, for tests.
+    pub fn test(kind: EventKind) -> Self {
+        LogEvent {
+            timestamp_ms: None,
+            kind,
+            severity: None,
+            sources: Vec::new(),
+            reporter: None,
+        }
+    }
+
+    /// The event stamped at `timestamp_ms`, which may be `None`.
+    pub fn at(self, timestamp_ms: impl Into<Option<i64>>) -> Self {
+        LogEvent {
+            timestamp_ms: timestamp_ms.into(),
+            ..self
+        }
+    }
+}
+
+/// An `ActorDeath`'s fields, so tests can set only the ones they care about:
+/// `TestDeath { zone: Some(..), ..TestDeath::new("Victim", "Killer") }.into()`.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct TestDeath {
+    pub victim: String,
+    pub victim_geid: Option<String>,
+    pub zone: Option<String>,
+    pub killer: String,
+    pub killer_geid: Option<String>,
+    pub weapon: Option<String>,
+    pub weapon_name: Option<String>,
+    pub weapon_class: Option<String>,
+    pub damage_type: Option<String>,
+    pub cause_category: Option<CauseCategory>,
+    pub assists: Vec<String>,
+    pub interior: Option<String>,
+    pub carrying: Vec<String>,
+}
+
+#[cfg(test)]
+impl TestDeath {
+    /// `victim` killed by `killer`, with everything else unknown.
+    pub fn new(victim: &str, killer: &str) -> Self {
+        TestDeath {
+            victim: victim.into(),
+            killer: killer.into(),
+            ..TestDeath::default()
+        }
+    }
+}
+
+#[cfg(test)]
+impl From<TestDeath> for EventKind {
+    fn from(death: TestDeath) -> Self {
+        EventKind::ActorDeath {
+            victim: death.victim,
+            victim_geid: death.victim_geid,
+            zone: death.zone,
+            killer: death.killer,
+            killer_geid: death.killer_geid,
+            weapon: death.weapon,
+            weapon_name: death.weapon_name,
+            weapon_class: death.weapon_class,
+            damage_type: death.damage_type,
+            cause_category: death.cause_category,
+            assists: death.assists,
+            interior: death.interior,
+            carrying: death.carrying,
+        }
+    }
+}
+
+/// Stable identifier for an event, used to dedupe and match events across
+/// sources (e.g. the same kill reported by both the killer's and the victim's log).
+///
+/// ```
+/// use picologs_lib::{event_fingerprint, parse_line};
+///
+/// let quit = "<2024-06-07T12:34:56.789Z> [Notice] <SystemQuit> CSystem::Quit invoked";
+/// let mine = parse_line(quit, false).unwrap();
+/// let mut theirs = mine.clone();
+/// theirs.reporter = Some("Friend".to_string());
+/// assert_eq!(event_fingerprint(&mine), event_fingerprint(&theirs));
+/// ```
+pub fn event_fingerprint(event: &LogEvent) -> String {
+    let mut hasher = Fnv1a::new();
+    hasher.write(event.kind_name().as_bytes());
+    hasher.write(&event.timestamp_ms.unwrap_or_default().to_le_bytes());
+    for part in event.kind.identity() {
+        hasher.write(b"|");
+        hasher.write(part.as_bytes());
+    }
+
+    hasher.finish_hex()
+}
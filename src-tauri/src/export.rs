@@ -0,0 +1,647 @@
+use crate::event::LogEvent;
+use crate::parser::{self, LineParser};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    /// One JSON-serialized `LogEvent` per line.
+    Jsonl,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Every field any event kind can carry, in the order they appear as CSV
+/// columns. Fields a kind doesn't have are left empty.
+pub const CSV_COLUMNS: [&str; 36] = [
+    "timestamp_ms",
+    "kind",
+    "severity",
+    "handle",
+    "geid",
+    "victim",
+    "victim_geid",
+    "zone",
+    "interior",
+    "killer",
+    "killer_geid",
+    "weapon",
+    "weapon_name",
+    "weapon_class",
+    "damage_type",
+    "cause_category",
+    "vehicle",
+    "vehicle_id",
+    "vehicle_name",
+    "driver",
+    "cause",
+    "destroy_level_from",
+    "destroy_level_to",
+    "player",
+    "location",
+    "shard",
+    "region",
+    "summary",
+    "source",
+    "target",
+    "amount",
+    "from",
+    "to",
+    "slot",
+    "item",
+    "item_name",
+];
+
+/// The last CSV column: the clock correction in effect when the file was
+/// written. Timestamps are always exported as the log's own UTC, so a file
+/// imported again keeps its fingerprints; add this to show corrected times.
+pub const CLOCK_OFFSET_COLUMN: &str = "clock_offset_ms";
+
+/// Columns holding numbers rather than text.
+const NUMERIC_COLUMNS: [&str; 2] = ["timestamp_ms", "amount"];
+
+fn csv_field(value: Option<&Value>) -> String {
+    let text = match value {
+        None | Some(Value::Null) => return String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    };
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// Writes events as CSV rows under a fixed `CSV_COLUMNS` header, followed by
+/// `CLOCK_OFFSET_COLUMN`.
+pub struct CsvWriter<W: Write> {
+    out: W,
+    rows: usize,
+    clock_offset_ms: i64,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(mut out: W, clock_offset_ms: i64) -> io::Result<Self> {
+        writeln!(out, "{},{}", CSV_COLUMNS.join(","), CLOCK_OFFSET_COLUMN)?;
+        Ok(CsvWriter {
+            out,
+            rows: 0,
+            clock_offset_ms,
+        })
+    }
+
+    pub fn write(&mut self, event: &LogEvent) -> io::Result<()> {
+        let value = serde_json::to_value(event)?;
+        let row: Vec<String> = CSV_COLUMNS
+            .iter()
+            .map(|column| csv_field(value.get(column)))
+            .collect();
+        writeln!(self.out, "{},{}", row.join(","), self.clock_offset_ms)?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Flush the output and return how many rows were written.
+    pub fn finish(mut self) -> io::Result<usize> {
+        self.out.flush()?;
+        Ok(self.rows)
+    }
+}
+
+/// Write events to a file in `format`. Returns the number of rows. JSONL
+/// rows are the events as they are; only CSV records `clock_offset_ms`.
+fn write_events<'a>(
+    events: impl IntoIterator<Item = &'a LogEvent>,
+    dest: &Path,
+    format: ExportFormat,
+    clock_offset_ms: i64,
+) -> io::Result<usize> {
+    let mut out = BufWriter::new(File::create(dest)?);
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = CsvWriter::new(out, clock_offset_ms)?;
+            for event in events {
+                writer.write(event)?;
+            }
+            writer.finish()
+        }
+        ExportFormat::Jsonl => {
+            let rows = write_jsonl(&mut out, events)?;
+            out.flush()?;
+            Ok(rows)
+        }
+    }
+}
+
+/// Write events as JSON lines. Returns the number written.
+pub fn write_jsonl<'a>(
+    out: &mut impl Write,
+    events: impl IntoIterator<Item = &'a LogEvent>,
+) -> io::Result<usize> {
+    let mut rows = 0;
+    for event in events {
+        serde_json::to_writer(&mut *out, event)?;
+        out.write_all(b"\n")?;
+        rows += 1;
+    }
+    Ok(rows)
+}
+
+/// Split CSV text into records, each with the line it starts on. Quoted
+/// fields may hold commas, doubled quotes and line breaks; a record that
+/// can't be read is an error and the next one starts on the following line.
+fn csv_records(text: &str) -> Vec<(usize, Result<Vec<String>, String>)> {
+    let mut records = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while chars.peek().is_some() {
+        let start = line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut at_field_start = true;
+        let mut error = None;
+
+        loop {
+            match chars.next() {
+                None => break,
+                Some('\n') => {
+                    line += 1;
+                    break;
+                }
+                Some('\r') if chars.peek() == Some(&'\n') => {}
+                Some(',') => {
+                    fields.push(std::mem::take(&mut field));
+                    at_field_start = true;
+                    continue;
+                }
+                Some('"') if at_field_start => {
+                    loop {
+                        match chars.next() {
+                            None => {
+                                error = Some("unterminated quoted field".to_string());
+                                break;
+                            }
+                            Some('"') if chars.peek() == Some(&'"') => {
+                                chars.next();
+                                field.push('"');
+                            }
+                            Some('"') => break,
+                            Some(c) => {
+                                if c == '\n' {
+                                    line += 1;
+                                }
+                                field.push(c);
+                            }
+                        }
+                    }
+                    let ends_field = matches!(chars.peek(), None | Some(',' | '\r' | '\n'));
+                    if error.is_none() && !ends_field {
+                        error = Some(format!(
+                            "text after the closing quote of field {}",
+                            fields.len() + 1
+                        ));
+                    }
+                    if error.is_some() {
+                        // Skip to the next line
+                        for c in chars.by_ref() {
+                            if c == '\n' {
+                                line += 1;
+                                break;
+                            }
+                        }
+                        break;
+                    }
+                }
+                Some(c) => field.push(c),
+            }
+            at_field_start = false;
+        }
+        fields.push(field);
+
+        // Blank lines aren't rows
+        if error.is_none() && fields.len() == 1 && fields[0].is_empty() {
+            continue;
+        }
+        records.push((start, error.map_or(Ok(fields), Err)));
+    }
+    records
+}
+
+/// A CSV row that couldn't be turned back into an event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CsvRowError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CsvImport {
+    pub events: Vec<LogEvent>,
+    pub errors: Vec<CsvRowError>,
+}
+
+/// Rebuild an event from a row's non-empty cells, named by the header.
+fn event_from_row(header: &[String], row: Vec<String>) -> Result<LogEvent, String> {
+    if row.len() != header.len() {
+        return Err(format!(
+            "expected {} fields, found {}",
+            header.len(),
+            row.len()
+        ));
+    }
+    let mut fields = Map::new();
+    for (column, cell) in header.iter().zip(row) {
+        if cell.is_empty() || column == CLOCK_OFFSET_COLUMN {
+            continue;
+        }
+        let value = if NUMERIC_COLUMNS.contains(&column.as_str()) {
+            serde_json::from_str::<serde_json::Number>(&cell)
+                .map(Value::Number)
+                .map_err(|_| format!("{} is not a number: {:?}", column, cell))?
+        } else {
+            Value::String(cell)
+        };
+        fields.insert(column.clone(), value);
+    }
+    serde_json::from_value(Value::Object(fields)).map_err(|e| e.to_string())
+}
+
+/// Read back a CSV written by `export_events_csv` or `export_log_to_csv`.
+/// Columns are matched by name, so files from versions with fewer columns
+/// still load. Rows that can't be read are reported in `errors` and skipped.
+/// Fields that were never exported (assists, loadouts, crash context,
+/// sources) come back empty, and `clock_offset_ms` is ignored so timestamps
+/// stay as the log wrote them.
+pub fn import_events_csv(path: &Path) -> io::Result<CsvImport> {
+    let text = fs::read_to_string(path)?;
+    let mut records = csv_records(&text).into_iter();
+    let header = match records.next() {
+        Some((_, Ok(header))) if header.iter().any(|column| column == "kind") => header,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a picologs CSV export: no kind column",
+            ))
+        }
+    };
+
+    let mut import = CsvImport::default();
+    for (line, record) in records {
+        match record.and_then(|row| event_from_row(&header, row)) {
+            Ok(event) => import.events.push(event),
+            Err(message) => import.errors.push(CsvRowError { line, message }),
+        }
+    }
+    Ok(import)
+}
+
+/// Windows refuses these as file names whatever the extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Turn a handle into something safe to use as a file name on every OS.
+/// Letters and digits in any script are kept; anything else that could be
+/// a separator or is reserved somewhere becomes `_`.
+fn sanitize_file_name(handle: &str) -> String {
+    let mut name: String = handle
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name = name.trim_matches('.').to_string();
+
+    if name.is_empty() {
+        name = "player".to_string();
+    }
+    if RESERVED_NAMES.contains(&name.to_lowercase().as_str()) {
+        name.push('_');
+    }
+    name
+}
+
+/// Pick a file stem for each handle, appending `-2`, `-3`, ... when two handles
+/// sanitize to the same name. Names are compared case-insensitively since
+/// Windows and macOS file systems usually are.
+fn file_stems<'a>(handles: impl IntoIterator<Item = &'a str>) -> HashMap<&'a str, String> {
+    let mut taken = HashSet::new();
+    let mut stems = HashMap::new();
+
+    for handle in handles {
+        let base = sanitize_file_name(handle);
+        let mut stem = base.clone();
+        let mut n = 1;
+        while !taken.insert(stem.to_lowercase()) {
+            n += 1;
+            stem = format!("{}-{}", base, n);
+        }
+        stems.insert(handle, stem);
+    }
+    stems
+}
+
+/// Write one file per player into `dest_dir`, each holding every event that
+/// names that player (so a kill lands in both the killer's and the victim's
+/// file). Returns the number of rows written per player.
+pub fn export_by_player(
+    events: &[LogEvent],
+    dest_dir: &Path,
+    format: ExportFormat,
+    clock_offset_ms: i64,
+) -> io::Result<BTreeMap<String, usize>> {
+    let mut by_player: BTreeMap<&str, Vec<&LogEvent>> = BTreeMap::new();
+    for event in events {
+        let mut players = event.kind.players();
+        players.sort_unstable();
+        players.dedup();
+        for player in players.into_iter().filter(|p| *p != "unknown") {
+            by_player.entry(player).or_default().push(event);
+        }
+    }
+
+    fs::create_dir_all(dest_dir)?;
+    let stems = file_stems(by_player.keys().copied());
+    let mut counts = BTreeMap::new();
+
+    for (player, events) in &by_player {
+        let file = dest_dir.join(format!("{}.{}", stems[player], format.extension()));
+        let rows = write_events(events.iter().copied(), &file, format, clock_offset_ms)?;
+        counts.insert(player.to_string(), rows);
+    }
+    Ok(counts)
+}
+
+/// Write already parsed events to a CSV file. Returns the number of rows.
+pub fn export_events_csv(
+    events: &[LogEvent],
+    dest: &Path,
+    clock_offset_ms: i64,
+) -> io::Result<usize> {
+    write_events(events, dest, ExportFormat::Csv, clock_offset_ms)
+}
+
+/// Parse a log straight into a CSV file one event at a time, so memory use
+/// stays flat however large the log is. Only events whose kind is in `kinds`
+/// are written, when given. Returns the number of rows.
+pub fn export_log_to_csv(
+    path: &Path,
+    dest: &Path,
+    kinds: Option<&[String]>,
+    clock_offset_ms: i64,
+) -> io::Result<usize> {
+    let mut writer = CsvWriter::new(BufWriter::new(File::create(dest)?), clock_offset_ms)?;
+    parser::for_each_event(path, |event| {
+        if event.matches_kinds(kinds) {
+            writer.write(&event)?;
+        }
+        Ok(())
+    })?;
+    writer.finish()
+}
+
+/// An event with the log line it was parsed from, as written by
+/// `export_log_with_raw`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawEvent {
+    #[serde(flatten)]
+    pub event: LogEvent,
+    pub raw: String,
+    /// Byte offset of the start of `raw` in the log.
+    pub offset: u64,
+}
+
+/// The parser `export_log_with_raw` uses: every optional kind is on, so each
+/// line the parser keeps state from (hits for assists, interiors, loadouts)
+/// gets a row of its own. Feeding an unfiltered export's `raw` lines through
+/// a fresh one in order gives back its events.
+pub fn raw_export_parser() -> LineParser {
+    let mut parser = LineParser::with_damage_ticks(true);
+    parser.set_location_transitions(true);
+    parser.set_loadout_changes(true);
+    parser
+}
+
+/// Parse a log into JSONL like a `Jsonl` export, with each event's original
+/// line and its byte offset added, so the file can be re-parsed later by a
+/// newer parser. Roughly doubles the size of the export. Filtering by `kinds`
+/// can drop lines other events were built from, such as the hits behind a
+/// kill's assists. A crash's context lines aren't kept. Returns the number of
+/// rows.
+pub fn export_log_with_raw(
+    path: &Path,
+    dest: &Path,
+    kinds: Option<&[String]>,
+) -> io::Result<usize> {
+    let mut out = BufWriter::new(File::create(dest)?);
+    let mut rows = 0;
+    parser::for_each_event_with_line(path, raw_export_parser(), |event, raw, offset| {
+        if !event.matches_kinds(kinds) {
+            return Ok(());
+        }
+        let row = RawEvent {
+            event,
+            raw: raw.to_string(),
+            offset,
+        };
+        serde_json::to_writer(&mut out, &row)?;
+        out.write_all(b"\n")?;
+        rows += 1;
+        Ok(())
+    })?;
+    out.flush()?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{EventKind, TestDeath};
+
+    #[test]
+    fn raw_lines_reparse_to_the_exported_events() {
+        let dir = std::env::temp_dir().join(format!("picologs-raw-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("Game.log");
+        fs::write(
+            &log,
+            "<2024-06-07T12:00:00.000Z> Noise\r\n\
+<2024-06-07T12:34:50.000Z> [Notice] <Debug Hostility Events> [OnHandleHit] Hit FROM Wingman TO Victim damage: 10 [Team_CGP4][Hostility]\r\n\
+<2024-06-07T12:34:56.789Z> <Actor Death> CActor::Kill: 'Victim' [1] in zone 'Stanton' killed by 'Killer' [2] using 'wpn' [Class Rifle] with damage type 'Bullet'\r\n\
+<2024-06-07T12:35:00.000Z> [Notice] <RequestLocationInventory> Player[Pilot] Location[Area18]\n",
+        )
+        .unwrap();
+        let dest = dir.join("events.jsonl");
+
+        assert_eq!(export_log_with_raw(&log, &dest, None).unwrap(), 3);
+        let contents = fs::read(&log).unwrap();
+        let rows: Vec<RawEvent> = fs::read_to_string(&dest)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        for row in &rows {
+            assert!(contents[row.offset as usize..].starts_with(row.raw.as_bytes()));
+        }
+
+        // The kill's assist comes from the hit before it, so the lines are
+        // re-parsed in order by one parser, as the export parsed them
+        let mut reparser = raw_export_parser();
+        let reparsed: Vec<LogEvent> = rows
+            .iter()
+            .flat_map(|row| reparser.parse(&row.raw))
+            .collect();
+        let exported: Vec<LogEvent> = rows.into_iter().map(|row| row.event).collect();
+        assert_eq!(reparsed, exported);
+        match &exported[1].kind {
+            EventKind::ActorDeath { assists, .. } => assert_eq!(assists, &["Wingman"]),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn csv_exports_import_back_to_the_same_events() {
+        let event = |timestamp_ms, kind| LogEvent {
+            severity: Some(crate::severity::Severity::Info),
+            ..LogEvent::test(kind).at(timestamp_ms)
+        };
+        let events = vec![
+            event(
+                Some(1_000),
+                EventKind::LocationChange {
+                    player: "Pilot".into(),
+                    location: "Area18, \"ArcCorp\"\nLevel 2".into(),
+                },
+            ),
+            event(
+                Some(2_000),
+                TestDeath {
+                    victim_geid: Some("200000000001".into()),
+                    zone: Some("Stanton".into()),
+                    damage_type: Some("Bullet".into()),
+                    cause_category: Some(crate::cause::CauseCategory::Pvp),
+                    interior: Some("Hangar_LrgTop_003".into()),
+                    ..TestDeath::new("Victim", "Killer")
+                }
+                .into(),
+            ),
+            event(
+                Some(3_000),
+                EventKind::DamageTick {
+                    source: "Killer".into(),
+                    target: "Victim".into(),
+                    amount: Some(12.5),
+                    damage_type: None,
+                },
+            ),
+            event(None, EventKind::SystemQuit),
+        ];
+        let dir = std::env::temp_dir().join(format!("picologs-csv-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("events.csv");
+        assert_eq!(export_events_csv(&events, &dest, 3_600_000).unwrap(), 4);
+
+        // The offset is recorded beside the log's own times, not applied, so
+        // the events (and their fingerprints) come back unchanged
+        let import = import_events_csv(&dest).unwrap();
+        assert_eq!(import.events, events);
+        let csv = fs::read_to_string(&dest).unwrap();
+        assert_eq!(csv.matches(",3600000\n").count(), 4);
+        assert!(import.errors.is_empty());
+
+        // Bad rows are reported without losing the rest
+        let mut csv = fs::read_to_string(&dest).unwrap();
+        csv.push_str("4000,no_such_kind\n\"oops\"x,system_quit\n5000,system_quit,,\n");
+        fs::write(&dest, csv).unwrap();
+        let import = import_events_csv(&dest).unwrap();
+        assert_eq!(import.events, events);
+        let lines: Vec<usize> = import.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, [7, 8, 9]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sanitizes_handles_into_distinct_file_names() {
+        assert_eq!(sanitize_file_name("Pilot/../etc"), "Pilot_.._etc");
+        assert_eq!(sanitize_file_name("Ñandú_7"), "Ñandú_7");
+        assert_eq!(sanitize_file_name("a:b*c?"), "a_b_c_");
+        assert_eq!(sanitize_file_name(".."), "player");
+        assert_eq!(sanitize_file_name("CON"), "CON_");
+
+        let stems = file_stems(["a/b", "a_b", "A_B", "Ñandú"]);
+        assert_eq!(stems["a/b"], "a_b");
+        assert_eq!(stems["a_b"], "a_b-2");
+        assert_eq!(stems["A_B"], "A_B-3");
+        assert_eq!(stems["Ñandú"], "Ñandú");
+    }
+
+    #[test]
+    fn kills_are_written_to_both_players_files() {
+        let dir = std::env::temp_dir().join(format!("picologs-by-player-{}", std::process::id()));
+        let kill = LogEvent::test(TestDeath::new("Victim", "Killer").into()).at(1_000);
+        let quit = LogEvent::test(EventKind::LocationChange {
+            player: "Killer".into(),
+            location: "Area18".into(),
+        })
+        .at(2_000);
+
+        let counts = export_by_player(&[kill, quit], &dir, ExportFormat::Jsonl, 0).unwrap();
+        assert_eq!(counts["Killer"], 2);
+        assert_eq!(counts["Victim"], 1);
+
+        let victim = fs::read_to_string(dir.join("Victim.jsonl")).unwrap();
+        let parsed: LogEvent = serde_json::from_str(victim.trim_end()).unwrap();
+        assert_eq!(parsed.kind.players(), vec!["Victim", "Killer"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn writes_one_row_per_event_with_quoting() {
+        let mut writer = CsvWriter::new(Vec::new(), -250).unwrap();
+        writer
+            .write(
+                &LogEvent::test(EventKind::LocationChange {
+                    player: "Pilot".into(),
+                    location: "Area18, \"ArcCorp\"".into(),
+                })
+                .at(1_000),
+            )
+            .unwrap();
+        assert_eq!(writer.rows, 1);
+
+        let csv = String::from_utf8(writer.out).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            format!("{},clock_offset_ms", CSV_COLUMNS.join(","))
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "1000,location_change,,,,,,,,,,,,,,,,,,,,,,Pilot,\"Area18, \"\"ArcCorp\"\"\",,,,,,,,,,,,-250"
+        );
+    }
+}
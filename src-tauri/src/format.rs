@@ -0,0 +1,235 @@
+use crate::event::{EventKind, LogEvent};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+/// The zone formatted timestamps are shown in. Stored timestamps are always
+/// UTC; this only affects how they're rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayZone {
+    #[default]
+    Utc,
+    Local,
+    Named(Tz),
+}
+
+impl FromStr for DisplayZone {
+    type Err = String;
+
+    /// Accepts `utc`, `local` or an IANA zone name such as `Europe/London`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("utc") {
+            return Ok(DisplayZone::Utc);
+        }
+        if name.eq_ignore_ascii_case("local") {
+            return Ok(DisplayZone::Local);
+        }
+        name.parse::<Tz>()
+            .map(DisplayZone::Named)
+            .map_err(|_| format!("{:?} is not a known time zone", name))
+    }
+}
+
+impl fmt::Display for DisplayZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayZone::Utc => f.write_str("utc"),
+            DisplayZone::Local => f.write_str("local"),
+            DisplayZone::Named(tz) => f.write_str(tz.name()),
+        }
+    }
+}
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S %Z";
+
+fn format_in<Z: TimeZone>(at: DateTime<Utc>, zone: &Z) -> String
+where
+    Z::Offset: fmt::Display,
+{
+    at.with_timezone(zone).format(TIMESTAMP_FORMAT).to_string()
+}
+
+/// Render a Unix-millisecond timestamp in `zone`, e.g.
+/// `2024-06-07 13:34:56 BST`. Out of range values render as `-`.
+pub fn format_timestamp(timestamp_ms: i64, zone: DisplayZone) -> String {
+    let Some(at) = DateTime::from_timestamp_millis(timestamp_ms) else {
+        return "-".to_string();
+    };
+    match zone {
+        DisplayZone::Utc => format_in(at, &Utc),
+        DisplayZone::Local => format_in(at, &Local),
+        DisplayZone::Named(tz) => format_in(at, &tz),
+    }
+}
+
+/// One-line human readable description of an event, used for notifications
+/// and anywhere else an event is rendered as plain text.
+pub fn summarize(event: &LogEvent) -> String {
+    match &event.kind {
+        EventKind::PlayerIdentified { handle, .. } => format!("{} connected to the game", handle),
+        EventKind::ActorDeath {
+            victim,
+            killer,
+            weapon,
+            weapon_name,
+            ..
+        } => {
+            if victim == killer {
+                format!("{} died", victim)
+            } else {
+                let weapon = weapon_name.as_deref().or(weapon.as_deref());
+                match weapon.filter(|w| *w != "unknown") {
+                    Some(weapon) => format!("{} killed by {} using {}", victim, killer, weapon),
+                    None => format!("{} killed by {}", victim, killer),
+                }
+            }
+        }
+        EventKind::VehicleDestruction {
+            vehicle,
+            vehicle_name,
+            cause,
+            ..
+        } => {
+            let vehicle = vehicle_name.as_ref().unwrap_or(vehicle);
+            match cause {
+                Some(cause) => format!("{} destroyed by {}", vehicle, cause),
+                None => format!("{} destroyed", vehicle),
+            }
+        }
+        EventKind::VehicleControl {
+            vehicle,
+            vehicle_name,
+            ..
+        } => format!("Boarded {}", vehicle_name.as_ref().unwrap_or(vehicle)),
+        EventKind::LocationChange { player, location } => {
+            format!("{} requested inventory in {}", player, location)
+        }
+        EventKind::ShardConnected { shard, region } => match region {
+            Some(region) => format!("Joined shard {} ({})", shard, region),
+            None => format!("Joined shard {}", shard),
+        },
+        EventKind::SystemQuit => "Quit the game".to_string(),
+        EventKind::GameCrash { summary, .. } => format!("The game crashed: {}", summary),
+        EventKind::LocationTransition { player, from, to } => match from {
+            Some(from) => format!("{} moved from {} to {}", player, from, to),
+            None => format!("{} entered {}", player, to),
+        },
+        EventKind::LoadoutChange {
+            player,
+            slot,
+            item,
+            item_name,
+        } => format!(
+            "{} equipped {} ({})",
+            player,
+            item_name.as_ref().unwrap_or(item),
+            slot
+        ),
+        EventKind::DamageTick {
+            source,
+            target,
+            amount,
+            ..
+        } => match amount {
+            Some(amount) => format!("{} hit {} for {}", source, target, amount),
+            None => format!("{} hit {}", source, target),
+        },
+    }
+}
+
+/// Time since the stream started, as `H:MM:SS`, e.g. `1:23:45`. Times
+/// before the start are negative.
+pub fn format_stream_offset(offset_ms: i64) -> String {
+    let sign = if offset_ms < 0 { "-" } else { "" };
+    let seconds = offset_ms.unsigned_abs() / 1000;
+    format!(
+        "{}{}:{:02}:{:02}",
+        sign,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// `summarize`, prefixed with how far into the stream the event happened, for
+/// noting the moment in a VOD. Untimed events are summarized alone.
+pub fn format_event_with_stream_offset(event: &LogEvent, stream_start_ms: i64) -> String {
+    match event.timestamp_ms {
+        Some(timestamp_ms) => format!(
+            "[{}] {}",
+            format_stream_offset(timestamp_ms - stream_start_ms),
+            summarize(event)
+        ),
+        None => summarize(event),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TestDeath;
+
+    #[test]
+    fn stream_offsets_carry_over_hour_boundaries() {
+        assert_eq!(format_stream_offset(0), "0:00:00");
+        assert_eq!(format_stream_offset(59_999), "0:00:59");
+        assert_eq!(format_stream_offset(3_599_000), "0:59:59");
+        assert_eq!(format_stream_offset(3_600_000), "1:00:00");
+        assert_eq!(format_stream_offset(5_025_000), "1:23:45");
+        assert_eq!(format_stream_offset(36_000_000), "10:00:00");
+        assert_eq!(format_stream_offset(-5_000), "-0:00:05");
+
+        let quit = |timestamp_ms| LogEvent::test(EventKind::SystemQuit).at(timestamp_ms);
+        assert_eq!(
+            format_event_with_stream_offset(&quit(Some(1_000 + 5_025_000)), 1_000),
+            "[1:23:45] Quit the game"
+        );
+        assert_eq!(
+            format_event_with_stream_offset(&quit(None), 1_000),
+            "Quit the game"
+        );
+    }
+
+    #[test]
+    fn summarizes_kills_with_and_without_a_weapon() {
+        let kill = |weapon: &str| {
+            LogEvent::test(
+                TestDeath {
+                    weapon: Some(weapon.into()),
+                    ..TestDeath::new("Victim", "Killer")
+                }
+                .into(),
+            )
+        };
+
+        assert_eq!(
+            summarize(&kill("wpn_rifle")),
+            "Victim killed by Killer using wpn_rifle"
+        );
+        assert_eq!(summarize(&kill("unknown")), "Victim killed by Killer");
+    }
+
+    #[test]
+    fn formats_across_a_dst_transition() {
+        let london: DisplayZone = "Europe/London".parse().unwrap();
+        // Clocks went forward at 01:00 UTC on 2024-03-31
+        assert_eq!(
+            format_timestamp(1_711_846_799_000, london),
+            "2024-03-31 00:59:59 GMT"
+        );
+        assert_eq!(
+            format_timestamp(1_711_846_800_000, london),
+            "2024-03-31 02:00:00 BST"
+        );
+        assert_eq!(
+            format_timestamp(1_711_846_800_000, DisplayZone::Utc),
+            "2024-03-31 01:00:00 UTC"
+        );
+
+        assert_eq!(london.to_string(), "Europe/London");
+        assert_eq!("UTC".parse(), Ok(DisplayZone::Utc));
+        assert!("Mars/Olympus_Mons".parse::<DisplayZone>().is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use crate::buffer::EventBuffer;
+use crate::installs;
+use crate::settings;
+use crate::watcher::{self, WatchRegistry};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// The game client itself, lowercased.
+const GAME_PROCESS: &str = "starcitizen.exe";
+const LAUNCHER_PROCESS: &str = "rsi launcher.exe";
+
+/// How long a process scan is reused before scanning again.
+const CACHE_FOR: Duration = Duration::from_secs(2);
+
+pub const GAME_STARTED_EVENT: &str = "game-started";
+pub const GAME_STOPPED_EVENT: &str = "game-stopped";
+
+const AUTO_WATCH_ON_LAUNCH_KEY: &str = "autoWatchOnLaunch";
+const LAUNCH_POLL_INTERVAL_KEY: &str = "launchPollIntervalMs";
+const ON_GAME_STOP_KEY: &str = "onGameStop";
+
+const DEFAULT_LAUNCH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const MIN_LAUNCH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Processes {
+    game: bool,
+    launcher: bool,
+}
+
+/// What happens to the feed when the game exits while auto-watching.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnGameStop {
+    /// Leave the session's events in the feed.
+    #[default]
+    Keep,
+    /// Empty the event buffer; `game-stopped` tells the windows to clear too.
+    Clear,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameStarted {
+    /// The log being watched for this session, if one was found.
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameStopped {
+    pub cleared: bool,
+}
+
+struct LaunchWatch {
+    stop: AtomicBool,
+    interval: Duration,
+    on_stop: OnGameStop,
+}
+
+#[derive(Default)]
+pub struct GameState {
+    /// Kept between scans so only the process list is refreshed each time.
+    system: Mutex<Option<System>>,
+    last_scan: Mutex<Option<(Instant, Processes)>>,
+    launch_watch: Mutex<Option<Arc<LaunchWatch>>>,
+}
+
+fn classify(name: &OsStr) -> Processes {
+    let name = name.to_string_lossy().to_lowercase();
+    Processes {
+        game: name == GAME_PROCESS,
+        launcher: name == LAUNCHER_PROCESS,
+    }
+}
+
+fn scan(system: &mut System) -> Processes {
+    // Names only; no CPU, memory or exe paths. Processes we aren't allowed
+    // to inspect are simply missing from the list
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+    system
+        .processes()
+        .values()
+        .map(|process| classify(process.name()))
+        .fold(Processes::default(), |found, process| Processes {
+            game: found.game || process.game,
+            launcher: found.launcher || process.launcher,
+        })
+}
+
+/// Running processes from a scan at most `CACHE_FOR` old.
+fn processes(app: &AppHandle) -> Processes {
+    let state = app.state::<GameState>();
+    let mut last_scan = state.last_scan.lock().unwrap();
+    if let Some((at, processes)) = *last_scan {
+        if at.elapsed() < CACHE_FOR {
+            return processes;
+        }
+    }
+
+    let mut system = state.system.lock().unwrap();
+    let processes = scan(system.get_or_insert_with(System::new));
+    *last_scan = Some((Instant::now(), processes));
+    processes
+}
+
+/// Whether Star Citizen or the RSI Launcher is running.
+pub fn is_running(app: &AppHandle) -> bool {
+    let processes = processes(app);
+    processes.game || processes.launcher
+}
+
+#[tauri::command]
+pub fn is_game_running(app: AppHandle) -> bool {
+    is_running(&app)
+}
+
+/// The most recently written Game.log across every detected install.
+pub fn newest_log() -> Option<PathBuf> {
+    installs::list_installs()
+        .into_iter()
+        .flat_map(|install| install.environments)
+        .filter_map(|environment| {
+            let path = environment.log_path?;
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        .max_by_key(|(_, modified)| *modified)
+        .map(|(path, _)| path)
+}
+
+/// Start tailing the newest log for a game that just launched. Returns the
+/// path, and whether this launch owns the watch (a watch started by hand is
+/// left alone, then and when the game exits).
+fn watch_session(app: &AppHandle) -> Option<(String, bool)> {
+    let path = newest_log()?.to_string_lossy().to_string();
+    let registry = app.state::<WatchRegistry>();
+    if registry.is_watching(&path) {
+        return Some((path, false));
+    }
+    // The game starts each session by truncating its log, so read it all
+    match watcher::start(app, &registry, path.clone(), 0, None) {
+        Ok(_) => Some((path, true)),
+        Err(e) => {
+            tracing::warn!("Failed to start watching {}: {}", path, e);
+            Some((path, false))
+        }
+    }
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        tracing::warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+fn run_launch_watch(app: AppHandle, launch: Arc<LaunchWatch>) {
+    let mut was_running = false;
+    let mut owned: Option<String> = None;
+
+    while !launch.stop.load(Ordering::Relaxed) {
+        let running = processes(&app).game;
+        if running && !was_running {
+            tracing::info!("Star Citizen started");
+            let session = watch_session(&app);
+            let path = session.as_ref().map(|(path, _)| path.clone());
+            owned = session.and_then(|(path, owned)| owned.then_some(path));
+            emit(&app, GAME_STARTED_EVENT, GameStarted { path });
+        } else if !running && was_running {
+            tracing::info!("Star Citizen stopped");
+            if let Some(path) = owned.take() {
+                watcher::stop(&app, &app.state::<WatchRegistry>(), &path);
+            }
+            let cleared = launch.on_stop == OnGameStop::Clear;
+            if cleared {
+                app.state::<EventBuffer>().clear();
+            }
+            emit(&app, GAME_STOPPED_EVENT, GameStopped { cleared });
+        }
+        was_running = running;
+        thread::sleep(launch.interval);
+    }
+}
+
+fn stop_launch_watch(state: &GameState) {
+    if let Some(previous) = state.launch_watch.lock().unwrap().take() {
+        previous.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn start_launch_watch(app: &AppHandle, interval: Duration, on_stop: OnGameStop) {
+    let state = app.state::<GameState>();
+    stop_launch_watch(&state);
+    let launch = Arc::new(LaunchWatch {
+        stop: AtomicBool::new(false),
+        interval: interval.max(MIN_LAUNCH_POLL_INTERVAL),
+        on_stop,
+    });
+    *state.launch_watch.lock().unwrap() = Some(launch.clone());
+
+    let app = app.clone();
+    thread::spawn(move || run_launch_watch(app, launch));
+}
+
+fn launch_settings(app: &AppHandle) -> (Duration, OnGameStop) {
+    let interval = settings::get::<u64>(app, LAUNCH_POLL_INTERVAL_KEY)
+        .ok()
+        .flatten()
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_LAUNCH_POLL_INTERVAL);
+    let on_stop = settings::get(app, ON_GAME_STOP_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    (interval, on_stop)
+}
+
+/// Start the launch watcher if it was left on. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let enabled = settings::get::<bool>(app, AUTO_WATCH_ON_LAUNCH_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    if enabled {
+        let (interval, on_stop) = launch_settings(app);
+        start_launch_watch(app, interval, on_stop);
+    }
+}
+
+/// Watch for the game starting and follow its newest log automatically,
+/// stopping when it exits (unless the log was already being watched by hand).
+/// `interval_ms` and `on_stop` are persisted when given.
+#[tauri::command]
+pub fn set_auto_watch_on_launch(
+    app: AppHandle,
+    state: State<'_, GameState>,
+    enabled: bool,
+    interval_ms: Option<u64>,
+    on_stop: Option<OnGameStop>,
+) -> Result<(), String> {
+    settings::set(&app, AUTO_WATCH_ON_LAUNCH_KEY, &enabled)?;
+    if let Some(interval_ms) = interval_ms {
+        settings::set(&app, LAUNCH_POLL_INTERVAL_KEY, &interval_ms)?;
+    }
+    if let Some(on_stop) = on_stop {
+        settings::set(&app, ON_GAME_STOP_KEY, &on_stop)?;
+    }
+
+    if enabled {
+        let (interval, on_stop) = launch_settings(&app);
+        start_launch_watch(&app, interval, on_stop);
+    } else {
+        stop_launch_watch(&state);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_process_names_case_insensitively() {
+        assert!(classify(OsStr::new("StarCitizen.exe")).game);
+        assert!(classify(OsStr::new("starcitizen.EXE")).game);
+        assert!(classify(OsStr::new("RSI Launcher.exe")).launcher);
+        assert!(!classify(OsStr::new("RSI Launcher.exe")).game);
+
+        let other = classify(OsStr::new("StarCitizen_Launcher.exe"));
+        assert!(!other.game && !other.launcher);
+    }
+}
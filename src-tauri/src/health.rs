@@ -0,0 +1,166 @@
+use crate::diagnostics::Diagnostics;
+use crate::game;
+use crate::installs;
+use crate::watcher::{self, WatchStatus};
+use crate::ws::{self, RoomStatus};
+use serde::Serialize;
+use std::path::Path;
+use sysinfo::System;
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvironmentHealth {
+    pub name: String,
+    pub branch: String,
+    pub changelist: Option<String>,
+    pub log_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallHealth {
+    pub root: String,
+    pub environments: Vec<EnvironmentHealth>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSnapshot {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub game_running: bool,
+    pub installs: Vec<InstallHealth>,
+    pub watches: Vec<WatchStatus>,
+    pub server_url: Option<String>,
+    pub room: RoomStatus,
+    /// Warnings and errors currently in the diagnostics panel.
+    pub recent_warnings: usize,
+    pub recent_errors: usize,
+    /// Whether handles, room codes and the user's home folder were left in.
+    pub identifiers_included: bool,
+}
+
+/// `path` with the user's home folder, which usually contains their account
+/// name, replaced by `~`.
+fn redact_path(path: &str, home: Option<&Path>) -> String {
+    let Some(home) = home.map(|home| home.to_string_lossy()) else {
+        return path.to_string();
+    };
+    let home = home.trim_end_matches(['/', '\\']);
+    match path.get(..home.len()) {
+        Some(prefix)
+            if !home.is_empty()
+                && prefix.eq_ignore_ascii_case(home)
+                && path[home.len()..]
+                    .chars()
+                    .next()
+                    .is_none_or(|c| c == '/' || c == '\\') =>
+        {
+            format!("~{}", &path[home.len()..])
+        }
+        _ => path.to_string(),
+    }
+}
+
+fn home_dir() -> Option<std::path::PathBuf> {
+    std::env::var_os("USERPROFILE")
+        .or_else(|| std::env::var_os("HOME"))
+        .map(Into::into)
+}
+
+/// Blank out everything in a snapshot that identifies the user.
+fn redact(snapshot: &mut HealthSnapshot, home: Option<&Path>) {
+    for install in &mut snapshot.installs {
+        install.root = redact_path(&install.root, home);
+        for environment in &mut install.environments {
+            environment.log_path = environment
+                .log_path
+                .as_deref()
+                .map(|path| redact_path(path, home));
+        }
+    }
+    for watch in &mut snapshot.watches {
+        watch.path = redact_path(&watch.path, home);
+        watch.local_handle = None;
+    }
+    snapshot.room.code = None;
+    snapshot.identifiers_included = false;
+}
+
+/// Everything support usually asks for first, in one place: versions, what
+/// was detected and is being watched, and the room connection. Handles, room
+/// codes and home folder paths are left out unless `include_identifiers`.
+#[tauri::command]
+pub fn health_snapshot(app: AppHandle, include_identifiers: Option<bool>) -> HealthSnapshot {
+    let installs = installs::list_installs()
+        .into_iter()
+        .map(|install| InstallHealth {
+            root: install.root.to_string_lossy().to_string(),
+            environments: install
+                .environments
+                .into_iter()
+                .map(|environment| EnvironmentHealth {
+                    name: environment.name,
+                    branch: environment.branch,
+                    changelist: environment.version.and_then(|version| version.changelist),
+                    log_path: environment
+                        .log_path
+                        .map(|path| path.to_string_lossy().to_string()),
+                })
+                .collect(),
+        })
+        .collect();
+
+    let diagnostics = app.state::<Diagnostics>();
+    let mut snapshot = HealthSnapshot {
+        app_version: app.package_info().version.to_string(),
+        os: System::long_os_version().unwrap_or_else(|| std::env::consts::OS.to_string()),
+        arch: std::env::consts::ARCH.to_string(),
+        game_running: game::is_running(&app),
+        installs,
+        watches: watcher::list_log_watches(app.state()),
+        server_url: ws::get_server_url(app.clone()),
+        room: ws::get_room_status(app.state()),
+        recent_warnings: diagnostics.count_at("warn"),
+        recent_errors: diagnostics.count_at("error"),
+        identifiers_included: true,
+    };
+    if !include_identifiers.unwrap_or(false) {
+        redact(&mut snapshot, home_dir().as_deref());
+    }
+    snapshot
+}
+
+/// `health_snapshot` as pretty-printed JSON, ready to paste into a report.
+#[tauri::command]
+pub fn copy_health_snapshot(
+    app: AppHandle,
+    include_identifiers: Option<bool>,
+) -> Result<String, String> {
+    serde_json::to_string_pretty(&health_snapshot(app, include_identifiers))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_the_home_folder() {
+        let home = Path::new("C:\\Users\\Jane");
+        assert_eq!(
+            redact_path("c:\\users\\jane\\AppData\\Game.log", Some(home)),
+            "~\\AppData\\Game.log"
+        );
+        assert_eq!(redact_path("C:\\Users\\Jane", Some(home)), "~");
+        // Only whole folder names match
+        assert_eq!(
+            redact_path("C:\\Users\\Janet\\Game.log", Some(home)),
+            "C:\\Users\\Janet\\Game.log"
+        );
+        assert_eq!(
+            redact_path("D:\\Games\\Game.log", Some(home)),
+            "D:\\Games\\Game.log"
+        );
+        assert_eq!(redact_path("/home/j/Game.log", None), "/home/j/Game.log");
+    }
+}
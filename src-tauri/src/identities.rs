@@ -0,0 +1,158 @@
+use crate::event::{EventKind, LogEvent};
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+const IDENTITY_MAP_KEY: &str = "identityMap";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdentityEntry {
+    pub geid: String,
+    pub handle: String,
+    pub last_seen_ms: Option<i64>,
+}
+
+/// One-to-one mapping between geids and handles, as last observed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IdentityMap {
+    by_geid: BTreeMap<String, IdentityEntry>,
+}
+
+impl IdentityMap {
+    /// Record that `geid` is currently known as `handle`. Any other geid that
+    /// previously claimed the same handle is dropped, so the newest observation
+    /// wins in both directions. Returns whether anything changed.
+    pub fn observe(&mut self, geid: &str, handle: &str, seen_ms: Option<i64>) -> bool {
+        let unchanged = self
+            .by_geid
+            .get(geid)
+            .is_some_and(|entry| entry.handle == handle && entry.last_seen_ms >= seen_ms);
+        if unchanged {
+            return false;
+        }
+
+        self.by_geid
+            .retain(|g, entry| g == geid || !entry.handle.eq_ignore_ascii_case(handle));
+        self.by_geid.insert(
+            geid.to_string(),
+            IdentityEntry {
+                geid: geid.to_string(),
+                handle: handle.to_string(),
+                last_seen_ms: seen_ms,
+            },
+        );
+        true
+    }
+
+    pub fn handle_for(&self, geid: &str) -> Option<&str> {
+        self.by_geid.get(geid).map(|entry| entry.handle.as_str())
+    }
+
+    pub fn geid_for(&self, handle: &str) -> Option<&str> {
+        self.by_geid
+            .values()
+            .find(|entry| entry.handle.eq_ignore_ascii_case(handle))
+            .map(|entry| entry.geid.as_str())
+    }
+
+    pub fn entries(&self) -> Vec<IdentityEntry> {
+        self.by_geid.values().cloned().collect()
+    }
+}
+
+#[derive(Default)]
+pub struct IdentityState(Mutex<IdentityMap>);
+
+pub fn init(app: &AppHandle) {
+    if let Ok(Some(map)) = settings::get::<IdentityMap>(app, IDENTITY_MAP_KEY) {
+        *app.state::<IdentityState>().0.lock().unwrap() = map;
+    }
+}
+
+/// Fold any `PlayerIdentified` events in a batch into the persisted map.
+pub fn observe_batch(app: &AppHandle, events: &[LogEvent]) {
+    let state = app.state::<IdentityState>();
+    let mut map = state.0.lock().unwrap();
+    let mut changed = false;
+
+    for event in events {
+        if let EventKind::PlayerIdentified {
+            handle,
+            geid: Some(geid),
+        } = &event.kind
+        {
+            changed |= map.observe(geid, handle, event.timestamp_ms);
+        }
+    }
+
+    if changed {
+        if let Err(e) = settings::set(app, IDENTITY_MAP_KEY, &*map) {
+            tracing::warn!("Failed to persist identity map: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn resolve_handle(state: State<'_, IdentityState>, geid: String) -> Option<String> {
+    state.0.lock().unwrap().handle_for(&geid).map(String::from)
+}
+
+#[tauri::command]
+pub fn resolve_geid(state: State<'_, IdentityState>, handle: String) -> Option<String> {
+    state.0.lock().unwrap().geid_for(&handle).map(String::from)
+}
+
+/// The full table, for debugging attribution issues.
+#[tauri::command]
+pub fn get_identity_table(state: State<'_, IdentityState>) -> Vec<IdentityEntry> {
+    state.0.lock().unwrap().entries()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_in_both_directions() {
+        let mut map = IdentityMap::default();
+        assert!(map.observe("100", "Pilot", Some(1)));
+
+        assert_eq!(map.handle_for("100"), Some("Pilot"));
+        assert_eq!(map.geid_for("pilot"), Some("100"));
+        assert_eq!(map.handle_for("999"), None);
+    }
+
+    #[test]
+    fn same_geid_with_new_handle_replaces_the_old_handle() {
+        let mut map = IdentityMap::default();
+        map.observe("100", "OldName", Some(1));
+        assert!(map.observe("100", "NewName", Some(2)));
+
+        assert_eq!(map.handle_for("100"), Some("NewName"));
+        assert_eq!(map.geid_for("NewName"), Some("100"));
+        assert_eq!(map.geid_for("OldName"), None);
+        assert_eq!(map.entries().len(), 1);
+    }
+
+    #[test]
+    fn same_handle_with_new_geid_drops_the_old_geid() {
+        let mut map = IdentityMap::default();
+        map.observe("100", "Pilot", Some(1));
+        map.observe("200", "Pilot", Some(2));
+
+        assert_eq!(map.geid_for("Pilot"), Some("200"));
+        assert_eq!(map.handle_for("100"), None);
+    }
+
+    #[test]
+    fn repeated_observation_is_not_a_change() {
+        let mut map = IdentityMap::default();
+        map.observe("100", "Pilot", Some(5));
+
+        assert!(!map.observe("100", "Pilot", Some(5)));
+        assert!(!map.observe("100", "Pilot", Some(3)));
+        assert!(map.observe("100", "Pilot", Some(6)));
+    }
+}
@@ -0,0 +1,335 @@
+use crate::ENVIRONMENTS;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::UNIX_EPOCH;
+
+/// The header is written in the first few dozen lines; stop looking after this
+/// many so a log without one isn't read end to end.
+const HEADER_LINES: usize = 64;
+
+static BRANCH: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"Branch: (\S+)").unwrap());
+static CHANGELIST: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"Changelist: (\d+)").unwrap());
+static FILE_VERSION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"FileVersion: (\S+)").unwrap());
+
+/// Build information from the header Game.log starts with.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LogVersion {
+    /// e.g. `sc-alpha-3.24.3`
+    pub branch: Option<String>,
+    pub changelist: Option<String>,
+    pub file_version: Option<String>,
+}
+
+/// Read the build header of a Game.log. `None` if the file can't be read or
+/// has no header yet.
+pub fn read_log_version(path: &Path) -> Option<LogVersion> {
+    let reader = BufReader::new(File::open(path).ok()?);
+    let mut version = LogVersion::default();
+
+    for line in reader.lines().take(HEADER_LINES) {
+        let Ok(line) = line else {
+            break;
+        };
+        let capture = |re: &Regex| re.captures(&line).map(|caps| caps[1].to_string());
+        version.branch = version.branch.or_else(|| capture(&BRANCH));
+        version.changelist = version.changelist.or_else(|| capture(&CHANGELIST));
+        version.file_version = version.file_version.or_else(|| capture(&FILE_VERSION));
+    }
+
+    (version != LogVersion::default()).then_some(version)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Environment {
+    /// The folder name, e.g. `PTU`.
+    pub name: String,
+    pub log_path: Option<PathBuf>,
+    /// The branch from the log header, or the folder name when there's no
+    /// header to read yet.
+    pub branch: String,
+    /// Set when `branch` is only the folder name.
+    pub inferred: bool,
+    pub version: Option<LogVersion>,
+}
+
+/// The environments present under an install root, given either the
+/// `StarCitizen` folder or the `Roberts Space Industries` folder above it.
+pub fn list_environments(root: &Path) -> Vec<Environment> {
+    let base = if root.join("StarCitizen").is_dir() {
+        root.join("StarCitizen")
+    } else {
+        root.to_path_buf()
+    };
+
+    ENVIRONMENTS
+        .iter()
+        .filter(|name| base.join(name).is_dir())
+        .map(|name| {
+            let log = base.join(name).join("Game.log");
+            let log_path = log.is_file().then_some(log);
+            let version = log_path.as_deref().and_then(read_log_version);
+            let header_branch = version.as_ref().and_then(|v| v.branch.clone());
+
+            Environment {
+                name: name.to_string(),
+                inferred: header_branch.is_none(),
+                branch: header_branch.unwrap_or_else(|| name.to_string()),
+                log_path,
+                version,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Install {
+    pub root: PathBuf,
+    pub environments: Vec<Environment>,
+}
+
+/// Every string stored under a `libraryFolder` key, at any depth.
+fn collect_library_folders(value: &Value, folders: &mut Vec<PathBuf>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    Value::String(path) if key.eq_ignore_ascii_case("libraryFolder") => {
+                        folders.push(PathBuf::from(path))
+                    }
+                    _ => collect_library_folders(value, folders),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                collect_library_folders(value, folders);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Library folders the RSI Launcher has recorded in the JSON files of its
+/// config directory (`%APPDATA%\rsilauncher`). The launcher's file names and
+/// layout change between releases, so every JSON file is searched for a
+/// `libraryFolder` key, and files that are missing or don't parse are skipped.
+fn launcher_library_folders(config_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(config_dir) else {
+        return Vec::new();
+    };
+    let mut folders = Vec::new();
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let Ok(contents) = fs::read(&path) else {
+            continue;
+        };
+        match serde_json::from_slice::<Value>(&contents) {
+            Ok(value) => collect_library_folders(&value, &mut folders),
+            Err(e) => tracing::debug!("Skipping launcher config {}: {}", path.display(), e),
+        }
+    }
+
+    // The library folder usually sits above `Roberts Space Industries`
+    folders
+        .into_iter()
+        .flat_map(|folder| [folder.join("Roberts Space Industries"), folder])
+        .collect()
+}
+
+/// Folders a Star Citizen install (or its logs) may live under on this
+/// machine, from the launcher's registry keys and config files and the default
+/// locations.
+fn install_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+
+    #[cfg(windows)]
+    {
+        use winreg::enums::*;
+        use winreg::RegKey;
+
+        let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+        if let Ok(key) = hklm.open_subkey(
+            "SOFTWARE\\Wow6432Node\\Microsoft\\Windows\\CurrentVersion\\App Paths\\StarCitizen Launcher.exe",
+        ) {
+            if let Ok(path) = key.get_value::<String, _>("Path") {
+                if let Some(parent) = Path::new(&path).parent() {
+                    roots.push(parent.to_path_buf());
+                }
+            }
+        }
+        if let Ok(key) = hklm
+            .open_subkey("SOFTWARE\\Wow6432Node\\Cloud Imperium Games\\StarCitizen Launcher.exe")
+        {
+            if let Ok(path) = key.get_value::<String, _>("InstallLocation") {
+                roots.push(PathBuf::from(path));
+            }
+        }
+        if let Ok(appdata) = std::env::var("APPDATA") {
+            roots.push(PathBuf::from(appdata).join("Roberts Space Industries"));
+        }
+        roots.push(PathBuf::from("C:\\Program Files\\Roberts Space Industries"));
+    }
+
+    // Finds installs moved to another drive that the registry doesn't know
+    if let Ok(appdata) = std::env::var("APPDATA") {
+        let config_dir = PathBuf::from(appdata).join("rsilauncher");
+        roots.extend(launcher_library_folders(&config_dir));
+    }
+
+    let mut seen = HashSet::new();
+    roots.retain(|root| seen.insert(root.clone()));
+    roots
+}
+
+/// Every detected install with at least one environment, with the branch each
+/// environment's log reports.
+pub fn list_installs() -> Vec<Install> {
+    install_roots()
+        .into_iter()
+        .filter(|root| root.is_dir())
+        .map(|root| Install {
+            environments: list_environments(&root),
+            root,
+        })
+        .filter(|install| !install.environments.is_empty())
+        .collect()
+}
+
+/// The release channel a header branch is built for. Test branches name their
+/// channel (`sc-alpha-4.0-ptu`, `...-hotfix`); a bare version is LIVE.
+fn branch_channel(branch: &str) -> &'static str {
+    let branch = branch.to_lowercase();
+    if branch.contains("eptu") || branch.contains("evocati") {
+        "EPTU"
+    } else if branch.contains("ptu") {
+        "PTU"
+    } else if branch.contains("hotfix") {
+        "HOTFIX"
+    } else if branch.contains("tech-preview") {
+        "TECH-PREVIEW"
+    } else {
+        "LIVE"
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvMismatch {
+    pub root: PathBuf,
+    /// The folder name, e.g. `PTU`.
+    pub environment: String,
+    /// The branch from the log header, when there is one.
+    pub branch: Option<String>,
+    /// The channel `branch` is built for.
+    pub branch_channel: Option<&'static str>,
+    /// Set when the header's channel isn't the folder's.
+    pub mismatch: bool,
+    /// When the environment's Game.log was last written.
+    pub modified_ms: Option<i64>,
+}
+
+/// For every detected environment, whether the build its log header reports
+/// belongs to a different channel than the folder it's in, as happens when a
+/// folder is reused during a test wave. Only log headers are read.
+pub fn environment_mismatch_report() -> Vec<EnvMismatch> {
+    let mut report = Vec::new();
+    for install in list_installs() {
+        for environment in install.environments {
+            let branch = (!environment.inferred).then_some(environment.branch);
+            let channel = branch.as_deref().map(branch_channel);
+            let modified_ms = environment
+                .log_path
+                .as_ref()
+                .and_then(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_millis() as i64);
+
+            report.push(EnvMismatch {
+                root: install.root.clone(),
+                mismatch: channel
+                    .is_some_and(|channel| !channel.eq_ignore_ascii_case(&environment.name)),
+                environment: environment.name,
+                branch,
+                branch_channel: channel,
+                modified_ms,
+            });
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const HEADER: &str = "Log started on 12/10/2024 19:32:35\nExecutable: C:\\Games\\StarCitizen\\PTU\\Bin64\\StarCitizen.exe\nFileVersion: 3.24.3.9317301\nProductVersion: 3.24.3.9317301\nBranch: sc-alpha-3.24.3\nChangelist: 9317301\n";
+
+    #[test]
+    fn reads_the_branch_from_the_header() {
+        let root = std::env::temp_dir().join(format!("picologs-installs-{}", std::process::id()));
+        for env in ["LIVE", "PTU"] {
+            fs::create_dir_all(root.join("StarCitizen").join(env)).unwrap();
+        }
+        // The PTU folder is running the LIVE branch's build here
+        fs::write(root.join("StarCitizen/PTU/Game.log"), HEADER).unwrap();
+
+        let environments = list_environments(&root);
+        assert_eq!(environments.len(), 2);
+
+        let live = &environments[0];
+        assert_eq!((live.name.as_str(), live.branch.as_str()), ("LIVE", "LIVE"));
+        assert!(live.inferred);
+        assert_eq!(live.log_path, None);
+
+        let ptu = &environments[1];
+        assert_eq!(ptu.branch, "sc-alpha-3.24.3");
+        assert!(!ptu.inferred);
+        assert_eq!(
+            ptu.version.as_ref().unwrap().changelist.as_deref(),
+            Some("9317301")
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn reads_the_channel_from_the_branch() {
+        assert_eq!(branch_channel("sc-alpha-3.24.3"), "LIVE");
+        assert_eq!(branch_channel("sc-alpha-4.0-ptu"), "PTU");
+        assert_eq!(branch_channel("sc-alpha-4.0-EPTU"), "EPTU");
+        assert_eq!(branch_channel("sc-alpha-3.24.3-hotfix"), "HOTFIX");
+    }
+
+    #[test]
+    fn finds_library_folders_in_launcher_config() {
+        let dir = std::env::temp_dir().join(format!("picologs-launcher-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("settings.json"),
+            r#"{"library": {"defaults": [{"libraryFolder": "D:\\Games"}]}}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("log-config.json"), "{ not json").unwrap();
+        fs::write(dir.join("notes.txt"), r#"{"libraryFolder": "E:\\"}"#).unwrap();
+
+        assert_eq!(
+            launcher_library_folders(&dir),
+            vec![
+                PathBuf::from("D:\\Games").join("Roberts Space Industries"),
+                PathBuf::from("D:\\Games"),
+            ]
+        );
+        assert!(launcher_library_folders(&dir.join("missing")).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
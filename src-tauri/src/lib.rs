@@ -1,12 +1,104 @@
-use tauri::Emitter;
+// Without the app, the analysis modules below are only reached from their own
+// tests
+#![cfg_attr(not(feature = "app"), allow(dead_code))]
+
+#[cfg(feature = "app")]
+use std::collections::BTreeMap;
+#[cfg(feature = "app")]
 use std::path::PathBuf;
+#[cfg(feature = "app")]
+use tauri::Emitter;
+
+#[cfg(feature = "app")]
+mod activity;
+#[cfg(feature = "app")]
+mod archive;
+#[cfg(feature = "app")]
+mod autowatch;
+#[cfg(feature = "app")]
+mod bookmarks;
+#[cfg(feature = "app")]
+mod budget;
+#[cfg(feature = "app")]
+mod buffer;
+mod bursts;
+pub mod catalog;
+#[cfg(feature = "app")]
+mod clock;
+pub mod cause;
+mod correlate;
+#[cfg(feature = "app")]
+mod diagnostics;
+mod diff;
+pub mod event;
+mod export;
+mod fnv;
+mod format;
+#[cfg(feature = "app")]
+mod game;
+#[cfg(feature = "app")]
+mod health;
+#[cfg(feature = "app")]
+mod identities;
+mod installs;
+#[cfg(feature = "app")]
+mod notifications;
+#[cfg(feature = "app")]
+mod parse_cache;
+pub mod parser;
+#[cfg(feature = "app")]
+mod pipeline;
+#[cfg(feature = "app")]
+mod recents;
+#[cfg(feature = "app")]
+mod replay;
+mod report;
+mod search;
+#[cfg(feature = "app")]
+mod session;
+#[cfg(feature = "app")]
+mod settings;
+pub mod severity;
+#[cfg(feature = "app")]
+mod shard;
+#[cfg(feature = "app")]
+mod streak;
+#[cfg(feature = "app")]
+mod updates;
+#[cfg(feature = "app")]
+mod watcher;
+#[cfg(feature = "app")]
+mod webhooks;
+#[cfg(feature = "app")]
+mod windows;
+#[cfg(feature = "app")]
+mod ws;
+// Without the app only the room protocol, which needs nothing but serde
+#[cfg(not(feature = "app"))]
+mod ws {
+    pub mod protocol;
+}
+
+pub use event::{event_fingerprint, EventKind, LogEvent, KIND_NAMES};
+pub use parser::{parse_line, LineParser};
+pub use ws::protocol;
+
+#[cfg(feature = "app")]
+use bursts::Burst;
+#[cfg(feature = "app")]
+use diff::EventDiff;
+
+/// Environment folders the game installs side by side under `StarCitizen/`.
+pub const ENVIRONMENTS: [&str; 3] = ["LIVE", "PTU", "HOTFIX"];
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+#[cfg(feature = "app")]
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+#[cfg(feature = "app")]
 #[tauri::command]
 fn find_star_citizen_logs() -> Result<Vec<String>, String> {
     #[cfg(windows)]
@@ -28,7 +120,7 @@ fn find_star_citizen_logs() -> Result<Vec<String>, String> {
                     let sc_path = parent.join("StarCitizen");
 
                     // Check for LIVE, PTU, and HOTFIX environments
-                    for env in &["LIVE", "PTU", "HOTFIX"] {
+                    for env in &ENVIRONMENTS {
                         let log_path = sc_path.join(env).join("Game.log");
                         if log_path.exists() {
                             paths.push(log_path.to_string_lossy().to_string());
@@ -45,7 +137,7 @@ fn find_star_citizen_logs() -> Result<Vec<String>, String> {
                     let install_path = PathBuf::from(path);
                     let sc_path = install_path.join("StarCitizen");
 
-                    for env in &["LIVE", "PTU", "HOTFIX"] {
+                    for env in &ENVIRONMENTS {
                         let log_path = sc_path.join(env).join("Game.log");
                         if log_path.exists() {
                             paths.push(log_path.to_string_lossy().to_string());
@@ -62,7 +154,7 @@ fn find_star_citizen_logs() -> Result<Vec<String>, String> {
                     .join("Roberts Space Industries")
                     .join("StarCitizen");
 
-                for env in &["LIVE", "PTU", "HOTFIX"] {
+                for env in &ENVIRONMENTS {
                     let log_path = sc_appdata.join(env).join("Game.log");
                     if log_path.exists() {
                         paths.push(log_path.to_string_lossy().to_string());
@@ -89,13 +181,229 @@ fn find_star_citizen_logs() -> Result<Vec<String>, String> {
     }
 }
 
+/// Every detected install, with the branch each environment's log header
+/// reports.
+#[cfg(feature = "app")]
+#[tauri::command]
+fn list_installs() -> Vec<installs::Install> {
+    installs::list_installs()
+}
+
+/// Environments whose log header reports another channel's build.
+#[cfg(feature = "app")]
+#[tauri::command]
+fn environment_mismatch_report() -> Vec<installs::EnvMismatch> {
+    installs::environment_mismatch_report()
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+fn list_environments(root: String) -> Vec<installs::Environment> {
+    installs::list_environments(&PathBuf::from(root))
+}
+
+/// Render an event prefixed with its time into the stream, e.g.
+/// `[1:23:45] Target killed by PlayerX`.
+#[cfg(feature = "app")]
+#[tauri::command]
+fn format_event_with_stream_offset(
+    app: tauri::AppHandle,
+    mut event: LogEvent,
+    stream_start_ms: i64,
+) -> String {
+    clock::correct(&app, std::slice::from_mut(&mut event));
+    format::format_event_with_stream_offset(&event, stream_start_ms)
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+fn read_log_version(path: String) -> Option<installs::LogVersion> {
+    installs::read_log_version(&PathBuf::from(path))
+}
+
+/// Stream a log file straight to CSV, optionally keeping only some kinds.
+/// Returns the number of rows written.
+#[cfg(feature = "app")]
+#[tauri::command]
+async fn export_log_to_csv(
+    app: tauri::AppHandle,
+    path: String,
+    dest: String,
+    kinds: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let clock_offset_ms = clock::offset_ms(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        export::export_log_to_csv(
+            &PathBuf::from(path),
+            &PathBuf::from(dest),
+            kinds.as_deref(),
+            clock_offset_ms,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Parse a log into JSONL with each event's raw line and byte offset. Returns
+/// the number of rows written.
+#[cfg(feature = "app")]
+#[tauri::command]
+async fn export_log_with_raw(
+    path: String,
+    dest: String,
+    kinds: Option<Vec<String>>,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        export::export_log_with_raw(
+            &PathBuf::from(path),
+            &PathBuf::from(dest),
+            kinds.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Write one file per player named in `events` into `dest_dir`. Returns the
+/// rows written per player.
+#[cfg(feature = "app")]
+#[tauri::command]
+fn export_by_player(
+    app: tauri::AppHandle,
+    events: Vec<LogEvent>,
+    dest_dir: String,
+    format: export::ExportFormat,
+) -> Result<BTreeMap<String, usize>, String> {
+    export::export_by_player(
+        &events,
+        &PathBuf::from(dest_dir),
+        format,
+        clock::offset_ms(&app),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Read back a CSV export. Rows that can't be read are listed in `errors`
+/// rather than failing the import.
+#[cfg(feature = "app")]
+#[tauri::command]
+async fn import_events_csv(path: String) -> Result<export::CsvImport, String> {
+    tauri::async_runtime::spawn_blocking(move || export::import_events_csv(&PathBuf::from(path)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+fn export_events_csv(
+    app: tauri::AppHandle,
+    events: Vec<LogEvent>,
+    dest: String,
+) -> Result<usize, String> {
+    export::export_events_csv(&events, &PathBuf::from(dest), clock::offset_ms(&app))
+        .map_err(|e| e.to_string())
+}
+
+/// Merge reports of the same kill from different room members into one event.
+#[cfg(feature = "app")]
+#[tauri::command]
+fn correlate_kills(events: Vec<LogEvent>) -> Vec<LogEvent> {
+    correlate::correlate_kills(events)
+}
+
+/// Render an after-action report from several members' feeds of the same
+/// operation. `own_source` is the index of this user's own feed, the only one
+/// the clock correction applies to.
+#[cfg(feature = "app")]
+#[tauri::command]
+fn generate_org_report(
+    app: tauri::AppHandle,
+    sources: Vec<Vec<LogEvent>>,
+    format: report::ReportFormat,
+    own_source: Option<usize>,
+) -> String {
+    report::generate_org_report(
+        sources,
+        format,
+        settings::display_zone(&app),
+        own_source,
+        clock::offset_ms(&app),
+    )
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+fn weapon_breakdown(events: Vec<LogEvent>, handle: Option<String>) -> Vec<report::WeaponStat> {
+    report::weapon_breakdown(&events, handle.as_deref())
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+fn death_causes(events: Vec<LogEvent>, handle: Option<String>) -> report::DeathCauses {
+    report::death_causes(&events, handle.as_deref())
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+fn ship_performance(events: Vec<LogEvent>, handle: String) -> Vec<report::ShipStat> {
+    report::ship_performance(&events, &handle)
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+fn diff_events(a: Vec<LogEvent>, b: Vec<LogEvent>) -> EventDiff {
+    diff::diff_events(a, b)
+}
+
+/// Group combat events into fights for the timeline view.
+#[cfg(feature = "app")]
+#[tauri::command]
+fn detect_bursts(events: Vec<LogEvent>, window_ms: u64, min_events: usize) -> Vec<Burst> {
+    bursts::detect_bursts(&events, window_ms, min_events)
+}
+
+#[cfg(feature = "app")]
+#[tauri::command]
+fn reresolve_names(events: Vec<LogEvent>) -> Vec<LogEvent> {
+    catalog::reresolve_names(events)
+}
+
+#[cfg(feature = "app")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .manage(diagnostics::Diagnostics::default())
+        .manage(buffer::EventBuffer::default())
+        .manage(pipeline::EmitState::default())
+        .manage(parse_cache::ParseCache::default())
+        .manage(parse_cache::ParseTokens::default())
+        .manage(windows::WindowFilters::default())
+        .manage(windows::OverlayTray::default())
+        .manage(watcher::WatchRegistry::default())
+        .manage(watcher::checkpoint::CheckpointState::default())
+        .manage(notifications::NotificationState::default())
+        .manage(identities::IdentityState::default())
+        .manage(autowatch::AutoWatchState::default())
+        .manage(game::GameState::default())
+        .manage(replay::ReplayState::default())
+        .manage(ws::RoomState::default())
+        .manage(shard::ShardState::default())
+        .manage(streak::StreakState::default())
+        .manage(activity::ActivityState::default())
+        .manage(budget::WorkBudget::default())
+        .manage(clock::ClockState::default())
+        .manage(webhooks::WebhookState::default())
+        .manage(session::SessionState::default())
+        .manage(settings::StoreRecoveryState::default())
+        .manage(updates::UpdateState::default())
         .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_websocket::init())
         .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
@@ -111,7 +419,171 @@ pub fn run() {
             }
         }))
         .plugin(tauri_plugin_updater::Builder::new().pubkey("dW50cnVzdGVkIGNvbW1lbnQ6IG1pbmlzaWduIHB1YmxpYyBrZXk6IDdCRDA5MjA5MDI3NUI1NEIKUldSTHRYVUNDWkxRZTBsSWpYbmd2SGprNmVmTHpwaW5PTGFCUFdtdXNpOCszWmdjOXF4S2RaaTMK").build())
-        .invoke_handler(tauri::generate_handler![greet, find_star_citizen_logs])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .setup(|app| {
+            diagnostics::init(app.handle());
+            settings::recover_store(app.handle());
+            pipeline::init(app.handle());
+            notifications::init(app.handle());
+            identities::init(app.handle());
+            streak::init(app.handle());
+            activity::init(app.handle());
+            budget::init(app.handle());
+            clock::init(app.handle());
+            webhooks::init(app.handle());
+            watcher::checkpoint::init(app.handle());
+            catalog::commands::init(app.handle());
+            watcher::init(app.handle());
+            watcher::restore(app.handle());
+            game::init(app.handle());
+            session::init(app.handle());
+            ws::presets::restore(app.handle());
+            windows::init(app.handle());
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            match event {
+                tauri::WindowEvent::CloseRequested { .. } => windows::on_close_requested(window),
+                tauri::WindowEvent::Destroyed => windows::forget(window),
+                _ => {}
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            find_star_citizen_logs,
+            list_installs,
+            environment_mismatch_report,
+            list_environments,
+            read_log_version,
+            format_event_with_stream_offset,
+            parse_cache::parse_log_file,
+            parse_cache::parse_log_file_cancellable,
+            parse_cache::cancel_parse,
+            parse_cache::clear_parse_cache,
+            diff_events,
+            correlate_kills,
+            generate_org_report,
+            weapon_breakdown,
+            death_causes,
+            ship_performance,
+            export_events_csv,
+            import_events_csv,
+            export_log_to_csv,
+            export_log_with_raw,
+            export_by_player,
+            reresolve_names,
+            catalog::commands::get_catalog_overrides,
+            catalog::commands::set_catalog_override,
+            catalog::commands::clear_catalog_override,
+            catalog::commands::update_catalog,
+            detect_bursts,
+            settings::get_app_paths,
+            settings::get_store_recovery,
+            settings::set_display_timezone,
+            settings::get_display_timezone,
+            settings::set_stream_start,
+            settings::get_stream_start,
+            updates::check_for_update,
+            updates::get_pending_update,
+            updates::install_update,
+            diagnostics::get_diagnostics,
+            diagnostics::clear_diagnostics,
+            bookmarks::bookmark_event,
+            bookmarks::remove_bookmark,
+            bookmarks::list_bookmarks,
+            pipeline::set_local_emit,
+            pipeline::set_room_share,
+            pipeline::get_emit_settings,
+            pipeline::set_tracking_cutoff,
+            pipeline::clear_tracking_cutoff,
+            buffer::replay_buffer,
+            buffer::load_history_into_buffer,
+            buffer::search_events,
+            windows::open_feed_window,
+            windows::set_window_filter,
+            windows::set_overlay_mode,
+            windows::set_overlay_opacity,
+            windows::set_overlay_click_through,
+            windows::get_overlay_settings,
+            watcher::start_log_watch,
+            watcher::stop_log_watch,
+            watcher::list_log_watches,
+            watcher::get_watch_kind_counts,
+            watcher::prune_stale_state,
+            watcher::set_prune_stale_state_on_startup,
+            watcher::set_max_backfill_events,
+            watcher::set_parse_damage_ticks,
+            watcher::set_parse_location_transitions,
+            watcher::set_parse_loadout_changes,
+            watcher::get_active_parser_profile,
+            watcher::set_parser_profile,
+            watcher::verify_offsets,
+            watcher::set_stale_log_threshold,
+            watcher::set_adaptive_polling,
+            watcher::get_poll_settings,
+            watcher::checkpoint::flush_checkpoints,
+            watcher::checkpoint::set_checkpoint_interval,
+            notifications::set_notification_kinds,
+            notifications::set_notification_rate_limit,
+            identities::resolve_handle,
+            identities::resolve_geid,
+            identities::get_identity_table,
+            shard::get_current_shard,
+            streak::get_current_streak,
+            streak::get_streak_records,
+            activity::set_activity_kinds,
+            activity::get_activity_kinds,
+            budget::set_parse_concurrency,
+            budget::get_parse_budget,
+            clock::set_clock_offset,
+            clock::get_clock_offset,
+            webhooks::register_webhook,
+            webhooks::list_webhooks,
+            webhooks::set_webhook_enabled,
+            webhooks::remove_webhook,
+            archive::archive_history,
+            session::snapshot_session,
+            session::get_saved_session,
+            session::restore_session,
+            recents::get_recent_logs,
+            recents::clear_recent_logs,
+            game::is_game_running,
+            game::set_auto_watch_on_launch,
+            health::health_snapshot,
+            health::copy_health_snapshot,
+            replay::replay_log,
+            replay::stop_replay,
+            autowatch::watch_install_root,
+            autowatch::stop_install_root_watch,
+            ws::join_room,
+            ws::join_as_spectator,
+            ws::probe_room,
+            ws::diagnose_connection,
+            ws::leave_room,
+            ws::get_room_status,
+            ws::set_server_url,
+            ws::get_server_url,
+            ws::clear_server_url,
+            ws::send_chat,
+            ws::set_room_subscription,
+            ws::get_room_subscription,
+            ws::colors::set_member_color,
+            ws::colors::get_member_colors,
+            ws::presets::save_room_preset,
+            ws::presets::list_room_presets,
+            ws::presets::delete_room_preset,
+            ws::presets::join_preset,
+            ws::presets::set_rejoin_last_preset
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                if let Err(e) = watcher::checkpoint::flush(app) {
+                    tracing::warn!("Failed to flush checkpoints on exit: {}", e);
+                }
+                // Exiting the app, e.g. to install an update, closes the
+                // overlay without a CloseRequested
+                windows::save_overlay_bounds(app);
+            }
+        });
 }
@@ -0,0 +1,299 @@
+use crate::event::LogEvent;
+use crate::format;
+use crate::settings;
+use crate::severity::Severity;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+const NOTIFICATION_KINDS_KEY: &str = "notificationKinds";
+const RATE_LIMIT_KEY: &str = "notificationRateLimitSeconds";
+
+/// Default minimum gap between two notifications, so a burst of deaths shows
+/// one toast.
+const DEFAULT_RATE_LIMIT: Duration = Duration::from_secs(5);
+
+pub struct NotificationState {
+    /// Event kinds that notify in addition to anything `Critical`.
+    kinds: Mutex<HashSet<String>>,
+    rate_limit: Mutex<Duration>,
+    limiter: Mutex<Limiter>,
+}
+
+impl Default for NotificationState {
+    fn default() -> Self {
+        NotificationState {
+            kinds: Mutex::default(),
+            rate_limit: Mutex::new(DEFAULT_RATE_LIMIT),
+            limiter: Mutex::default(),
+        }
+    }
+}
+
+/// Shared by every notification, so at most one toast shows per rate-limit
+/// window. Anything that would have notified inside the window is counted and
+/// reported in one summary toast once the window ends.
+#[derive(Default)]
+struct Limiter {
+    last_shown: Option<Instant>,
+    suppressed: usize,
+    /// Set while a summary toast is waiting for the window to end.
+    summary_scheduled: bool,
+}
+
+/// What to do with a batch's notable events.
+#[derive(Debug, PartialEq, Eq)]
+enum Decision {
+    /// Show a toast for the first event, mentioning `more` others.
+    Show { more: usize },
+    /// Hold them for the summary. `schedule_in` is set when no summary is
+    /// waiting yet, to how long is left of the window.
+    Suppress { schedule_in: Option<Duration> },
+}
+
+impl Limiter {
+    /// Decide on `count` notable events arriving at `now`.
+    fn decide(&mut self, now: Instant, count: usize, rate_limit: Duration) -> Decision {
+        let elapsed = self.last_shown.map(|at| now.saturating_duration_since(at));
+        if let Some(elapsed) = elapsed.filter(|elapsed| *elapsed < rate_limit) {
+            self.suppressed += count;
+            let schedule_in = (!std::mem::replace(&mut self.summary_scheduled, true))
+                .then(|| rate_limit - elapsed);
+            return Decision::Suppress { schedule_in };
+        }
+        self.last_shown = Some(now);
+        // Anything still waiting for a summary is folded into this toast instead
+        Decision::Show {
+            more: count - 1 + std::mem::take(&mut self.suppressed),
+        }
+    }
+
+    /// The window has ended: how many events the summary toast covers. A
+    /// summary starts a new window, as any toast does.
+    fn take_summary(&mut self, now: Instant) -> usize {
+        self.summary_scheduled = false;
+        if self.suppressed > 0 {
+            self.last_shown = Some(now);
+        }
+        std::mem::take(&mut self.suppressed)
+    }
+}
+
+/// Whether an event notifies: anything `Critical`, plus the chosen kinds.
+fn is_notable(event: &LogEvent, kinds: &HashSet<String>) -> bool {
+    event.severity == Some(Severity::Critical) || kinds.contains(event.kind_name())
+}
+
+/// Load the persisted kind selection and rate limit. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let state = app.state::<NotificationState>();
+    let kinds: Vec<String> = settings::get(app, NOTIFICATION_KINDS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    *state.kinds.lock().unwrap() = kinds.into_iter().collect();
+
+    if let Ok(Some(seconds)) = settings::get::<f64>(app, RATE_LIMIT_KEY) {
+        *state.rate_limit.lock().unwrap() = Duration::from_secs_f64(seconds.max(0.0));
+    }
+}
+
+#[tauri::command]
+pub fn set_notification_kinds(
+    app: AppHandle,
+    state: State<'_, NotificationState>,
+    kinds: Vec<String>,
+) -> Result<(), String> {
+    settings::set(&app, NOTIFICATION_KINDS_KEY, &kinds)?;
+    *state.kinds.lock().unwrap() = kinds.into_iter().collect();
+    Ok(())
+}
+
+/// Allow at most one notification every `per_seconds` (0 turns the limit
+/// off). Extra notifications inside the window are folded into one summary.
+#[tauri::command]
+pub fn set_notification_rate_limit(
+    app: AppHandle,
+    state: State<'_, NotificationState>,
+    per_seconds: f64,
+) -> Result<(), String> {
+    if !per_seconds.is_finite() || per_seconds < 0.0 {
+        return Err("The rate limit must be zero or more seconds".to_string());
+    }
+    settings::set(&app, RATE_LIMIT_KEY, &per_seconds)?;
+    *state.rate_limit.lock().unwrap() = Duration::from_secs_f64(per_seconds);
+    Ok(())
+}
+
+fn show(app: &AppHandle, body: String) {
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title("Picologs")
+        .body(body)
+        .show()
+    {
+        tracing::warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// Show one "N more events" toast for whatever was suppressed once the current
+/// window has passed.
+fn schedule_summary(app: &AppHandle, wait: Duration) {
+    let app = app.clone();
+    thread::spawn(move || {
+        thread::sleep(wait);
+        let state = app.state::<NotificationState>();
+        let suppressed = state.limiter.lock().unwrap().take_summary(Instant::now());
+        if suppressed > 0 && !main_window_focused(&app) {
+            show(&app, format!("{} more events", suppressed));
+        }
+    });
+}
+
+fn main_window_focused(app: &AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+/// Show a native notification for the first notable event in a batch, if the
+/// user is looking elsewhere and we haven't notified recently.
+pub fn notify_batch(app: &AppHandle, events: &[LogEvent]) {
+    let state = app.state::<NotificationState>();
+    let matching: Vec<&LogEvent> = {
+        let kinds = state.kinds.lock().unwrap();
+        events
+            .iter()
+            .filter(|event| is_notable(event, &kinds))
+            .collect()
+    };
+
+    let Some(first) = matching.first() else {
+        return;
+    };
+    if main_window_focused(app) {
+        return;
+    }
+
+    let rate_limit = *state.rate_limit.lock().unwrap();
+    let decision = state
+        .limiter
+        .lock()
+        .unwrap()
+        .decide(Instant::now(), matching.len(), rate_limit);
+    let more = match decision {
+        Decision::Show { more } => more,
+        Decision::Suppress { schedule_in } => {
+            if let Some(wait) = schedule_in {
+                schedule_summary(app, wait);
+            }
+            return;
+        }
+    };
+
+    let mut body = format::summarize(first);
+    if more > 0 {
+        body.push_str(&format!(" (+{} more)", more));
+    }
+    show(app, body);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventKind;
+
+    const WINDOW: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn the_first_toast_shows_and_the_window_holds_the_rest() {
+        let start = Instant::now();
+        let mut limiter = Limiter::default();
+        assert_eq!(limiter.decide(start, 3, WINDOW), Decision::Show { more: 2 });
+
+        // The first suppressed batch schedules the summary for the window's end
+        assert_eq!(
+            limiter.decide(start + Duration::from_secs(2), 1, WINDOW),
+            Decision::Suppress {
+                schedule_in: Some(Duration::from_secs(3)),
+            }
+        );
+        assert_eq!(
+            limiter.decide(start + Duration::from_secs(4), 2, WINDOW),
+            Decision::Suppress { schedule_in: None }
+        );
+        assert_eq!(limiter.take_summary(start + WINDOW), 3);
+
+        // The summary opened a window of its own
+        assert_eq!(
+            limiter.decide(start + Duration::from_secs(6), 1, WINDOW),
+            Decision::Suppress {
+                schedule_in: Some(Duration::from_secs(4)),
+            }
+        );
+    }
+
+    #[test]
+    fn a_window_with_nothing_held_resets_quietly() {
+        let start = Instant::now();
+        let mut limiter = Limiter::default();
+        limiter.decide(start, 1, WINDOW);
+        assert_eq!(limiter.take_summary(start + WINDOW), 0);
+        assert_eq!(
+            limiter.decide(start + WINDOW, 1, WINDOW),
+            Decision::Show { more: 0 }
+        );
+    }
+
+    #[test]
+    fn held_events_roll_into_the_next_toast_if_it_beats_the_summary() {
+        let start = Instant::now();
+        let mut limiter = Limiter::default();
+        limiter.decide(start, 1, WINDOW);
+        limiter.decide(start + Duration::from_secs(1), 4, WINDOW);
+        assert_eq!(
+            limiter.decide(start + Duration::from_secs(6), 2, WINDOW),
+            Decision::Show { more: 5 }
+        );
+        assert_eq!(limiter.take_summary(start + Duration::from_secs(6)), 0);
+    }
+
+    #[test]
+    fn no_limit_shows_every_batch() {
+        let start = Instant::now();
+        let mut limiter = Limiter::default();
+        for _ in 0..3 {
+            assert_eq!(
+                limiter.decide(start, 1, Duration::ZERO),
+                Decision::Show { more: 0 }
+            );
+        }
+    }
+
+    #[test]
+    fn critical_events_and_chosen_kinds_notify() {
+        let event = |kind, severity| LogEvent {
+            severity,
+            ..LogEvent::test(kind)
+        };
+        let kinds = HashSet::from(["system_quit".to_string()]);
+        assert!(is_notable(&event(EventKind::SystemQuit, None), &kinds));
+        assert!(!is_notable(
+            &event(EventKind::SystemQuit, None),
+            &HashSet::new()
+        ));
+        let crash = EventKind::GameCrash {
+            summary: "Unhandled exception".into(),
+            context: Vec::new(),
+        };
+        assert!(is_notable(
+            &event(crash.clone(), Some(Severity::Critical)),
+            &HashSet::new()
+        ));
+        assert!(!is_notable(&event(crash, Some(Severity::Info)), &kinds));
+    }
+}
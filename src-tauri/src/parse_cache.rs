@@ -0,0 +1,311 @@
+use crate::budget::{Priority, WorkBudget};
+use crate::event::LogEvent;
+use crate::parser;
+use crate::recents;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const PARSE_PROGRESS_EVENT: &str = "parse-progress";
+
+/// `parse-progress` is sent at most this often, and only once at least
+/// `PROGRESS_BYTES` more of the file has been read.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const PROGRESS_BYTES: u64 = 256 * 1024;
+
+/// Identifies one version of a file on disk: if neither the size nor the
+/// modification time changed, the contents are assumed unchanged too.
+#[derive(Debug, Clone, PartialEq)]
+struct FileStamp {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> std::io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileStamp {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified: metadata.modified()?,
+        })
+    }
+}
+
+/// The result of the most recent `parse_log_file`.
+#[derive(Default)]
+pub struct ParseCache(Mutex<Option<(FileStamp, Arc<Vec<LogEvent>>)>>);
+
+/// Cancellation flags of the cancellable parses in progress, by token.
+#[derive(Default)]
+pub struct ParseTokens(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+/// How far a parse has got. The last one sent for a parse has `done` set,
+/// including when it was cancelled or answered from the cache.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseProgress {
+    pub path: String,
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+    pub events_so_far: usize,
+    pub done: bool,
+}
+
+/// Decides when the next progress report is due.
+struct ProgressThrottle {
+    last_at: Instant,
+    last_bytes: u64,
+}
+
+impl ProgressThrottle {
+    fn new(now: Instant) -> Self {
+        ProgressThrottle {
+            last_at: now,
+            last_bytes: 0,
+        }
+    }
+
+    fn due(&mut self, bytes: u64, now: Instant) -> bool {
+        if bytes < self.last_bytes + PROGRESS_BYTES
+            || now.duration_since(self.last_at) < PROGRESS_INTERVAL
+        {
+            return false;
+        }
+        self.last_at = now;
+        self.last_bytes = bytes;
+        true
+    }
+}
+
+fn emit_progress(app: &AppHandle, progress: &ParseProgress) {
+    if let Err(e) = app.emit(PARSE_PROGRESS_EVENT, progress) {
+        tracing::warn!("Failed to emit {}: {}", PARSE_PROGRESS_EVENT, e);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParsedLog {
+    pub events: Vec<LogEvent>,
+    /// Set when the file hadn't changed since the last parse and the earlier
+    /// result was returned without reading it again.
+    pub from_cache: bool,
+    /// Set when the parse was cancelled; `events` is then only the start of
+    /// the log.
+    pub cancelled: bool,
+}
+
+#[cfg(test)]
+fn parse_cached(cache: &ParseCache, path: &Path) -> std::io::Result<ParsedLog> {
+    parse_cached_unless(cache, path, &AtomicBool::new(false), |_| {})
+}
+
+/// Parse a log through the cache, stopping early if `cancel` is set, and
+/// passing progress to `report`. A cancelled parse isn't cached.
+fn parse_cached_unless(
+    cache: &ParseCache,
+    path: &Path,
+    cancel: &AtomicBool,
+    mut report: impl FnMut(&ParseProgress),
+) -> std::io::Result<ParsedLog> {
+    let stamp = FileStamp::of(path)?;
+    let mut progress = ParseProgress {
+        path: path.to_string_lossy().to_string(),
+        bytes_read: 0,
+        total_bytes: stamp.size,
+        events_so_far: 0,
+        done: false,
+    };
+    if let Some((cached, events)) = cache.0.lock().unwrap().as_ref() {
+        if *cached == stamp {
+            report(&ParseProgress {
+                bytes_read: stamp.size,
+                events_so_far: events.len(),
+                done: true,
+                ..progress
+            });
+            return Ok(ParsedLog {
+                events: events.to_vec(),
+                from_cache: true,
+                cancelled: false,
+            });
+        }
+    }
+
+    let mut throttle = ProgressThrottle::new(Instant::now());
+    let (events, cancelled) = parser::parse_file_cancellable(path, cancel, |bytes, events| {
+        progress.bytes_read = bytes;
+        progress.events_so_far = events;
+        if throttle.due(bytes, Instant::now()) {
+            report(&progress);
+        }
+    })?;
+    if !cancelled {
+        progress.bytes_read = stamp.size;
+        *cache.0.lock().unwrap() = Some((stamp, Arc::new(events.clone())));
+    }
+    progress.events_so_far = events.len();
+    progress.done = true;
+    report(&progress);
+
+    Ok(ParsedLog {
+        events,
+        from_cache: false,
+        cancelled,
+    })
+}
+
+/// Parse a whole log. Calling this again for a file whose size and mtime
+/// haven't changed returns the previous result without touching the disk.
+/// Progress is sent as `parse-progress` while it runs. Waits for a slot in
+/// the parse budget first.
+#[tauri::command]
+pub async fn parse_log_file(app: AppHandle, path: String) -> Result<ParsedLog, String> {
+    recents::record(&app, &path);
+    tauri::async_runtime::spawn_blocking(move || {
+        let budget = app.state::<WorkBudget>();
+        let _permit = budget.acquire(Priority::Background);
+        parse_cached_unless(
+            &app.state::<ParseCache>(),
+            &PathBuf::from(path),
+            &AtomicBool::new(false),
+            |progress| emit_progress(&app, progress),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// `parse_log_file`, cancellable with `cancel_parse(token_id)`. A cancelled
+/// parse returns what it had read so far with `cancelled` set.
+#[tauri::command]
+pub async fn parse_log_file_cancellable(
+    app: AppHandle,
+    tokens: State<'_, ParseTokens>,
+    path: String,
+    token_id: String,
+) -> Result<ParsedLog, String> {
+    recents::record(&app, &path);
+    let cancel = Arc::new(AtomicBool::new(false));
+    tokens
+        .0
+        .lock()
+        .unwrap()
+        .insert(token_id.clone(), cancel.clone());
+
+    let parse_app = app.clone();
+    let parsed = tauri::async_runtime::spawn_blocking(move || {
+        let budget = parse_app.state::<WorkBudget>();
+        let _permit = budget.acquire(Priority::Background);
+        parse_cached_unless(
+            &parse_app.state::<ParseCache>(),
+            &PathBuf::from(path),
+            &cancel,
+            |progress| emit_progress(&parse_app, progress),
+        )
+    })
+    .await;
+    tokens.0.lock().unwrap().remove(&token_id);
+    parsed
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Returns whether a parse with that token was running.
+#[tauri::command]
+pub fn cancel_parse(tokens: State<'_, ParseTokens>, token_id: String) -> bool {
+    match tokens.0.lock().unwrap().get(&token_id) {
+        Some(cancel) => {
+            cancel.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+#[tauri::command]
+pub fn clear_parse_cache(cache: State<'_, ParseCache>) {
+    cache.0.lock().unwrap().take();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_the_result_until_the_file_changes() {
+        let path = std::env::temp_dir().join(format!("picologs-cache-{}.log", std::process::id()));
+        let quit = "<2024-06-07T12:34:56.789Z> [Notice] <SystemQuit> CSystem::Quit invoked\n";
+        fs::write(&path, quit).unwrap();
+        let cache = ParseCache::default();
+
+        let first = parse_cached(&cache, &path).unwrap();
+        assert!(!first.from_cache);
+        let second = parse_cached(&cache, &path).unwrap();
+        assert!(second.from_cache);
+        assert_eq!(second.events, first.events);
+
+        fs::write(&path, quit.repeat(2)).unwrap();
+        let third = parse_cached(&cache, &path).unwrap();
+        assert!(!third.from_cache);
+        assert_eq!(third.events.len(), 2);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_cancelled_parse_stops_early_and_isnt_cached() {
+        let path = std::env::temp_dir().join(format!("picologs-cancel-{}.log", std::process::id()));
+        let quit = "<2024-06-07T12:34:56.789Z> [Notice] <SystemQuit> CSystem::Quit invoked\n";
+        fs::write(&path, quit.repeat(10_000)).unwrap();
+        let cache = ParseCache::default();
+
+        let cancelled = parse_cached_unless(&cache, &path, &AtomicBool::new(true), |_| {}).unwrap();
+        assert!(cancelled.cancelled);
+        assert!(cancelled.events.len() < 10_000);
+        // The file was closed, so it can be removed straight away on any OS
+        let complete = parse_cached(&cache, &path).unwrap();
+        assert!(!complete.from_cache && !complete.cancelled);
+        assert_eq!(complete.events.len(), 10_000);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn progress_is_throttled_and_ends_with_the_whole_file() {
+        let start = Instant::now();
+        let mut throttle = ProgressThrottle::new(start);
+        assert!(!throttle.due(PROGRESS_BYTES, start));
+        assert!(!throttle.due(PROGRESS_BYTES - 1, start + PROGRESS_INTERVAL));
+        assert!(throttle.due(PROGRESS_BYTES, start + PROGRESS_INTERVAL));
+        assert!(!throttle.due(2 * PROGRESS_BYTES, start + PROGRESS_INTERVAL));
+
+        let path =
+            std::env::temp_dir().join(format!("picologs-progress-{}.log", std::process::id()));
+        let quit = "<2024-06-07T12:34:56.789Z> [Notice] <SystemQuit> CSystem::Quit invoked\n";
+        fs::write(&path, quit.repeat(3)).unwrap();
+        let mut reports = Vec::new();
+        let cache = ParseCache::default();
+        for _ in 0..2 {
+            parse_cached_unless(&cache, &path, &AtomicBool::new(false), |progress| {
+                reports.push(progress.clone())
+            })
+            .unwrap();
+        }
+        let total = quit.len() as u64 * 3;
+        let finals: Vec<_> = reports
+            .iter()
+            .map(|p| (p.bytes_read, p.total_bytes, p.events_so_far, p.done))
+            .collect();
+        // The one from the cache is still reported
+        assert_eq!(finals, [(total, total, 3, true), (total, total, 3, true)]);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,1033 @@
+use crate::catalog;
+use crate::cause;
+use crate::event::{EventKind, LogEvent};
+use chrono::NaiveDate;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+
+// Game.log writes either `<2024-06-07T12:34:56.789Z>` or the older `<2024.06.07-12:34:56:789>`
+static TIMESTAMP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^<(\d{4})[.-](\d{2})[.-](\d{2})[T-](\d{2}):(\d{2}):(\d{2})(?:[.:](\d{1,3}))?Z?>")
+        .unwrap()
+});
+
+static CHARACTER_NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"- name (\S+)").unwrap());
+static CHARACTER_GEID: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:geid |EntityId\[)(\d+)").unwrap());
+
+static ACTOR_DEATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"'([^']+)' \[(\d+)\] in zone '([^']+)' killed by '([^']+)' \[(\d+)\] using '([^']+)' \[Class ([^\]]+)\] with damage type '([^']+)'",
+    )
+    .unwrap()
+});
+
+static VEHICLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"Vehicle '([^']+)' \[(\d+)\]").unwrap());
+static VEHICLE_ZONE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"in zone '([^']+)'").unwrap());
+static VEHICLE_DRIVER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"driven by '([^']+)'").unwrap());
+static VEHICLE_CAUSE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"caused by '([^']+)'").unwrap());
+static VEHICLE_DAMAGE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"caused by '[^']+' \[\d+\] with '([^']+)'").unwrap());
+// Older builds: `destroyLevel from 'None' to 'HardDeath'`, newer: `advanced from destroy level 0 to 1`
+static DESTROY_LEVEL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:destroyLevel from '([^']+)' to '([^']+)'|from destroy level (\d+) to (\d+))")
+        .unwrap()
+});
+
+static CONTROL_VEHICLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"'([A-Za-z0-9_]+_\d+)' \[(\d+)\]").unwrap());
+
+static INVENTORY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"Player\[([^\]]+)\] Location\[([^\]]+)\]").unwrap());
+
+static SHARD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"shard\[([^\]]+)\]").unwrap());
+// `pub_euw1b_...`: the region is the letters and digits before the zone letter
+static SHARD_REGION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-z]+_([a-z]+\d+)[a-z]?_").unwrap());
+
+// `[OnHandleHit] Fake hit FROM Attacker TO ANVL_Arrow_123 ...`; names can't contain dots,
+// so a full stop straight after the target isn't taken as part of it
+static DAMAGE_HIT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"FROM ([^\s.]+) TO ([^\s.]+)").unwrap());
+// Unconfirmed: no hit line carrying an amount or a damage type has been seen.
+// `damage: 35.5` and `damageType: 'Ballistic'` are guesses, so both are
+// usually left empty.
+static DAMAGE_AMOUNT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bdamage[:=]?\s*(\d+(?:\.\d+)?)").unwrap());
+static DAMAGE_TYPE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)damage ?type[:=]?\s*'?([A-Za-z_]+)").unwrap());
+
+static ENTER_ZONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"OnEntityEnterZone - InstancedInterior \[([^\]]+)\].*?-> Entity \[([^\]]+)\]")
+        .unwrap()
+});
+
+// Unconfirmed: no log on hand has an `<AttachmentReceived>` line, so
+// `Player[..] Attachment[<item>, <class>, <id>] Status[..] Port[<slot>]` is a
+// guess and may not match what the game writes.
+static ATTACHMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"Player\[([^\]]+)\] Attachment\[([^,\]]+)[^\]]*\].*?Port\[([^\]]+)\]").unwrap()
+});
+
+// The first line of a crash report, tagged by the crash handler itself. The
+// lines after it (exception code, call stack) are detail, not more crashes.
+// Unconfirmed: no crashed session's log is on hand, so the `<CrashHandler>`
+// tag and its `Unhandled exception`/`Fatal error` headline are a guess.
+static CRASH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:<[^>]*>\s*)?(?:\[[^\]]*\]\s*)?<CrashHandler>\s*((?i:unhandled exception|fatal error)\b.*)",
+    )
+    .unwrap()
+});
+
+/// How much of a crash line goes into `GameCrash::summary`.
+const CRASH_SUMMARY_CHARS: usize = 200;
+/// How many preceding lines go into `GameCrash::context`.
+const CRASH_CONTEXT_LINES: usize = 10;
+/// How long before a kill a hit on the victim earns its source an assist.
+const ASSIST_WINDOW_MS: i64 = 15_000;
+/// How many lines `parse_file_cancellable` reads between checks of its flag.
+const CANCEL_CHECK_LINES: usize = 4096;
+
+/// A line as the matchers expect it: without a byte order mark (a log saved
+/// by a Windows editor starts with one) or a line ending of any kind.
+fn normalize(line: &str) -> &str {
+    line.trim_start_matches('\u{feff}')
+        .trim_end_matches(['\r', '\n'])
+}
+
+/// Split a chunk read up to `\n` at any lone `\r` in it, for logs with old Mac
+/// line endings or a mix. Each piece keeps its line ending and comes with its
+/// byte offset in the chunk.
+pub fn split_lines(chunk: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (i, byte) in chunk.iter().enumerate() {
+        if *byte == b'\r' && chunk.get(i + 1) != Some(&b'\n') {
+            lines.push((start, &chunk[start..=i]));
+            start = i + 1;
+        }
+    }
+    if start < chunk.len() {
+        lines.push((start, &chunk[start..]));
+    }
+    lines
+}
+
+/// Parse the leading `<...>` timestamp of a log line into Unix milliseconds.
+pub fn parse_timestamp(line: &str) -> Option<i64> {
+    let caps = TIMESTAMP.captures(normalize(line))?;
+    let num = |i: usize| caps.get(i).and_then(|m| m.as_str().parse::<u32>().ok());
+
+    let date = NaiveDate::from_ymd_opt(num(1)? as i32, num(2)?, num(3)?)?;
+    // Pad the millisecond field the same way the frontend did ("7" -> 700)
+    let millis = caps
+        .get(7)
+        .map(|m| format!("{:0<3}", m.as_str()).parse::<u32>().unwrap_or(0))
+        .unwrap_or(0);
+    let time = date.and_hms_milli_opt(num(4)?, num(5)?, num(6)?, millis)?;
+
+    Some(time.and_utc().timestamp_millis())
+}
+
+/// The rulesets a log can be parsed with. There is only one so far: it reads
+/// every known line format, old and new, without looking at the log's build.
+pub const PROFILES: [&str; 1] = ["default"];
+
+/// The profile used unless another is chosen.
+pub const DEFAULT_PROFILE: &str = PROFILES[0];
+
+/// Check a profile name against `PROFILES`.
+pub fn check_profile(name: &str) -> Result<(), String> {
+    if PROFILES.contains(&name) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown parser profile {:?}; known profiles: {}",
+            name,
+            PROFILES.join(", ")
+        ))
+    }
+}
+
+fn capture(re: &Regex, line: &str, group: usize) -> Option<String> {
+    re.captures(line)
+        .and_then(|caps| caps.get(group))
+        .map(|m| m.as_str().to_string())
+}
+
+/// Parse a single Game.log line. Returns `None` for lines that aren't a
+/// recognised event. Damage hits are only parsed when `damage_ticks` is set.
+///
+/// ```
+/// use picologs_lib::{parse_line, EventKind};
+///
+/// let line = "<2024-06-07T12:34:56.789Z> [Notice] <Actor Death> CActor::Kill: 'Pilot' \
+///     [200000000001] in zone 'Stanton_Crusader' killed by 'Enemy-Player' [200000000002] \
+///     using 'behr_rifle_ballistic_01_123' [Class behr_rifle_ballistic_01] with damage type \
+///     'Bullet' from direction x: 1, y: 0, z: 0";
+/// let event = parse_line(line, false).unwrap();
+/// assert_eq!(event.timestamp_ms, Some(1_717_763_696_789));
+/// match event.kind {
+///     EventKind::ActorDeath { victim, killer, .. } => {
+///         assert_eq!((victim.as_str(), killer.as_str()), ("Pilot", "Enemy-Player"));
+///     }
+///     other => panic!("unexpected {:?}", other),
+/// }
+///
+/// assert!(parse_line("<2024-06-07T12:34:56.789Z> [Notice] Loading...", false).is_none());
+/// ```
+pub fn parse_line(line: &str, damage_ticks: bool) -> Option<LogEvent> {
+    let line = normalize(line);
+    let mut kind = parse_kind(line, damage_ticks)?;
+    catalog::resolve_names(&mut kind);
+    cause::resolve_cause(&mut kind);
+
+    Some(LogEvent {
+        timestamp_ms: parse_timestamp(line),
+        kind,
+        severity: None,
+        sources: Vec::new(),
+        reporter: None,
+    })
+}
+
+fn parse_kind(line: &str, damage_ticks: bool) -> Option<EventKind> {
+    if line.contains("AccountLoginCharacterStatus_Character") {
+        return Some(EventKind::PlayerIdentified {
+            handle: capture(&CHARACTER_NAME, line, 1)?,
+            geid: capture(&CHARACTER_GEID, line, 1),
+        });
+    }
+
+    if line.contains("<Actor Death>") {
+        let caps = ACTOR_DEATH.captures(line)?;
+        let group = |i: usize| caps.get(i).map(|m| m.as_str().to_string());
+        return Some(EventKind::ActorDeath {
+            victim: group(1)?,
+            victim_geid: group(2),
+            zone: group(3),
+            killer: group(4)?,
+            killer_geid: group(5),
+            weapon: group(6),
+            weapon_name: None,
+            weapon_class: group(7),
+            damage_type: group(8),
+            cause_category: None,
+            assists: Vec::new(),
+            interior: None,
+            carrying: Vec::new(),
+        });
+    }
+
+    if line.contains("<Vehicle Destruction>") {
+        let levels = DESTROY_LEVEL.captures(line);
+        let level = |named: usize, numeric: usize| {
+            levels
+                .as_ref()
+                .and_then(|caps| caps.get(named).or_else(|| caps.get(numeric)))
+                .map(|m| m.as_str().to_string())
+        };
+        return Some(EventKind::VehicleDestruction {
+            vehicle: capture(&VEHICLE, line, 1)?,
+            vehicle_id: capture(&VEHICLE, line, 2),
+            vehicle_name: None,
+            zone: capture(&VEHICLE_ZONE, line, 1),
+            driver: capture(&VEHICLE_DRIVER, line, 1),
+            cause: capture(&VEHICLE_CAUSE, line, 1),
+            destroy_level_from: level(1, 3),
+            destroy_level_to: level(2, 4),
+            damage_type: capture(&VEHICLE_DAMAGE, line, 1),
+        });
+    }
+
+    if line.contains("<Vehicle Control Flow>") {
+        return Some(EventKind::VehicleControl {
+            vehicle: capture(&CONTROL_VEHICLE, line, 1)?,
+            vehicle_id: capture(&CONTROL_VEHICLE, line, 2),
+            vehicle_name: None,
+        });
+    }
+
+    if line.contains("<RequestLocationInventory>") {
+        return Some(EventKind::LocationChange {
+            player: capture(&INVENTORY, line, 1)?,
+            location: capture(&INVENTORY, line, 2)?,
+        });
+    }
+
+    if line.contains("<Join PU>") {
+        let shard = capture(&SHARD, line, 1)?;
+        return Some(EventKind::ShardConnected {
+            region: capture(&SHARD_REGION, &shard, 1),
+            shard,
+        });
+    }
+
+    if line.contains("<SystemQuit>") {
+        return Some(EventKind::SystemQuit);
+    }
+
+    if line.contains("OnEntityEnterZone") {
+        return Some(EventKind::LocationTransition {
+            player: capture(&ENTER_ZONE, line, 2)?,
+            from: None,
+            to: capture(&ENTER_ZONE, line, 1)?,
+        });
+    }
+
+    if line.contains("<AttachmentReceived>") {
+        return Some(EventKind::LoadoutChange {
+            player: capture(&ATTACHMENT, line, 1)?,
+            slot: capture(&ATTACHMENT, line, 3)?,
+            item: capture(&ATTACHMENT, line, 2)?,
+            item_name: None,
+        });
+    }
+
+    // Ahead of the crash regex, as most lines written during a fight are hits
+    if damage_ticks && line.contains("[OnHandleHit]") {
+        return Some(EventKind::DamageTick {
+            source: capture(&DAMAGE_HIT, line, 1)?,
+            target: capture(&DAMAGE_HIT, line, 2)?,
+            amount: capture(&DAMAGE_AMOUNT, line, 1).and_then(|amount| amount.parse().ok()),
+            damage_type: capture(&DAMAGE_TYPE, line, 1),
+        });
+    }
+
+    if let Some(message) = capture(&CRASH, line, 1) {
+        return Some(EventKind::GameCrash {
+            summary: message
+                .trim_end()
+                .chars()
+                .take(CRASH_SUMMARY_CHARS)
+                .collect(),
+            context: Vec::new(),
+        });
+    }
+
+    None
+}
+
+/// A hit from a `DamageTick`, kept for crediting assists.
+#[derive(Debug)]
+struct Hit {
+    timestamp_ms: i64,
+    source: String,
+    target: String,
+}
+
+/// Parses a log line by line, keeping what single lines can't show: the lines
+/// before a crash, whether the session already crashed, recent hits for
+/// assists, the interior each player last entered and what they have
+/// equipped. Use a fresh one for each session of a log.
+#[derive(Debug, Default)]
+pub struct LineParser {
+    recent: VecDeque<String>,
+    crashed: bool,
+    damage_ticks: bool,
+    hits: VecDeque<Hit>,
+    location_transitions: bool,
+    interiors: HashMap<String, String>,
+    loadout_changes: bool,
+    /// The handle from the session's `PlayerIdentified`.
+    local_handle: Option<String>,
+    /// Item names by slot for the local player.
+    loadout: BTreeMap<String, String>,
+}
+
+impl LineParser {
+    /// A parser that also yields `DamageTick` events, which are off by default.
+    pub fn with_damage_ticks(damage_ticks: bool) -> Self {
+        LineParser {
+            damage_ticks,
+            ..LineParser::default()
+        }
+    }
+
+    /// Start over for a new session, keeping the options.
+    pub fn reset(&mut self) {
+        *self = LineParser {
+            location_transitions: self.location_transitions,
+            loadout_changes: self.loadout_changes,
+            ..LineParser::with_damage_ticks(self.damage_ticks)
+        };
+    }
+
+    pub fn set_damage_ticks(&mut self, damage_ticks: bool) {
+        self.damage_ticks = damage_ticks;
+    }
+
+    /// Whether to yield `LocationTransition` events, which are off by
+    /// default. Interiors are tracked for deaths either way.
+    pub fn set_location_transitions(&mut self, location_transitions: bool) {
+        self.location_transitions = location_transitions;
+    }
+
+    /// Whether to yield `LoadoutChange` events, which are off by default.
+    /// Deaths are annotated with what the victim carried either way.
+    pub fn set_loadout_changes(&mut self, loadout_changes: bool) {
+        self.loadout_changes = loadout_changes;
+    }
+
+    fn is_local(&self, player: &str) -> bool {
+        self.local_handle
+            .as_deref()
+            .is_some_and(|local| local.eq_ignore_ascii_case(player))
+    }
+
+    /// Record what the local player equipped, or note on their death what
+    /// they were carrying; they respawn without it. Other players' loadouts
+    /// aren't kept, as the log only reliably shows the local one. Returns
+    /// false for a change that shouldn't be yielded.
+    fn track_loadouts(&mut self, event: &mut LogEvent) -> bool {
+        match &mut event.kind {
+            EventKind::PlayerIdentified { handle, .. } => {
+                if !self.is_local(handle) {
+                    self.loadout.clear();
+                }
+                self.local_handle = Some(handle.clone());
+                true
+            }
+            EventKind::LoadoutChange {
+                player,
+                slot,
+                item,
+                item_name,
+            } => {
+                if self.is_local(player) {
+                    self.loadout
+                        .insert(slot.clone(), item_name.clone().unwrap_or(item.clone()));
+                }
+                self.loadout_changes
+            }
+            EventKind::ActorDeath {
+                victim, carrying, ..
+            } => {
+                if self.is_local(victim) {
+                    *carrying = std::mem::take(&mut self.loadout).into_values().collect();
+                }
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// Record where a player went, or place a death in the victim's interior.
+    /// Returns false for a transition that shouldn't be yielded.
+    fn track_interiors(&mut self, event: &mut LogEvent) -> bool {
+        match &mut event.kind {
+            EventKind::LocationTransition { player, from, to } => {
+                *from = self.interiors.insert(player.clone(), to.clone());
+                self.location_transitions
+            }
+            EventKind::ActorDeath {
+                victim, interior, ..
+            } => {
+                *interior = self.interiors.get(victim).cloned();
+                true
+            }
+            _ => true,
+        }
+    }
+
+    fn forget_hits_before(&mut self, timestamp_ms: i64) {
+        while self
+            .hits
+            .front()
+            .is_some_and(|hit| hit.timestamp_ms < timestamp_ms - ASSIST_WINDOW_MS)
+        {
+            self.hits.pop_front();
+        }
+    }
+
+    /// Remember a hit, or credit a kill's assists from the hits before it.
+    fn track_hits(&mut self, event: &mut LogEvent) {
+        let Some(timestamp_ms) = event.timestamp_ms else {
+            return;
+        };
+        match &mut event.kind {
+            EventKind::DamageTick { source, target, .. } => {
+                self.forget_hits_before(timestamp_ms);
+                self.hits.push_back(Hit {
+                    timestamp_ms,
+                    source: source.clone(),
+                    target: target.clone(),
+                });
+            }
+            EventKind::ActorDeath {
+                victim,
+                killer,
+                assists,
+                ..
+            } => {
+                self.forget_hits_before(timestamp_ms);
+                for hit in &self.hits {
+                    let credited = hit.target == *victim
+                        && hit.source != *killer
+                        && hit.source != *victim
+                        && !assists.contains(&hit.source);
+                    if credited {
+                        assists.push(hit.source.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Parse one line. A crash yields the `GameCrash` with its context, then a
+    /// `SystemQuit` to close the session; the rest of the crash trail is
+    /// ignored.
+    pub fn parse(&mut self, line: &str) -> Vec<LogEvent> {
+        let parsed = parse_line(line, self.damage_ticks);
+        let context: Vec<String> = self.recent.iter().cloned().collect();
+        if self.recent.len() == CRASH_CONTEXT_LINES {
+            self.recent.pop_front();
+        }
+        self.recent
+            .push_back(normalize(line).trim_end().to_string());
+
+        let Some(mut event) = parsed else {
+            return Vec::new();
+        };
+        if self.damage_ticks {
+            self.track_hits(&mut event);
+        }
+        if !self.track_interiors(&mut event) || !self.track_loadouts(&mut event) {
+            return Vec::new();
+        }
+        let EventKind::GameCrash {
+            context: crash_context,
+            ..
+        } = &mut event.kind
+        else {
+            return vec![event];
+        };
+        if std::mem::replace(&mut self.crashed, true) {
+            return Vec::new();
+        }
+
+        *crash_context = context;
+        crash_context.push(normalize(line).trim_end().to_string());
+        let quit = LogEvent {
+            timestamp_ms: event.timestamp_ms,
+            kind: EventKind::SystemQuit,
+            severity: None,
+            sources: Vec::new(),
+            reporter: None,
+        };
+        vec![event, quit]
+    }
+}
+
+/// Parse every recognised event in a log file, in file order.
+pub fn parse_file(path: &Path) -> std::io::Result<Vec<LogEvent>> {
+    let mut events = Vec::new();
+    for_each_event(path, |event| {
+        events.push(event);
+        Ok(())
+    })?;
+    Ok(events)
+}
+
+/// Stream the events in a log file to `on_event` one at a time, in file
+/// order, without collecting them. Stops at the first error either side.
+pub fn for_each_event(
+    path: &Path,
+    mut on_event: impl FnMut(LogEvent) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    for_each_event_with_line(path, LineParser::default(), |event, _, _| on_event(event))
+}
+
+/// `for_each_event` with `parser`'s options, also passing the line each event
+/// came from (without its line ending) and the byte offset the line starts at.
+pub fn for_each_event_with_line(
+    path: &Path,
+    mut parser: LineParser,
+    mut on_event: impl FnMut(LogEvent, &str, u64) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    for_each_line(path, |line, offset| {
+        for event in parser.parse(line) {
+            on_event(event, line.trim_end_matches(['\r', '\n']), offset)?;
+        }
+        Ok(true)
+    })?;
+    Ok(())
+}
+
+/// Parse a whole log like `parse_file`, giving up if `cancel` is set. The flag
+/// is checked every `CANCEL_CHECK_LINES` lines; returns the events parsed so
+/// far and whether it was cancelled. `on_progress` is called before each line
+/// with the bytes read up to it and the events found so far.
+pub fn parse_file_cancellable(
+    path: &Path,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(u64, usize),
+) -> std::io::Result<(Vec<LogEvent>, bool)> {
+    let mut parser = LineParser::default();
+    let mut events = Vec::new();
+    let mut lines = 0usize;
+    let finished = for_each_line(path, |line, offset| {
+        on_progress(offset, events.len());
+        lines += 1;
+        if lines.is_multiple_of(CANCEL_CHECK_LINES) && cancel.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        events.extend(parser.parse(line));
+        Ok(true)
+    })?;
+    Ok((events, !finished))
+}
+
+/// Call `on_line` with each line of a file (line ending included) and the
+/// byte offset it starts at, until it returns `false`. Returns whether every
+/// line was read.
+fn for_each_line(
+    path: &Path,
+    mut on_line: impl FnMut(&str, u64) -> std::io::Result<bool>,
+) -> std::io::Result<bool> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = Vec::new();
+    let mut offset = 0;
+
+    // Game.log isn't guaranteed to be valid UTF-8, so read raw lines and decode lossily
+    loop {
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        for (start, line) in split_lines(&buf) {
+            if !on_line(&String::from_utf8_lossy(line), offset + start as u64)? {
+                return Ok(false);
+            }
+        }
+        offset += read as u64;
+        buf.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cause::CauseCategory;
+    use crate::event::TestDeath;
+
+    #[test]
+    fn parses_files_with_a_bom_and_mixed_line_endings() {
+        let quit = "<2024-06-07T12:34:56.789Z> [Notice] <SystemQuit> CSystem::Quit invoked";
+        let path =
+            std::env::temp_dir().join(format!("picologs-endings-{}.log", std::process::id()));
+        let contents = format!("\u{feff}{quit}\r\n{quit}\r{quit}\n{quit}");
+        std::fs::write(&path, &contents).unwrap();
+
+        let mut offsets = Vec::new();
+        for_each_event_with_line(&path, LineParser::default(), |event, raw, offset| {
+            assert!(event.timestamp_ms.is_some());
+            assert!(contents.as_bytes()[offset as usize..].starts_with(raw.as_bytes()));
+            offsets.push(offset);
+            Ok(())
+        })
+        .unwrap();
+        let bom = '\u{feff}'.len_utf8() as u64;
+        let line = quit.len() as u64;
+        assert_eq!(
+            offsets,
+            [0, bom + line + 2, bom + 2 * line + 3, bom + 3 * line + 4]
+        );
+        assert!(parse_line(&format!("\u{feff}{quit}\r\n"), false).is_some());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parses_both_timestamp_formats() {
+        let iso = parse_timestamp("<2024-06-07T12:34:56.789Z> [Notice] something");
+        let legacy = parse_timestamp("<2024.06.07-12:34:56:789> something");
+        assert_eq!(iso, Some(1_717_763_696_789));
+        assert_eq!(iso, legacy);
+        assert_eq!(parse_timestamp("no timestamp here"), None);
+    }
+
+    #[test]
+    fn parses_actor_death() {
+        let line = "<2024.06.07-12:34:56:789> <Actor Death> CActor::Kill: 'VictimPlayer' [12345] in zone 'Stanton_Crusader' killed by 'KillerPlayer' [67890] using 'wpn_rifle_ballistic_01' [Class Ballistic_Rifle] with damage type 'Ballistic' from direction x: 1.0, y: 0.5, z: -0.3";
+        let event = parse_line(line, false).unwrap();
+        assert_eq!(
+            event.kind,
+            TestDeath {
+                victim_geid: Some("12345".into()),
+                zone: Some("Stanton_Crusader".into()),
+                killer_geid: Some("67890".into()),
+                weapon: Some("wpn_rifle_ballistic_01".into()),
+                weapon_class: Some("Ballistic_Rifle".into()),
+                damage_type: Some("Ballistic".into()),
+                cause_category: Some(CauseCategory::Pvp),
+                ..TestDeath::new("VictimPlayer", "KillerPlayer")
+            }
+            .into()
+        );
+    }
+
+    #[test]
+    fn parses_shard_connections() {
+        let line = "<2025-01-10T19:32:37.810Z> [Notice] <Join PU> address[34.86.12.34] port[64319] shard[pub_euw1b_9335932_110] locationId[-281470681677823] [Team_GameServices][Session][Join]";
+        assert_eq!(
+            parse_line(line, false).unwrap().kind,
+            EventKind::ShardConnected {
+                shard: "pub_euw1b_9335932_110".into(),
+                region: Some("euw1".into()),
+            }
+        );
+
+        let opaque =
+            "<2025-01-10T19:32:37.810Z> [Notice] <Join PU> shard[9335932] [Team_GameServices]";
+        assert_eq!(
+            parse_line(opaque, false).unwrap().kind,
+            EventKind::ShardConnected {
+                shard: "9335932".into(),
+                region: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_both_vehicle_destruction_formats() {
+        let legacy = "<2024.06.07-12:34:56:789> <Vehicle Destruction> Vehicle 'AEGS_Gladius_12345' [12345] caused by 'EnemyPlayer' [67890] destroyLevel from 'None' to 'HardDeath'";
+        let current = "<2024-06-07T12:34:56.789Z> [Notice] <Vehicle Destruction> CVehicle::OnAdvanceDestroyLevel: Vehicle 'ANVL_Arrow_2729096379834' [2729096379834] in zone 'OOC_Stanton_1_Hurston' [pos x: 1.0, y: 2.0, z: 3.0 vel x: 0.0, y: 0.0, z: 0.0] driven by 'Pilot' [200000000001] advanced from destroy level 1 to 2 caused by 'EnemyPlayer' [200000000002] with 'Combat' [Team_CGP4][Vehicle]";
+
+        match parse_line(legacy, false).unwrap().kind {
+            EventKind::VehicleDestruction {
+                vehicle,
+                cause,
+                destroy_level_to,
+                ..
+            } => {
+                assert_eq!(vehicle, "AEGS_Gladius_12345");
+                assert_eq!(cause.as_deref(), Some("EnemyPlayer"));
+                assert_eq!(destroy_level_to.as_deref(), Some("HardDeath"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        match parse_line(current, false).unwrap().kind {
+            EventKind::VehicleDestruction {
+                zone,
+                driver,
+                destroy_level_from,
+                destroy_level_to,
+                damage_type,
+                ..
+            } => {
+                assert_eq!(zone.as_deref(), Some("OOC_Stanton_1_Hurston"));
+                assert_eq!(driver.as_deref(), Some("Pilot"));
+                assert_eq!(destroy_level_from.as_deref(), Some("1"));
+                assert_eq!(destroy_level_to.as_deref(), Some("2"));
+                assert_eq!(damage_type.as_deref(), Some("Combat"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    const HIT_LINES: &str = "\
+<2024-06-07T12:34:56.100Z> [Notice] <Debug Hostility Events> [OnHandleHit] Fake hit FROM Attacker TO ANVL_Arrow_2729096379834. Being sent to child Pilot [Team_CGP4][Hostility]
+<2024-06-07T12:34:56.200Z> [Notice] <Debug Hostility Events> [OnHandleHit] Hit FROM Attacker TO Pilot damage: 12.5 damageType: Ballistic [Team_CGP4][Hostility]
+";
+
+    const TRANSITION_LINES: &str = "\
+<2024-06-07T12:34:50.000Z> [Notice] <CEntityComponentInstancedInterior::OnEntityEnterZone> [InstancedInterior] OnEntityEnterZone - InstancedInterior [Hangar_LrgTop_003] [4612549562] -> Entity [Pilot] [200000000001] -- m_openDoors[0], m_managerGEID[4612549561] [Team_CGP4][Interiors]
+<2024-06-07T12:34:52.000Z> [Notice] <CEntityComponentInstancedInterior::OnEntityEnterZone> [InstancedInterior] OnEntityEnterZone - InstancedInterior [TransitCarriage_Elevator_1] [4612549570] -> Entity [Pilot] [200000000001] -- m_openDoors[1], m_managerGEID[4612549569] [Team_CGP4][Interiors]
+<2024-06-07T12:34:56.789Z> [Notice] <Actor Death> CActor::Kill: 'Pilot' [200000000001] in zone 'Stanton_Crusader' killed by 'Enemy-Player' [200000000002] using 'behr_rifle_ballistic_01_123' [Class behr_rifle_ballistic_01] with damage type 'Bullet' from direction x: 1, y: 0, z: 0
+";
+
+    #[test]
+    fn tracks_interiors_and_emits_transitions_only_when_enabled() {
+        let interior_of_death = |parser: &mut LineParser| {
+            let events: Vec<LogEvent> = TRANSITION_LINES
+                .lines()
+                .flat_map(|line| parser.parse(line))
+                .collect();
+            let Some(EventKind::ActorDeath { interior, .. }) = events.last().map(|e| &e.kind)
+            else {
+                panic!("expected the death last");
+            };
+            (events.len(), interior.clone())
+        };
+
+        let mut default = LineParser::default();
+        assert_eq!(
+            interior_of_death(&mut default),
+            (1, Some("TransitCarriage_Elevator_1".into()))
+        );
+
+        let mut parser = LineParser::default();
+        parser.set_location_transitions(true);
+        let transitions: Vec<EventKind> = TRANSITION_LINES
+            .lines()
+            .take(2)
+            .flat_map(|line| parser.parse(line))
+            .map(|event| event.kind)
+            .collect();
+        assert_eq!(
+            transitions,
+            [
+                EventKind::LocationTransition {
+                    player: "Pilot".into(),
+                    from: None,
+                    to: "Hangar_LrgTop_003".into(),
+                },
+                EventKind::LocationTransition {
+                    player: "Pilot".into(),
+                    from: Some("Hangar_LrgTop_003".into()),
+                    to: "TransitCarriage_Elevator_1".into(),
+                },
+            ]
+        );
+        // A new session forgets where everyone was but keeps the option
+        parser.reset();
+        let first = parser.parse(TRANSITION_LINES.lines().nth(1).unwrap());
+        assert!(matches!(
+            &first[..],
+            [LogEvent {
+                kind: EventKind::LocationTransition { from: None, .. },
+                ..
+            }]
+        ));
+    }
+
+    const LOADOUT_LINES: &str = "\
+<2024.06.07-12:34:30:000> AccountLoginCharacterStatus_Character - name Pilot EntityId[1234567890]
+<2024-06-07T12:34:40.000Z> [Notice] <AttachmentReceived> Player[Pilot] Attachment[behr_rifle_ballistic_01_4554328452, behr_rifle_ballistic_01, 4554328452] Status[persistent] Port[wep_stocked_2] Elapsed[0.742] [Team_ActorFeatures][Inventory]
+<2024-06-07T12:34:41.000Z> [Notice] <AttachmentReceived> Player[Pilot] Attachment[cds_armor_medium_torso_01_4554328460, cds_armor_medium_torso_01, 4554328460] Status[persistent] Port[Armor_Torso] Elapsed[0.743] [Team_ActorFeatures][Inventory]
+<2024-06-07T12:34:56.789Z> [Notice] <Actor Death> CActor::Kill: 'Pilot' [200000000001] in zone 'Stanton_Crusader' killed by 'Enemy-Player' [200000000002] using 'behr_rifle_ballistic_01_123' [Class behr_rifle_ballistic_01] with damage type 'Bullet' from direction x: 1, y: 0, z: 0
+";
+
+    #[test]
+    fn notes_the_loadout_on_deaths_and_emits_changes_only_when_enabled() {
+        let mut default = LineParser::default();
+        let events: Vec<LogEvent> = LOADOUT_LINES
+            .lines()
+            .flat_map(|line| default.parse(line))
+            .collect();
+        let [_, LogEvent {
+            kind: EventKind::ActorDeath { carrying, .. },
+            ..
+        }] = &events[..]
+        else {
+            panic!("expected only the death, got {:?}", events);
+        };
+        assert_eq!(
+            carrying,
+            &[
+                "cds_armor_medium_torso_01_4554328460",
+                "behr_rifle_ballistic_01_4554328452",
+            ]
+        );
+
+        let mut parser = LineParser::default();
+        parser.set_loadout_changes(true);
+        parser.parse(LOADOUT_LINES.lines().next().unwrap());
+        assert_eq!(
+            parser.parse(LOADOUT_LINES.lines().nth(1).unwrap())[0].kind,
+            EventKind::LoadoutChange {
+                player: "Pilot".into(),
+                slot: "wep_stocked_2".into(),
+                item: "behr_rifle_ballistic_01_4554328452".into(),
+                item_name: None,
+            }
+        );
+        // The death strips the loadout, so the next one carries nothing
+        let death = LOADOUT_LINES.lines().nth(3).unwrap();
+        parser.parse(death);
+        let events = parser.parse(death);
+        assert!(matches!(
+            &events[0].kind,
+            EventKind::ActorDeath { carrying, .. } if carrying.is_empty()
+        ));
+    }
+
+    #[test]
+    fn only_the_local_players_loadout_is_noted_on_deaths() {
+        // Without knowing who the local player is, nobody's loadout is kept
+        let mut anonymous = LineParser::default();
+        let events: Vec<LogEvent> = LOADOUT_LINES
+            .lines()
+            .skip(1)
+            .flat_map(|line| anonymous.parse(line))
+            .collect();
+        assert!(matches!(
+            &events[..],
+            [LogEvent {
+                kind: EventKind::ActorDeath { carrying, .. },
+                ..
+            }] if carrying.is_empty()
+        ));
+
+        // Someone else's equipment doesn't follow them to their death
+        let mut parser = LineParser::default();
+        parser.parse(LOADOUT_LINES.lines().next().unwrap());
+        let other = LOADOUT_LINES
+            .lines()
+            .nth(1)
+            .unwrap()
+            .replace("Pilot", "Wingman");
+        parser.parse(&other);
+        let death = LOADOUT_LINES
+            .lines()
+            .nth(3)
+            .unwrap()
+            .replace("'Pilot'", "'Wingman'");
+        assert!(matches!(
+            &parser.parse(&death)[..],
+            [LogEvent {
+                kind: EventKind::ActorDeath { carrying, .. },
+                ..
+            }] if carrying.is_empty()
+        ));
+    }
+
+    #[test]
+    fn parses_damage_ticks_only_when_enabled() {
+        let mut default = LineParser::default();
+        assert!(HIT_LINES.lines().all(|line| default.parse(line).is_empty()));
+        assert!(HIT_LINES
+            .lines()
+            .all(|line| parse_line(line, false).is_none()));
+
+        let mut parser = LineParser::with_damage_ticks(true);
+        let ticks: Vec<EventKind> = HIT_LINES
+            .lines()
+            .flat_map(|line| parser.parse(line))
+            .map(|event| event.kind)
+            .collect();
+        assert_eq!(
+            ticks,
+            [
+                EventKind::DamageTick {
+                    source: "Attacker".into(),
+                    target: "ANVL_Arrow_2729096379834".into(),
+                    amount: None,
+                    damage_type: None,
+                },
+                EventKind::DamageTick {
+                    source: "Attacker".into(),
+                    target: "Pilot".into(),
+                    amount: Some(12.5),
+                    damage_type: Some("Ballistic".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_known_profiles_are_accepted() {
+        assert!(check_profile(DEFAULT_PROFILE).is_ok());
+        assert!(check_profile("Default").is_err());
+        assert!(check_profile("4.0").is_err());
+    }
+
+    #[test]
+    fn credits_assists_from_recent_hits_on_the_victim() {
+        let hit = |at: &str, source: &str| {
+            format!("<2024-06-07T12:{}Z> [Notice] <Debug Hostility Events> [OnHandleHit] Hit FROM {} TO Victim damage: 10 [Team_CGP4][Hostility]", at, source)
+        };
+        let lines = [
+            hit("33:00.000", "Stale"),
+            hit("34:50.000", "Wingman"),
+            hit("34:51.000", "Killer"),
+            hit("34:52.000", "Wingman"),
+            hit("34:53.000", "Gunner"),
+            "<2024-06-07T12:34:56.789Z> <Actor Death> CActor::Kill: 'Victim' [1] in zone 'Stanton' killed by 'Killer' [2] using 'wpn' [Class Rifle] with damage type 'Bullet'".to_string(),
+        ];
+
+        let assists_with = |damage_ticks| {
+            let mut parser = LineParser::with_damage_ticks(damage_ticks);
+            let events: Vec<LogEvent> = lines.iter().flat_map(|line| parser.parse(line)).collect();
+            match &events.last().unwrap().kind {
+                EventKind::ActorDeath { assists, .. } => assists.clone(),
+                other => panic!("unexpected {:?}", other),
+            }
+        };
+        assert_eq!(assists_with(true), ["Wingman", "Gunner"]);
+        // Without hits to go on, nobody is credited
+        assert!(assists_with(false).is_empty());
+    }
+
+    #[test]
+    fn parses_player_identified_and_ignores_noise() {
+        let line = "<2024.06.07-12:34:56:789> AccountLoginCharacterStatus_Character - name TestPlayer EntityId[1234567890]";
+        assert_eq!(
+            parse_line(line, false).unwrap().kind,
+            EventKind::PlayerIdentified {
+                handle: "TestPlayer".into(),
+                geid: Some("1234567890".into()),
+            }
+        );
+        assert!(parse_line("<2024.06.07-12:34:56:789> Event data", false).is_none());
+    }
+
+    const CRASH_BLOCK: &str = "\
+<2024-06-07T12:40:01.100Z> [Notice] <Vehicle Control Flow> CVehicleMovementBase::SetDriver: Local client node [1] requesting control token for 'AEGS_Gladius_123' [456]
+<2024-06-07T12:40:02.200Z> [Notice] <Context Establisher Done> establisher=\"Network\" runningTime=0.5
+<2024-06-07T12:40:03.300Z> [Error] <CrashHandler> Unhandled exception 0xC0000005 (EXCEPTION_ACCESS_VIOLATION) at StarCitizen.exe+0x1a2b3c
+<2024-06-07T12:40:03.301Z> [Error] <CrashHandler> Exception code: C0000005, thread 12
+<2024-06-07T12:40:03.302Z> [Error] <CrashHandler> Call stack: StarCitizen.exe+0x1a2b3c
+";
+
+    #[test]
+    fn parses_a_crash_block_once_with_context() {
+        let mut parser = LineParser::default();
+        let events: Vec<LogEvent> = CRASH_BLOCK
+            .lines()
+            .flat_map(|line| parser.parse(line))
+            .collect();
+
+        let kinds: Vec<&str> = events.iter().map(|e| e.kind_name()).collect();
+        assert_eq!(kinds, ["vehicle_control", "game_crash", "system_quit"]);
+
+        let EventKind::GameCrash { summary, context } = &events[1].kind else {
+            panic!("expected a crash");
+        };
+        assert_eq!(
+            summary,
+            "Unhandled exception 0xC0000005 (EXCEPTION_ACCESS_VIOLATION) at StarCitizen.exe+0x1a2b3c"
+        );
+        assert_eq!(context.len(), 3);
+        assert!(context[0].contains("AEGS_Gladius_123"));
+        assert!(context[2].contains("Unhandled exception"));
+        assert_eq!(events[2].timestamp_ms, events[1].timestamp_ms);
+
+        // The report's other lines aren't crashes on their own either
+        let crashes = CRASH_BLOCK
+            .lines()
+            .filter_map(|line| parse_line(line, false))
+            .filter(|event| event.kind_name() == "game_crash")
+            .count();
+        assert_eq!(crashes, 1);
+    }
+
+    #[test]
+    fn ordinary_lines_mentioning_crash_words_are_not_crashes() {
+        let lines = [
+            "<2024-06-07T12:40:01.100Z> [Notice] <Shader Cache> Fatal error loading shader, falling back to default",
+            "<2024-06-07T12:40:01.200Z> [Warning] <Lua> Unhandled exception in mission script MissionBroker.lua",
+            "<2024-06-07T12:40:01.300Z> [Notice] <Telemetry> last session exception code: none",
+            "<2024-06-07T12:40:01.400Z> [Notice] <Chat> Pilot: anyone else get a <Crash> today?",
+            "<2024-06-07T12:40:01.500Z> [Error] <CrashHandler> Exception code: C0000005, thread 12",
+        ];
+        for line in lines {
+            assert_eq!(parse_line(line, false), None, "{}", line);
+        }
+
+        let mut parser = LineParser::default();
+        assert!(lines.iter().all(|line| parser.parse(line).is_empty()));
+    }
+}
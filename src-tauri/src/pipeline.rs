@@ -0,0 +1,267 @@
+use crate::activity;
+use crate::buffer::EventBuffer;
+use crate::event::LogEvent;
+use crate::identities;
+use crate::notifications;
+use crate::settings;
+use crate::shard;
+use crate::streak;
+use crate::webhooks;
+use crate::windows;
+use crate::ws;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+/// Event name the frontend listens on for newly parsed events.
+pub const LOG_BATCH_EVENT: &str = "log-batch";
+
+const LOCAL_EMIT_KEY: &str = "localEmit";
+const ROOM_SHARE_KEY: &str = "roomShare";
+
+/// Where parsed events go. Local display and room sharing are switched
+/// independently; either way events are still buffered and the watch keeps
+/// advancing, so nothing is lost while one is off.
+pub struct EmitState {
+    local: AtomicBool,
+    room: AtomicBool,
+    /// Events stamped before this are dropped. Kept for this run of the app
+    /// only.
+    cutoff: Mutex<Option<i64>>,
+}
+
+impl Default for EmitState {
+    fn default() -> Self {
+        EmitState {
+            local: AtomicBool::new(true),
+            room: AtomicBool::new(true),
+            cutoff: Mutex::new(None),
+        }
+    }
+}
+
+/// Drop events from before `cutoff`. Events exactly at it are kept, as are
+/// untimed ones, which can't be placed either side of it.
+fn retain_from(events: &mut Vec<LogEvent>, cutoff: i64) {
+    events.retain(|event| event.timestamp_ms.is_none_or(|at| at >= cutoff));
+}
+
+/// Drop events from before the tracking cutoff, if one is set. The watcher
+/// calls this before capping a backfill, so the cap counts only events that
+/// would be shown.
+pub fn apply_cutoff(app: &AppHandle, events: &mut Vec<LogEvent>) {
+    if let Some(cutoff) = *app.state::<EmitState>().cutoff.lock().unwrap() {
+        retain_from(events, cutoff);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmitSettings {
+    pub local_emit: bool,
+    pub room_share: bool,
+}
+
+/// Load the persisted toggles. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let state = app.state::<EmitState>();
+    for (key, flag) in [
+        (LOCAL_EMIT_KEY, &state.local),
+        (ROOM_SHARE_KEY, &state.room),
+    ] {
+        let enabled = settings::get(app, key).ok().flatten().unwrap_or(true);
+        flag.store(enabled, Ordering::Relaxed);
+    }
+}
+
+/// Where a batch of events came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Origin {
+    /// A watched log, as it's being played.
+    Live,
+    /// A log played back with `replay_log`, which already happened.
+    Replay,
+    /// A watch catching up on what a log already held, when it starts or
+    /// after the log was truncated.
+    Backfill,
+}
+
+/// What a batch is delivered to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Delivery {
+    /// The feed buffer, identities, shard and streak records.
+    record: bool,
+    /// This app's windows.
+    display: bool,
+    /// Notifications.
+    alert: bool,
+    /// Registered webhooks.
+    webhooks: bool,
+    /// The joined room.
+    room: bool,
+}
+
+/// A replay is only shown: it isn't shared, alerted on or folded into
+/// anything that outlives it. A backfill is recorded and shown, but it's
+/// history, so it isn't shared or alerted on either.
+fn delivery(origin: Origin, local: bool, room: bool) -> Delivery {
+    let live = origin == Origin::Live;
+    Delivery {
+        record: origin != Origin::Replay,
+        display: local,
+        alert: live && local,
+        webhooks: live,
+        room: live && room,
+    }
+}
+
+/// Deliver a batch of freshly parsed events to the frontend and the room.
+pub fn emit_batch(app: &AppHandle, events: Vec<LogEvent>) {
+    deliver(app, events, Origin::Live);
+}
+
+/// Deliver the events a watch read while catching up: buffered and shown,
+/// but not sent to webhooks or the room, nor notified about.
+pub fn emit_backfill_batch(app: &AppHandle, events: Vec<LogEvent>) {
+    deliver(app, events, Origin::Backfill);
+}
+
+/// Show a batch of replayed events in this app's windows, leaving the feed,
+/// the room, webhooks, notifications and stats alone.
+pub fn emit_replay_batch(app: &AppHandle, events: Vec<LogEvent>) {
+    deliver(app, events, Origin::Replay);
+}
+
+fn deliver(app: &AppHandle, mut events: Vec<LogEvent>, origin: Origin) {
+    apply_cutoff(app, &mut events);
+    if events.is_empty() {
+        return;
+    }
+
+    let state = app.state::<EmitState>();
+    let delivery = delivery(
+        origin,
+        state.local.load(Ordering::Relaxed),
+        state.room.load(Ordering::Relaxed),
+    );
+    if delivery.record {
+        app.state::<EventBuffer>().push_batch(&events);
+        identities::observe_batch(app, &events);
+        shard::observe_batch(app, &events);
+        streak::observe_batch(app, &events);
+    }
+    if delivery.webhooks {
+        webhooks::fire_batch(app, &events);
+    }
+    if delivery.alert {
+        notifications::notify_batch(app, &events);
+    }
+    if delivery.display {
+        windows::emit_batch(app, &events);
+        activity::observe_batch(app, &events);
+    }
+    if delivery.room {
+        ws::share_batch(app, &events);
+    }
+}
+
+/// Stop or resume sending parsed events to this app's windows (and
+/// notifications). Room sharing is unaffected.
+#[tauri::command]
+pub fn set_local_emit(
+    app: AppHandle,
+    state: State<'_, EmitState>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings::set(&app, LOCAL_EMIT_KEY, &enabled)?;
+    state.local.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Stop or resume forwarding parsed events to the joined room. Local display is
+/// unaffected.
+#[tauri::command]
+pub fn set_room_share(
+    app: AppHandle,
+    state: State<'_, EmitState>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings::set(&app, ROOM_SHARE_KEY, &enabled)?;
+    state.room.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Ignore everything stamped before `timestamp_ms`, live or backfilled, until
+/// the cutoff is cleared or the app restarts.
+#[tauri::command]
+pub fn set_tracking_cutoff(state: State<'_, EmitState>, timestamp_ms: i64) {
+    *state.cutoff.lock().unwrap() = Some(timestamp_ms);
+}
+
+/// Returns whether a cutoff was set.
+#[tauri::command]
+pub fn clear_tracking_cutoff(state: State<'_, EmitState>) -> bool {
+    state.cutoff.lock().unwrap().take().is_some()
+}
+
+#[tauri::command]
+pub fn get_emit_settings(state: State<'_, EmitState>) -> EmitSettings {
+    EmitSettings {
+        local_emit: state.local.load(Ordering::Relaxed),
+        room_share: state.room.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventKind;
+
+    #[test]
+    fn cutoff_keeps_events_at_the_cutoff() {
+        let mut events = vec![
+            LogEvent::test(EventKind::SystemQuit).at(999),
+            LogEvent::test(EventKind::SystemQuit).at(1_000),
+            LogEvent::test(EventKind::SystemQuit).at(None),
+            LogEvent::test(EventKind::SystemQuit).at(1_001),
+        ];
+        retain_from(&mut events, 1_000);
+        let times: Vec<_> = events.iter().map(|event| event.timestamp_ms).collect();
+        assert_eq!(times, [Some(1_000), None, Some(1_001)]);
+    }
+
+    #[test]
+    fn replays_leave_identities_streaks_and_sharing_alone() {
+        let replay = delivery(Origin::Replay, true, true);
+        assert_eq!(
+            replay,
+            Delivery {
+                record: false,
+                display: true,
+                alert: false,
+                webhooks: false,
+                room: false,
+            }
+        );
+        assert!(!delivery(Origin::Replay, false, true).display);
+
+        let live = delivery(Origin::Live, false, true);
+        assert!(live.record && live.webhooks && live.room);
+        assert!(!live.display && !live.alert);
+    }
+
+    #[test]
+    fn backfills_are_recorded_and_shown_but_not_sent_anywhere() {
+        assert_eq!(
+            delivery(Origin::Backfill, true, true),
+            Delivery {
+                record: true,
+                display: true,
+                alert: false,
+                webhooks: false,
+                room: false,
+            }
+        );
+        assert!(!delivery(Origin::Backfill, false, true).display);
+    }
+}
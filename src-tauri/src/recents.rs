@@ -0,0 +1,111 @@
+use crate::settings;
+use crate::ENVIRONMENTS;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+const RECENT_LOGS_KEY: &str = "recentLogs";
+const MAX_RECENT_LOGS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Opened {
+    path: String,
+    opened_at_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentLog {
+    pub path: String,
+    /// The environment folder the log is in, e.g. `PTU`, when it's in one.
+    pub environment: Option<String>,
+    pub opened_at_ms: i64,
+    pub exists: bool,
+}
+
+/// Move `path` to the front of the list, dropping the oldest past the cap.
+fn push(recent: &mut Vec<Opened>, path: &str, opened_at_ms: i64) {
+    recent.retain(|opened| opened.path != path);
+    recent.insert(
+        0,
+        Opened {
+            path: path.to_string(),
+            opened_at_ms,
+        },
+    );
+    recent.truncate(MAX_RECENT_LOGS);
+}
+
+fn environment_of(path: &Path) -> Option<String> {
+    let folder = path.parent()?.file_name()?.to_str()?;
+    ENVIRONMENTS
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(folder))
+        .map(|name| name.to_string())
+}
+
+fn load(app: &AppHandle) -> Vec<Opened> {
+    settings::get(app, RECENT_LOGS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Note that a log was just watched or parsed.
+pub fn record(app: &AppHandle, path: &str) {
+    let mut recent = load(app);
+    push(&mut recent, path, chrono::Utc::now().timestamp_millis());
+    if let Err(e) = settings::set(app, RECENT_LOGS_KEY, &recent) {
+        tracing::warn!("Failed to persist {}: {}", RECENT_LOGS_KEY, e);
+    }
+}
+
+/// Logs recently watched or parsed, most recent first. Logs that have since
+/// been deleted are kept but flagged, as a new session may recreate them.
+#[tauri::command]
+pub fn get_recent_logs(app: AppHandle) -> Vec<RecentLog> {
+    load(&app)
+        .into_iter()
+        .map(|opened| {
+            let path = Path::new(&opened.path);
+            RecentLog {
+                environment: environment_of(path),
+                exists: path.is_file(),
+                opened_at_ms: opened.opened_at_ms,
+                path: opened.path,
+            }
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn clear_recent_logs(app: AppHandle) -> Result<(), String> {
+    settings::remove(&app, RECENT_LOGS_KEY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_most_recent_logs_first_without_duplicates() {
+        let mut recent = Vec::new();
+        for (i, path) in ["a", "b", "a", "c"].into_iter().enumerate() {
+            push(&mut recent, path, i as i64);
+        }
+        let order: Vec<(&str, i64)> = recent
+            .iter()
+            .map(|opened| (opened.path.as_str(), opened.opened_at_ms))
+            .collect();
+        assert_eq!(order, [("c", 3), ("a", 2), ("b", 1)]);
+
+        for i in 0..20 {
+            push(&mut recent, &i.to_string(), i);
+        }
+        assert_eq!(recent.len(), MAX_RECENT_LOGS);
+        assert_eq!(recent[0].path, "19");
+
+        let ptu = Path::new("C:/Roberts Space Industries/StarCitizen/ptu/Game.log");
+        assert_eq!(environment_of(ptu), Some("PTU".to_string()));
+        assert_eq!(environment_of(Path::new("/tmp/Game.log")), None);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::event::{EventKind, LogEvent};
+use crate::parser;
+use crate::pipeline;
+use crate::severity::classify_severity;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const REPLAY_FINISHED_EVENT: &str = "replay-finished";
+
+/// How often a sleeping replay checks whether it was stopped.
+const STOP_CHECK: Duration = Duration::from_millis(100);
+
+/// Slowest and fastest replay speeds. Below the minimum a long gap would
+/// overflow the wait.
+const MIN_SPEED: f64 = 0.01;
+const MAX_SPEED: f64 = 1000.0;
+
+/// The replay in progress, if any. Only one runs at a time.
+#[derive(Default)]
+pub struct ReplayState(Mutex<Option<Arc<AtomicBool>>>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayFinished {
+    pub path: String,
+    pub emitted: usize,
+    pub cancelled: bool,
+}
+
+/// Group events stamped at the same moment into one batch, each with how long
+/// to wait after the previous batch. Untimed events go out with the batch
+/// before them.
+fn schedule(events: Vec<LogEvent>, speed: f64) -> Vec<(Duration, Vec<LogEvent>)> {
+    let mut batches: Vec<(Duration, Vec<LogEvent>)> = Vec::new();
+    let mut last: Option<i64> = None;
+
+    for event in events {
+        let starts_batch = match (event.timestamp_ms, last) {
+            (Some(at), Some(previous)) => at != previous,
+            (Some(_), None) => true,
+            (None, _) => batches.is_empty(),
+        };
+        if starts_batch {
+            let gap_ms = match (event.timestamp_ms, last) {
+                (Some(at), Some(previous)) => at.saturating_sub(previous).max(0) as f64,
+                _ => 0.0,
+            };
+            batches.push((Duration::from_secs_f64(gap_ms / 1000.0 / speed), Vec::new()));
+        }
+        last = event.timestamp_ms.or(last);
+        batches.last_mut().unwrap().1.push(event);
+    }
+    batches
+}
+
+/// Sleep for `duration` unless stopped first. Returns whether it was stopped.
+fn wait(duration: Duration, stop: &AtomicBool) -> bool {
+    let until = Instant::now() + duration;
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            return true;
+        }
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return false;
+        }
+        thread::sleep(left.min(STOP_CHECK));
+    }
+}
+
+/// Parse a log and classify its events the way a watch would.
+fn read_events(path: &Path) -> Result<Vec<LogEvent>, String> {
+    let mut events = parser::parse_file(path).map_err(|e| e.to_string())?;
+    let mut local_handle: Option<String> = None;
+    for event in &mut events {
+        if let EventKind::PlayerIdentified { handle, .. } = &event.kind {
+            local_handle = Some(handle.clone());
+        }
+        event.severity = Some(classify_severity(event, local_handle.as_deref()));
+    }
+    Ok(events)
+}
+
+fn check_speed(speed: f64) -> Result<(), String> {
+    if (MIN_SPEED..=MAX_SPEED).contains(&speed) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Replay speed must be between {} and {}, got {}",
+            MIN_SPEED, MAX_SPEED, speed
+        ))
+    }
+}
+
+/// Play a log back to this app's windows as if it were being watched, with the
+/// original gaps between events divided by `speed` (2.0 plays twice as fast).
+/// Nothing is shared, notified on or kept: the room, webhooks, the feed
+/// buffer, identities and streaks never see a replay. Replaces any replay
+/// already running. Returns how many events will be
+/// played; `replay-finished` is sent at the end or when stopped.
+#[tauri::command]
+pub async fn replay_log(app: AppHandle, path: String, speed: f64) -> Result<usize, String> {
+    check_speed(speed)?;
+    let read_path = path.clone();
+    let (total, batches) = tauri::async_runtime::spawn_blocking(move || {
+        let events = read_events(Path::new(&read_path))?;
+        Ok::<_, String>((events.len(), schedule(events, speed)))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let state = app.state::<ReplayState>();
+    if let Some(previous) = state.0.lock().unwrap().replace(stop.clone()) {
+        previous.store(true, Ordering::Relaxed);
+    }
+
+    tracing::info!("Replaying {} events from {} at {}x", total, path, speed);
+    thread::spawn(move || {
+        let mut emitted = 0;
+        let mut cancelled = false;
+        for (delay, batch) in batches {
+            if wait(delay, &stop) {
+                cancelled = true;
+                break;
+            }
+            emitted += batch.len();
+            pipeline::emit_replay_batch(&app, batch);
+        }
+
+        // Let stop_replay report false once this one is done
+        let state = app.state::<ReplayState>();
+        let mut current = state.0.lock().unwrap();
+        if current
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, &stop))
+        {
+            *current = None;
+        }
+        drop(current);
+
+        let finished = ReplayFinished {
+            path,
+            emitted,
+            cancelled,
+        };
+        if let Err(e) = app.emit(REPLAY_FINISHED_EVENT, finished) {
+            tracing::warn!("Failed to emit {}: {}", REPLAY_FINISHED_EVENT, e);
+        }
+    });
+    Ok(total)
+}
+
+/// Returns whether a replay was running.
+#[tauri::command]
+pub fn stop_replay(state: State<'_, ReplayState>) -> bool {
+    match state.0.lock().unwrap().take() {
+        Some(stop) => {
+            stop.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaces_batches_by_timestamp_over_speed() {
+        let events = vec![
+            LogEvent::test(EventKind::SystemQuit).at(1_000),
+            LogEvent::test(EventKind::SystemQuit).at(1_000),
+            LogEvent::test(EventKind::SystemQuit).at(None),
+            LogEvent::test(EventKind::SystemQuit).at(3_000),
+            LogEvent::test(EventKind::SystemQuit).at(2_000),
+        ];
+        let batches: Vec<(Duration, usize)> = schedule(events, 2.0)
+            .into_iter()
+            .map(|(delay, batch)| (delay, batch.len()))
+            .collect();
+        assert_eq!(
+            batches,
+            [
+                (Duration::ZERO, 3),
+                (Duration::from_secs(1), 1),
+                // Out-of-order lines don't wait
+                (Duration::ZERO, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_speeds_a_long_gap_would_overflow_at() {
+        assert!(check_speed(MIN_SPEED).is_ok());
+        assert!(check_speed(MAX_SPEED).is_ok());
+        for speed in [0.0, 1e-300, -1.0, 1001.0, f64::NAN, f64::INFINITY] {
+            assert!(check_speed(speed).is_err(), "{}", speed);
+        }
+    }
+}
@@ -0,0 +1,621 @@
+use crate::catalog;
+use crate::cause::{self, CauseCategory};
+use crate::correlate::correlate_kills;
+use crate::event::{event_fingerprint, EventKind, LogEvent};
+use crate::format::{self, DisplayZone};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LeaderboardEntry {
+    pub handle: String,
+    pub kills: usize,
+    pub deaths: usize,
+    /// Kills someone else landed the last hit on; see `ActorDeath::assists`.
+    pub assists: usize,
+}
+
+/// Combine several members' feeds into one timeline: exact duplicates are
+/// dropped, and the two sides of the same kill are merged with
+/// `correlate_kills`. The result is in chronological order, untimed events last.
+pub fn merge_sources(sources: Vec<Vec<LogEvent>>) -> Vec<LogEvent> {
+    let mut seen = HashSet::new();
+    let mut events: Vec<LogEvent> = sources
+        .into_iter()
+        .flatten()
+        .filter(|event| seen.insert(event_fingerprint(event)))
+        .collect();
+    events.sort_by_key(|event| (event.timestamp_ms.is_none(), event.timestamp_ms));
+    correlate_kills(events)
+}
+
+/// Kills, deaths and assists per player, most kills first. Suicides count as a
+/// death only, and the game's `unknown` placeholder is left out.
+pub fn leaderboard(events: &[LogEvent]) -> Vec<LeaderboardEntry> {
+    let mut totals: HashMap<&str, (usize, usize, usize)> = HashMap::new();
+    for event in events {
+        let EventKind::ActorDeath {
+            victim,
+            killer,
+            assists,
+            ..
+        } = &event.kind
+        else {
+            continue;
+        };
+        if victim != "unknown" {
+            totals.entry(victim).or_default().1 += 1;
+        }
+        if killer != victim && killer != "unknown" {
+            totals.entry(killer).or_default().0 += 1;
+        }
+        for assist in assists {
+            totals.entry(assist).or_default().2 += 1;
+        }
+    }
+
+    let mut entries: Vec<LeaderboardEntry> = totals
+        .into_iter()
+        .map(|(handle, (kills, deaths, assists))| LeaderboardEntry {
+            handle: handle.to_string(),
+            kills,
+            deaths,
+            assists,
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.kills
+            .cmp(&a.kills)
+            .then(a.deaths.cmp(&b.deaths))
+            .then_with(|| a.handle.cmp(&b.handle))
+    });
+    entries
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WeaponStat {
+    pub weapon: String,
+    pub kills: usize,
+}
+
+/// A readable name for the weapon of a kill: the catalog name where there is
+/// one, otherwise the raw class with its entity id dropped.
+fn weapon_label(weapon: Option<&str>, weapon_name: Option<&str>) -> String {
+    let Some(raw) = weapon.filter(|raw| *raw != "unknown") else {
+        return "unknown".to_string();
+    };
+    if let Some(name) = weapon_name
+        .map(String::from)
+        .or_else(|| catalog::weapon_name(raw))
+    {
+        return name;
+    }
+    match raw.rsplit_once('_') {
+        Some((class, id)) if id.parse::<u64>().is_ok() => class.to_string(),
+        _ => raw.to_string(),
+    }
+}
+
+/// Kills per weapon, most first and ties by name, optionally only those where
+/// `handle` was the killer. Events are deduped by fingerprint first so a kill
+/// shared by several members counts once; suicides aren't kills.
+pub fn weapon_breakdown(events: &[LogEvent], handle: Option<&str>) -> Vec<WeaponStat> {
+    let mut seen = HashSet::new();
+    let mut totals: HashMap<String, usize> = HashMap::new();
+
+    for event in events {
+        let EventKind::ActorDeath {
+            victim,
+            killer,
+            weapon,
+            weapon_name,
+            ..
+        } = &event.kind
+        else {
+            continue;
+        };
+        if killer == victim || handle.is_some_and(|handle| !killer.eq_ignore_ascii_case(handle)) {
+            continue;
+        }
+        if !seen.insert(event_fingerprint(event)) {
+            continue;
+        }
+        *totals
+            .entry(weapon_label(weapon.as_deref(), weapon_name.as_deref()))
+            .or_default() += 1;
+    }
+
+    let mut stats: Vec<WeaponStat> = totals
+        .into_iter()
+        .map(|(weapon, kills)| WeaponStat { weapon, kills })
+        .collect();
+    stats.sort_by(|a, b| b.kills.cmp(&a.kills).then_with(|| a.weapon.cmp(&b.weapon)));
+    stats
+}
+
+/// How many deaths each cause accounts for.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DeathCauses {
+    pub pvp: usize,
+    pub pve: usize,
+    pub environmental: usize,
+    pub suicide: usize,
+    pub unknown: usize,
+}
+
+/// Deaths by cause, optionally only `handle`'s. Deduped by fingerprint like
+/// `weapon_breakdown`; events from before `cause_category` existed are
+/// classified here.
+pub fn death_causes(events: &[LogEvent], handle: Option<&str>) -> DeathCauses {
+    let mut seen = HashSet::new();
+    let mut causes = DeathCauses::default();
+
+    for event in events {
+        let EventKind::ActorDeath {
+            victim,
+            killer,
+            damage_type,
+            cause_category,
+            ..
+        } = &event.kind
+        else {
+            continue;
+        };
+        if handle.is_some_and(|handle| !victim.eq_ignore_ascii_case(handle)) {
+            continue;
+        }
+        if !seen.insert(event_fingerprint(event)) {
+            continue;
+        }
+        let category = cause_category
+            .unwrap_or_else(|| cause::classify_cause(victim, killer, damage_type.as_deref()));
+        *match category {
+            CauseCategory::Pvp => &mut causes.pvp,
+            CauseCategory::Pve => &mut causes.pve,
+            CauseCategory::Environmental => &mut causes.environmental,
+            CauseCategory::Suicide => &mut causes.suicide,
+            CauseCategory::Unknown => &mut causes.unknown,
+        } += 1;
+    }
+    causes
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShipStat {
+    pub ship: String,
+    /// Kills by the player while flying this ship.
+    pub kills: usize,
+    /// Times a ship of this kind was destroyed under them.
+    pub losses: usize,
+}
+
+/// Kills and losses per ship for `handle`, most kills first, then fewest
+/// losses, then by name. The ship a kill was made in is the one the log last
+/// showed the player taking control of; after they die or lose it, kills go
+/// under `unknown` until they board another. Control events other members
+/// shared don't count, as they name someone else's ship. Each vehicle is
+/// lost once however many destruction stages it goes through.
+pub fn ship_performance(events: &[LogEvent], handle: &str) -> Vec<ShipStat> {
+    let mut seen = HashSet::new();
+    let mut lost = HashSet::new();
+    let mut totals: HashMap<String, (usize, usize)> = HashMap::new();
+    // Raw entity and label of the ship the player is in
+    let mut active: Option<(String, String)> = None;
+
+    for event in events {
+        if !seen.insert(event_fingerprint(event)) {
+            continue;
+        }
+        match &event.kind {
+            EventKind::VehicleControl {
+                vehicle,
+                vehicle_name,
+                ..
+            } if event.reporter.is_none() => {
+                // Vehicles resolve through the same catalog as weapons do
+                let label = weapon_label(Some(vehicle), vehicle_name.as_deref());
+                active = Some((vehicle.clone(), label));
+            }
+            EventKind::ActorDeath { victim, killer, .. } => {
+                if victim.eq_ignore_ascii_case(handle) {
+                    active = None;
+                } else if killer.eq_ignore_ascii_case(handle) {
+                    let ship = active.as_ref().map_or("unknown", |(_, label)| label);
+                    totals.entry(ship.to_string()).or_default().0 += 1;
+                }
+            }
+            EventKind::VehicleDestruction {
+                vehicle,
+                vehicle_name,
+                driver,
+                ..
+            } => {
+                let flying = active.as_ref().is_some_and(|(raw, _)| raw == vehicle);
+                let driving = driver
+                    .as_deref()
+                    .is_some_and(|driver| driver.eq_ignore_ascii_case(handle));
+                if !(flying || driving) || !lost.insert(vehicle.clone()) {
+                    continue;
+                }
+                let ship = weapon_label(Some(vehicle), vehicle_name.as_deref());
+                totals.entry(ship).or_default().1 += 1;
+                if flying {
+                    active = None;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut stats: Vec<ShipStat> = totals
+        .into_iter()
+        .map(|(ship, (kills, losses))| ShipStat {
+            ship,
+            kills,
+            losses,
+        })
+        .collect();
+    stats.sort_by(|a, b| {
+        b.kills
+            .cmp(&a.kills)
+            .then_with(|| a.losses.cmp(&b.losses))
+            .then_with(|| a.ship.cmp(&b.ship))
+    });
+    stats
+}
+
+fn time_of(event: &LogEvent, zone: DisplayZone) -> String {
+    event
+        .timestamp_ms
+        .map(|timestamp_ms| format::format_timestamp(timestamp_ms, zone))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn render_markdown(
+    events: &[LogEvent],
+    board: &[LeaderboardEntry],
+    members: usize,
+    zone: DisplayZone,
+) -> String {
+    let mut out = String::from("# After-action report\n\n");
+    out.push_str(&format!(
+        "{} events from {} feeds\n\n## Leaderboard\n\n| Player | Kills | Deaths | Assists |\n|---|---|---|---|\n",
+        events.len(),
+        members
+    ));
+    for entry in board {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            escape_markdown(&entry.handle),
+            entry.kills,
+            entry.deaths,
+            entry.assists
+        ));
+    }
+
+    out.push_str("\n## Timeline\n\n| Time | Event |\n|---|---|\n");
+    for event in events {
+        out.push_str(&format!(
+            "| {} | {} |\n",
+            time_of(event, zone),
+            escape_markdown(&format::summarize(event))
+        ));
+    }
+    out
+}
+
+fn render_html(
+    events: &[LogEvent],
+    board: &[LeaderboardEntry],
+    members: usize,
+    zone: DisplayZone,
+) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>After-action report</title></head>\n<body>\n<h1>After-action report</h1>\n",
+    );
+    out.push_str(&format!(
+        "<p>{} events from {} feeds</p>\n<h2>Leaderboard</h2>\n<table>\n<tr><th>Player</th><th>Kills</th><th>Deaths</th><th>Assists</th></tr>\n",
+        events.len(),
+        members
+    ));
+    for entry in board {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&entry.handle),
+            entry.kills,
+            entry.deaths,
+            entry.assists
+        ));
+    }
+
+    out.push_str("</table>\n<h2>Timeline</h2>\n<table>\n<tr><th>Time</th><th>Event</th></tr>\n");
+    for event in events {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            time_of(event, zone),
+            escape_html(&format::summarize(event))
+        ));
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+/// Shift the events that came from this user's own feed, `own` holding their
+/// fingerprints, by this machine's clock correction. A kill merged from
+/// several feeds is shifted if one of them was this user's.
+fn correct_own_events(events: &mut [LogEvent], own: &HashSet<String>, offset_ms: i64) {
+    if offset_ms == 0 || own.is_empty() {
+        return;
+    }
+    for event in events.iter_mut() {
+        let is_own = own.contains(&event_fingerprint(event))
+            || event.sources.iter().any(|source| own.contains(source));
+        if let (true, Some(timestamp_ms)) = (is_own, event.timestamp_ms.as_mut()) {
+            *timestamp_ms += offset_ms;
+        }
+    }
+    events.sort_by_key(|event| (event.timestamp_ms.is_none(), event.timestamp_ms));
+}
+
+/// Render an after-action report for an operation from every member's feed:
+/// the feeds are merged so a kill both sides logged counts once, then ranked
+/// and laid out as a leaderboard and timeline with times shown in `zone`.
+/// `sources[own_source]`, if given, is this user's feed; its times are shifted
+/// by `clock_offset_ms` after merging, so dedupe and kill correlation compare
+/// the raw timestamps every feed was written with.
+pub fn generate_org_report(
+    sources: Vec<Vec<LogEvent>>,
+    format: ReportFormat,
+    zone: DisplayZone,
+    own_source: Option<usize>,
+    clock_offset_ms: i64,
+) -> String {
+    let members = sources.len();
+    let own: HashSet<String> = own_source
+        .and_then(|index| sources.get(index))
+        .map(|events| events.iter().map(event_fingerprint).collect())
+        .unwrap_or_default();
+    let mut events = merge_sources(sources);
+    correct_own_events(&mut events, &own, clock_offset_ms);
+    let board = leaderboard(&events);
+    match format {
+        ReportFormat::Markdown => render_markdown(&events, &board, members, zone),
+        ReportFormat::Html => render_html(&events, &board, members, zone),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TestDeath;
+
+    fn kill(timestamp_ms: i64, victim: &str, killer: &str, zone: Option<&str>) -> LogEvent {
+        LogEvent::test(
+            TestDeath {
+                zone: zone.map(Into::into),
+                ..TestDeath::new(victim, killer)
+            }
+            .into(),
+        )
+        .at(timestamp_ms)
+    }
+
+    #[test]
+    fn a_kill_reported_by_both_members_counts_once() {
+        let killer_feed = vec![
+            kill(1_000, "Victim", "Killer", None),
+            kill(5_000, "Other", "Killer", None),
+        ];
+        // The victim's clock is a little behind and they know the zone
+        let victim_feed = vec![kill(800, "Victim", "Killer", Some("Hangar"))];
+        // The same feed shared twice
+        let duplicate_feed = killer_feed.clone();
+
+        let events = merge_sources(vec![killer_feed, victim_feed, duplicate_feed]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            leaderboard(&events),
+            vec![
+                LeaderboardEntry {
+                    handle: "Killer".into(),
+                    kills: 2,
+                    deaths: 0,
+                    assists: 0,
+                },
+                LeaderboardEntry {
+                    handle: "Other".into(),
+                    kills: 0,
+                    deaths: 1,
+                    assists: 0,
+                },
+                LeaderboardEntry {
+                    handle: "Victim".into(),
+                    kills: 0,
+                    deaths: 1,
+                    assists: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn leaderboard_counts_assists_separately() {
+        let assisted = LogEvent::test(
+            TestDeath {
+                assists: vec!["Wingman".into(), "Killer2".into()],
+                ..TestDeath::new("Victim", "Killer")
+            }
+            .into(),
+        )
+        .at(1_000);
+        let events = vec![assisted, kill(2_000, "Other", "Killer2", None)];
+
+        let board = leaderboard(&events);
+        let entry = |handle: &str| board.iter().find(|e| e.handle == handle).unwrap();
+        assert_eq!((entry("Killer").kills, entry("Killer").assists), (1, 0));
+        assert_eq!((entry("Killer2").kills, entry("Killer2").assists), (1, 1));
+        assert_eq!((entry("Wingman").kills, entry("Wingman").assists), (0, 1));
+    }
+
+    fn kill_with(timestamp_ms: i64, killer: &str, weapon: &str) -> LogEvent {
+        LogEvent::test(
+            TestDeath {
+                weapon: Some(weapon.into()),
+                ..TestDeath::new("Victim", killer)
+            }
+            .into(),
+        )
+        .at(timestamp_ms)
+    }
+
+    #[test]
+    fn breaks_kills_down_by_weapon() {
+        let events = vec![
+            kill_with(1, "Me", "behr_rifle_ballistic_01_1234"),
+            kill_with(2, "Me", "behr_rifle_ballistic_01_5678"),
+            kill_with(3, "Me", "klwe_pistol_energy_01_99"),
+            kill_with(4, "Me", "unknown"),
+            kill_with(5, "Other", "klwe_pistol_energy_01_7"),
+            // The same kill again from someone else's feed
+            kill_with(1, "Me", "behr_rifle_ballistic_01_1234"),
+            kill_with(6, "Victim", "behr_rifle_ballistic_01_1"),
+        ];
+
+        let stat = |weapon: &str, kills| WeaponStat {
+            weapon: weapon.into(),
+            kills,
+        };
+        assert_eq!(
+            weapon_breakdown(&events, Some("me")),
+            vec![
+                stat("behr_rifle_ballistic_01", 2),
+                stat("klwe_pistol_energy_01", 1),
+                stat("unknown", 1),
+            ]
+        );
+        // Ties are ordered by name
+        assert_eq!(
+            weapon_breakdown(&events, None),
+            vec![
+                stat("behr_rifle_ballistic_01", 2),
+                stat("klwe_pistol_energy_01", 2),
+                stat("unknown", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn credits_kills_and_losses_to_the_ship_flown() {
+        let board = |vehicle: &str| {
+            LogEvent::test(EventKind::VehicleControl {
+                vehicle: vehicle.into(),
+                vehicle_id: None,
+                vehicle_name: None,
+            })
+        };
+        let destroyed = |vehicle: &str, level: &str| {
+            LogEvent::test(EventKind::VehicleDestruction {
+                vehicle: vehicle.into(),
+                vehicle_id: None,
+                vehicle_name: None,
+                zone: None,
+                driver: None,
+                cause: None,
+                destroy_level_from: None,
+                destroy_level_to: Some(level.into()),
+                damage_type: None,
+            })
+        };
+        let mut shared = board("DRAK_Cutlass_Black_2");
+        shared.reporter = Some("Wingman".into());
+
+        let events = vec![
+            kill(1, "Target1", "Pilot", None),
+            board("AEGS_Gladius_1"),
+            kill(2, "Target2", "Pilot", None),
+            kill(3, "Target3", "Pilot", None),
+            shared,
+            kill(4, "Target4", "Pilot", None),
+            destroyed("AEGS_Gladius_1", "1"),
+            destroyed("AEGS_Gladius_1", "2"),
+            kill(5, "Target5", "Pilot", None),
+            board("ANVL_Arrow_3"),
+            kill(6, "Target6", "Pilot", None),
+            kill(7, "Pilot", "Target6", None),
+            kill(8, "Target7", "Pilot", None),
+        ];
+        let stats: Vec<(String, usize, usize)> = ship_performance(&events, "pilot")
+            .into_iter()
+            .map(|stat| (stat.ship, stat.kills, stat.losses))
+            .collect();
+        assert_eq!(
+            stats,
+            [
+                // Ties on kills go to the ship lost less
+                ("unknown".to_string(), 3, 0),
+                ("Gladius".to_string(), 3, 1),
+                ("Arrow".to_string(), 1, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn html_report_escapes_handles() {
+        let report = generate_org_report(
+            vec![vec![kill(1_000, "<b>Victim</b>", "Killer", None)]],
+            ReportFormat::Html,
+            DisplayZone::Utc,
+            None,
+            0,
+        );
+        assert!(report.contains("&lt;b&gt;Victim&lt;/b&gt;"));
+        assert!(!report.contains("<b>Victim"));
+
+        let markdown = generate_org_report(
+            vec![vec![kill(1_000, "a|b", "Killer", None)]],
+            ReportFormat::Markdown,
+            DisplayZone::Utc,
+            None,
+            0,
+        );
+        assert!(markdown.contains("| a\\|b | 0 | 1 | 0 |"));
+    }
+
+    #[test]
+    fn only_this_users_feed_is_clock_corrected_after_merging() {
+        let own = vec![
+            kill(10_000, "Target", "Pilot", None),
+            kill(20_000, "Other", "Pilot", None),
+        ];
+        // The other member exported the same first kill, with no reporter set
+        let theirs = vec![
+            kill(10_000, "Target", "Pilot", None),
+            kill(30_000, "Pilot", "Rival", None),
+        ];
+        let own_fingerprints: HashSet<String> = own.iter().map(event_fingerprint).collect();
+        let mut events = merge_sources(vec![own, theirs]);
+        assert_eq!(events.len(), 3);
+
+        correct_own_events(&mut events, &own_fingerprints, -15_000);
+        let times: Vec<_> = events.iter().map(|event| event.timestamp_ms).collect();
+        assert_eq!(times, [Some(-5_000), Some(5_000), Some(30_000)]);
+    }
+}
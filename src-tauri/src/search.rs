@@ -0,0 +1,189 @@
+use crate::event::{EventKind, LogEvent};
+use serde::Deserialize;
+
+/// Criteria for `search_events`. Every criterion that is set must match; text
+/// criteria are case- and accent-insensitive substring matches.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SearchQuery {
+    /// Any player named in the event: victim, killer, driver, cause, etc.
+    pub handle: Option<String>,
+    /// Weapon, weapon class or damage type.
+    pub weapon: Option<String>,
+    /// Zone or location.
+    pub zone: Option<String>,
+    pub kinds: Option<Vec<String>>,
+    /// Inclusive bounds on `timestamp_ms`. Events without a timestamp never
+    /// match a time range.
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+}
+
+/// Lowercase and strip the accents from Latin letters, so "Jöse" matches
+/// "jose".
+fn fold(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' => 'a',
+            'ç' | 'ć' | 'č' => 'c',
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => 'e',
+            'ì' | 'í' | 'î' | 'ï' | 'ī' => 'i',
+            'ñ' | 'ń' | 'ň' => 'n',
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' => 'o',
+            'ś' | 'š' => 's',
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' => 'u',
+            'ý' | 'ÿ' => 'y',
+            'ź' | 'ż' | 'ž' => 'z',
+            other => other,
+        })
+        .collect()
+}
+
+fn weapons(kind: &EventKind) -> Vec<&str> {
+    match kind {
+        EventKind::ActorDeath {
+            weapon,
+            weapon_name,
+            weapon_class,
+            damage_type,
+            ..
+        } => [weapon, weapon_name, weapon_class, damage_type]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect(),
+        EventKind::VehicleDestruction { damage_type, .. } => {
+            damage_type.iter().map(String::as_str).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn zones(kind: &EventKind) -> Vec<&str> {
+    match kind {
+        EventKind::ActorDeath { zone, .. } | EventKind::VehicleDestruction { zone, .. } => {
+            zone.iter().map(String::as_str).collect()
+        }
+        EventKind::LocationChange { location, .. } => vec![location],
+        _ => Vec::new(),
+    }
+}
+
+fn any_contains(fields: Vec<&str>, needle: &Option<String>) -> bool {
+    match needle {
+        None => true,
+        Some(needle) => {
+            let needle = fold(needle);
+            fields
+                .into_iter()
+                .any(|field| fold(field).contains(&needle))
+        }
+    }
+}
+
+impl SearchQuery {
+    pub fn matches(&self, event: &LogEvent) -> bool {
+        if !event.matches_kinds(self.kinds.as_deref()) {
+            return false;
+        }
+        if self.from_ms.is_some() || self.to_ms.is_some() {
+            let Some(timestamp) = event.timestamp_ms else {
+                return false;
+            };
+            if self.from_ms.is_some_and(|from| timestamp < from)
+                || self.to_ms.is_some_and(|to| timestamp > to)
+            {
+                return false;
+            }
+        }
+
+        any_contains(event.kind.players(), &self.handle)
+            && any_contains(weapons(&event.kind), &self.weapon)
+            && any_contains(zones(&event.kind), &self.zone)
+    }
+}
+
+/// Events matching `query`, in chronological order. Events without a
+/// timestamp sort first.
+pub fn search_events(
+    events: impl IntoIterator<Item = LogEvent>,
+    query: &SearchQuery,
+) -> Vec<LogEvent> {
+    let mut matches: Vec<LogEvent> = events.into_iter().filter(|e| query.matches(e)).collect();
+    matches.sort_by_key(|event| event.timestamp_ms);
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TestDeath;
+
+    fn kill(timestamp_ms: i64, victim: &str, killer: &str, weapon_class: &str) -> LogEvent {
+        LogEvent::test(
+            TestDeath {
+                zone: Some("Stanton_Crusader".into()),
+                weapon_class: Some(weapon_class.into()),
+                ..TestDeath::new(victim, killer)
+            }
+            .into(),
+        )
+        .at(timestamp_ms)
+    }
+
+    fn events() -> Vec<LogEvent> {
+        vec![
+            kill(3_000, "Target", "PlayerX", "Ballistic_Rifle"),
+            kill(1_000, "Other", "PlayerX", "Ballistic_Pistol"),
+            kill(2_000, "PlayerX", "Someone", "Energy_Rifle"),
+            kill(4_000, "Target", "Renée", "Ballistic_Rifle"),
+            LogEvent::test(EventKind::LocationChange {
+                player: "PlayerX".into(),
+                location: "Crusader".into(),
+            }),
+        ]
+    }
+
+    #[test]
+    fn combines_criteria_and_sorts_chronologically() {
+        let query = SearchQuery {
+            handle: Some("playerx".into()),
+            weapon: Some("BALLISTIC".into()),
+            kinds: Some(vec!["actor_death".into()]),
+            ..SearchQuery::default()
+        };
+
+        let found = search_events(events(), &query);
+        let times: Vec<_> = found.iter().map(|e| e.timestamp_ms).collect();
+        assert_eq!(times, vec![Some(1_000), Some(3_000)]);
+    }
+
+    #[test]
+    fn time_range_is_inclusive_and_excludes_untimed_events() {
+        let query = SearchQuery {
+            from_ms: Some(2_000),
+            to_ms: Some(3_000),
+            ..SearchQuery::default()
+        };
+        assert_eq!(search_events(events(), &query).len(), 2);
+
+        let zone = SearchQuery {
+            zone: Some("crusader".into()),
+            ..SearchQuery::default()
+        };
+        let found = search_events(events(), &zone);
+        assert_eq!(found.len(), 5);
+        assert_eq!(found[0].timestamp_ms, None);
+    }
+
+    #[test]
+    fn matching_ignores_accents() {
+        let query = SearchQuery {
+            handle: Some("renee".into()),
+            ..SearchQuery::default()
+        };
+        assert_eq!(search_events(events(), &query).len(), 1);
+        assert_eq!(fold("RENÉE"), "renee");
+    }
+}
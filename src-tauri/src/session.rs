@@ -0,0 +1,319 @@
+use crate::buffer::{self, EventBuffer};
+use crate::event::LogEvent;
+use crate::fnv::Fnv1a;
+use crate::shard::{self, Shard, ShardState};
+use crate::streak::{self, StreakRecords, StreakState};
+use crate::watcher::{self, WatchRegistry, WatchStatus};
+use crate::ws::{self, ConnectionState, RoomState};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// Bumped whenever a change to `SessionSnapshot` would make older snapshots
+/// restore wrongly. Snapshots from a newer version are ignored.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// How many of the newest buffered events go into a snapshot.
+const MAX_SNAPSHOT_EVENTS: usize = 2_000;
+
+/// How often the latest snapshot is saved while the app runs.
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where the latest snapshot is saved, in the app data folder.
+const SNAPSHOT_FILE: &str = "session.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotRoom {
+    pub code: String,
+    pub spectator: bool,
+}
+
+/// Everything needed to bring the app back to where it was: the visible
+/// feed, the watches and how far they'd read, and the room it was in.
+/// Fields added later default when an older snapshot is loaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    pub version: u32,
+    #[serde(default)]
+    pub taken_at_ms: i64,
+    /// The newest buffered events, oldest first.
+    #[serde(default)]
+    pub events: Vec<LogEvent>,
+    #[serde(default)]
+    pub watches: Vec<WatchStatus>,
+    #[serde(default)]
+    pub shard: Option<Shard>,
+    #[serde(default)]
+    pub streak: StreakRecords,
+    #[serde(default)]
+    pub room: Option<SnapshotRoom>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    /// Events that weren't already in the feed.
+    pub events_added: usize,
+    pub watches_started: usize,
+    /// Whether a rejoin of the snapshot's room was started.
+    pub rejoining_room: bool,
+}
+
+/// Hash of the last snapshot saved, so an idle app doesn't rewrite it.
+#[derive(Default)]
+pub struct SessionState(Mutex<Option<String>>);
+
+fn keep_newest(events: &mut Vec<LogEvent>, max: usize) {
+    let excess = events.len().saturating_sub(max);
+    events.drain(..excess);
+}
+
+fn check_version(version: u32) -> Result<(), String> {
+    if version > SNAPSHOT_VERSION {
+        return Err(format!(
+            "Session snapshot is version {}, newer than {}",
+            version, SNAPSHOT_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// Read a saved snapshot, refusing one this version doesn't understand.
+fn parse_snapshot(bytes: &[u8]) -> Result<SessionSnapshot, String> {
+    let snapshot: SessionSnapshot = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+    check_version(snapshot.version)?;
+    Ok(snapshot)
+}
+
+fn snapshot_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(SNAPSHOT_FILE))
+}
+
+fn take_snapshot(app: &AppHandle) -> SessionSnapshot {
+    let mut events = app.state::<EventBuffer>().snapshot(None);
+    keep_newest(&mut events, MAX_SNAPSHOT_EVENTS);
+    let room = ws::get_room_status(app.state::<RoomState>());
+    let room = room.code.and_then(|code| match room.state {
+        ConnectionState::Joined => Some(SnapshotRoom {
+            code,
+            spectator: false,
+        }),
+        ConnectionState::JoinedSpectator => Some(SnapshotRoom {
+            code,
+            spectator: true,
+        }),
+        _ => None,
+    });
+
+    SessionSnapshot {
+        version: SNAPSHOT_VERSION,
+        taken_at_ms: chrono::Utc::now().timestamp_millis(),
+        events,
+        watches: app.state::<WatchRegistry>().statuses(),
+        shard: shard::get_current_shard(app.state::<ShardState>()),
+        streak: streak::get_streak_records(app.state::<StreakState>()),
+        room,
+    }
+}
+
+/// Write the snapshot to a temporary file first, so a crash mid-write leaves
+/// the previous one intact.
+fn write_snapshot(path: &Path, snapshot: &SessionSnapshot) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let bytes = serde_json::to_vec(snapshot).map_err(|e| e.to_string())?;
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&temp, path).map_err(|e| e.to_string())
+}
+
+/// Save the current session now, even if it's empty, e.g. once events were
+/// taken out of the feed so the saved copy mustn't bring them back.
+pub fn save_now(app: &AppHandle) -> Result<(), String> {
+    let snapshot = take_snapshot(app);
+    write_snapshot(&snapshot_path(app)?, &snapshot)
+}
+
+/// Save the current session if it changed since the last save.
+fn save(app: &AppHandle) -> Result<(), String> {
+    let mut snapshot = take_snapshot(app);
+    if snapshot.events.is_empty() && snapshot.watches.is_empty() {
+        return Ok(());
+    }
+    let taken_at_ms = std::mem::take(&mut snapshot.taken_at_ms);
+    let mut hasher = Fnv1a::new();
+    hasher.write(&serde_json::to_vec(&snapshot).map_err(|e| e.to_string())?);
+    let hash = hasher.finish_hex();
+
+    let state = app.state::<SessionState>();
+    let mut last = state.0.lock().unwrap();
+    if last.as_deref() == Some(hash.as_str()) {
+        return Ok(());
+    }
+    snapshot.taken_at_ms = taken_at_ms;
+    write_snapshot(&snapshot_path(app)?, &snapshot)?;
+    *last = Some(hash);
+    Ok(())
+}
+
+/// Start saving the session in the background. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(SAVE_INTERVAL);
+        if let Err(e) = save(&app) {
+            tracing::warn!("Failed to save the session snapshot: {}", e);
+        }
+    });
+}
+
+/// The current session, with the newest buffered events only.
+#[tauri::command]
+pub fn snapshot_session(app: AppHandle) -> SessionSnapshot {
+    take_snapshot(&app)
+}
+
+/// The snapshot saved by the last run, for offering to pick up where it left
+/// off. `None` if there isn't one or it can't be read.
+#[tauri::command]
+pub fn get_saved_session(app: AppHandle) -> Option<SessionSnapshot> {
+    let bytes = fs::read(snapshot_path(&app).ok()?).ok()?;
+    parse_snapshot(&bytes)
+        .map_err(|e| tracing::warn!("Ignoring the saved session: {}", e))
+        .ok()
+}
+
+/// Bring back a snapshot: its events are merged into the feed (which is then
+/// re-sent), watches not already running resume from their saved offsets,
+/// and the room is rejoined if none is joined now.
+#[tauri::command]
+pub fn restore_session(
+    app: AppHandle,
+    buffer: State<'_, EventBuffer>,
+    registry: State<'_, WatchRegistry>,
+    room: State<'_, RoomState>,
+    snapshot: SessionSnapshot,
+) -> Result<RestoreReport, String> {
+    check_version(snapshot.version)?;
+    let mut events = snapshot.events;
+    keep_newest(&mut events, MAX_SNAPSHOT_EVENTS);
+    let events_added = buffer.merge_history(events);
+    buffer::reemit(&app, &buffer)?;
+
+    let mut watches_started = 0;
+    for watch in &snapshot.watches {
+        if registry.is_watching(&watch.path) {
+            continue;
+        }
+        // A log that shrank since is a new session, so read it from the start
+        let offset = match fs::metadata(&watch.path) {
+            Ok(metadata) if metadata.len() >= watch.offset => watch.offset,
+            Ok(_) => 0,
+            Err(e) => {
+                tracing::warn!("Not resuming {}: {}", watch.path, e);
+                continue;
+            }
+        };
+        match watcher::start(&app, &registry, watch.path.clone(), offset, None) {
+            Ok(_) => watches_started += 1,
+            Err(e) => tracing::warn!("Failed to resume watching {}: {}", watch.path, e),
+        }
+    }
+
+    if let Some(shard) = snapshot.shard {
+        shard::restore(&app, shard);
+    }
+    let local_handle = snapshot
+        .watches
+        .iter()
+        .find_map(|watch| watch.local_handle.clone());
+    streak::restore(&app, snapshot.streak, local_handle);
+
+    let rejoin = snapshot
+        .room
+        .filter(|_| ws::get_room_status(room).state == ConnectionState::Disconnected);
+    let rejoining_room = rejoin.is_some();
+    if let Some(SnapshotRoom { code, spectator }) = rejoin {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let joined = if spectator {
+                ws::join_as_spectator(app, code.clone()).await
+            } else {
+                ws::join_room(app, code.clone()).await
+            };
+            if let Err(e) = joined {
+                tracing::warn!("Failed to rejoin room {}: {}", code, e);
+            }
+        });
+    }
+
+    tracing::info!(
+        "Restored a session from {}: {} events, {} watches",
+        snapshot.taken_at_ms,
+        events_added,
+        watches_started
+    );
+    Ok(RestoreReport {
+        events_added,
+        watches_started,
+        rejoining_room,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventKind;
+
+    #[test]
+    fn snapshots_round_trip_and_newer_versions_are_refused() {
+        let mut events: Vec<LogEvent> = (0..5)
+            .map(|i| LogEvent::test(EventKind::SystemQuit).at(i))
+            .collect();
+        keep_newest(&mut events, 3);
+        let snapshot = SessionSnapshot {
+            version: SNAPSHOT_VERSION,
+            taken_at_ms: 1_000,
+            events,
+            watches: vec![WatchStatus {
+                path: "C:\\Games\\Game.log".into(),
+                offset: 4_096,
+                local_handle: Some("Pilot".into()),
+            }],
+            shard: Some(Shard {
+                shard: "pub_euw1b_9335932_110".into(),
+                region: Some("euw1b".into()),
+                connected_at_ms: Some(500),
+            }),
+            streak: StreakRecords {
+                current: 2,
+                session_best: 3,
+                all_time_best: 7,
+            },
+            room: Some(SnapshotRoom {
+                code: "ABC123".into(),
+                spectator: false,
+            }),
+        };
+        assert_eq!(
+            snapshot.events.first(),
+            Some(&LogEvent::test(EventKind::SystemQuit).at(2))
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("picologs-session-{}.json", std::process::id()));
+        write_snapshot(&path, &snapshot).unwrap();
+        assert_eq!(parse_snapshot(&fs::read(&path).unwrap()).unwrap(), snapshot);
+        fs::remove_file(&path).unwrap();
+
+        // Fields an older version didn't write default; a newer version is refused
+        let old = parse_snapshot(br#"{"version":0,"events":[]}"#).unwrap();
+        assert!(old.watches.is_empty() && old.room.is_none());
+        assert!(parse_snapshot(br#"{"version":99}"#).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use crate::format::DisplayZone;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+/// The same store file the frontend uses, so settings written from either side
+/// live together.
+pub const STORE_FILE: &str = "store.json";
+
+pub const STORE_RECOVERED_EVENT: &str = "store-recovered";
+
+const DISPLAY_TIMEZONE_KEY: &str = "displayTimezone";
+/// Unix-millisecond time the current stream's VOD starts at.
+const STREAM_START_KEY: &str = "streamStartMs";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreRecovery {
+    /// Where the unreadable store was moved to.
+    pub backup: PathBuf,
+}
+
+/// Set at startup if the store had to be reset, so the UI can ask about it
+/// once it has loaded (the startup event fires before anything listens).
+#[derive(Default)]
+pub struct StoreRecoveryState(Mutex<Option<StoreRecovery>>);
+
+/// If `path` exists but doesn't hold a JSON object, move it aside to
+/// `store.corrupt.<timestamp>` next to it so the store starts over from
+/// defaults. Returns the backup path when that happened.
+fn recover_if_corrupt(path: &Path, now_ms: i64) -> io::Result<Option<PathBuf>> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&contents).is_ok() {
+        return Ok(None);
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let backup = path.with_file_name(format!("{}.corrupt.{}", stem, now_ms));
+    fs::rename(path, &backup)?;
+    Ok(Some(backup))
+}
+
+/// Check the store file before anything loads it. A corrupt store (e.g. from
+/// power loss mid-write) is backed up and replaced by defaults instead of
+/// failing every read for the rest of the session.
+pub fn recover_store(app: &AppHandle) {
+    let path = match app.path().resolve(STORE_FILE, BaseDirectory::AppData) {
+        Ok(path) => path,
+        Err(e) => {
+            tracing::warn!("Failed to resolve the store path: {}", e);
+            return;
+        }
+    };
+
+    match recover_if_corrupt(&path, chrono::Utc::now().timestamp_millis()) {
+        Ok(Some(backup)) => {
+            tracing::warn!("Store was corrupt, moved it to {}", backup.display());
+            let recovery = StoreRecovery { backup };
+            *app.state::<StoreRecoveryState>().0.lock().unwrap() = Some(recovery.clone());
+            let _ = app.emit(STORE_RECOVERED_EVENT, &recovery);
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to check the store for corruption: {}", e),
+    }
+}
+
+/// Whether the store was reset at startup, and where the old one went.
+#[tauri::command]
+pub fn get_store_recovery(state: State<'_, StoreRecoveryState>) -> Option<StoreRecovery> {
+    state.0.lock().unwrap().clone()
+}
+
+/// Read a key from the store. A missing key, or one holding a value of the wrong
+/// shape, reads as `None`.
+pub fn get<T: DeserializeOwned>(app: &AppHandle, key: &str) -> Result<Option<T>, String> {
+    let store = app.store(STORE_FILE).map_err(|e| e.to_string())?;
+    Ok(store
+        .get(key)
+        .and_then(|value| serde_json::from_value(value).ok()))
+}
+
+/// Write a key to the store and flush it to disk immediately.
+pub fn set<T: Serialize>(app: &AppHandle, key: &str, value: &T) -> Result<(), String> {
+    let store = app.store(STORE_FILE).map_err(|e| e.to_string())?;
+    let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    store.set(key, value);
+    store.save().map_err(|e| e.to_string())
+}
+
+/// Delete a key from the store and flush it to disk immediately.
+pub fn remove(app: &AppHandle, key: &str) -> Result<(), String> {
+    let store = app.store(STORE_FILE).map_err(|e| e.to_string())?;
+    store.delete(key);
+    store.save().map_err(|e| e.to_string())
+}
+
+/// The zone formatted timestamps are shown in, UTC unless set.
+pub fn display_zone(app: &AppHandle) -> DisplayZone {
+    get::<String>(app, DISPLAY_TIMEZONE_KEY)
+        .ok()
+        .flatten()
+        .and_then(|name| name.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Set the zone used for formatted timestamps: `utc`, `local` or an IANA name
+/// such as `America/New_York`. Returns the normalized name.
+#[tauri::command]
+pub fn set_display_timezone(app: AppHandle, timezone: String) -> Result<String, String> {
+    let zone: DisplayZone = timezone.parse()?;
+    let name = zone.to_string();
+    set(&app, DISPLAY_TIMEZONE_KEY, &name)?;
+    Ok(name)
+}
+
+#[tauri::command]
+pub fn get_display_timezone(app: AppHandle) -> String {
+    display_zone(&app).to_string()
+}
+
+/// Take now as the start of the stream, for `format_event_with_stream_offset`.
+/// Returns the stored time.
+#[tauri::command]
+pub fn set_stream_start(app: AppHandle) -> Result<i64, String> {
+    let now = chrono::Utc::now().timestamp_millis();
+    set(&app, STREAM_START_KEY, &now)?;
+    Ok(now)
+}
+
+#[tauri::command]
+pub fn get_stream_start(app: AppHandle) -> Option<i64> {
+    get(&app, STREAM_START_KEY).ok().flatten()
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppPaths {
+    pub config_dir: PathBuf,
+    pub data_dir: PathBuf,
+    pub log_dir: PathBuf,
+    pub store_file: PathBuf,
+}
+
+/// Where this install keeps its config, data, logs and store on the current
+/// OS. The directories are created if they don't exist yet, so the UI can
+/// always reveal them.
+#[tauri::command]
+pub fn get_app_paths(app: AppHandle) -> Result<AppPaths, String> {
+    let resolver = app.path();
+    let paths = AppPaths {
+        config_dir: resolver.app_config_dir().map_err(|e| e.to_string())?,
+        data_dir: resolver.app_data_dir().map_err(|e| e.to_string())?,
+        log_dir: resolver.app_log_dir().map_err(|e| e.to_string())?,
+        // The store plugin resolves relative store paths against the data dir
+        store_file: resolver
+            .resolve(STORE_FILE, BaseDirectory::AppData)
+            .map_err(|e| e.to_string())?,
+    };
+
+    for dir in [&paths.config_dir, &paths.data_dir, &paths.log_dir] {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrupt_store_is_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("picologs-store-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(STORE_FILE);
+
+        assert_eq!(recover_if_corrupt(&path, 1).unwrap(), None);
+
+        fs::write(&path, r#"{"bookmarks": []}"#).unwrap();
+        assert_eq!(recover_if_corrupt(&path, 1).unwrap(), None);
+
+        fs::write(&path, r#"{"bookmarks": [{"fingerp"#).unwrap();
+        let backup = recover_if_corrupt(&path, 42).unwrap().unwrap();
+        assert_eq!(backup, dir.join("store.corrupt.42"));
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            r#"{"bookmarks": [{"fingerp"#
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
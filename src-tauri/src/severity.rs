@@ -0,0 +1,120 @@
+use crate::event::{EventKind, LogEvent};
+use serde::{Deserialize, Serialize};
+
+/// How prominently the UI (and notifications) should surface an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Notable,
+    Critical,
+}
+
+fn is_local(name: &str, local_handle: Option<&str>) -> bool {
+    local_handle.is_some_and(|handle| handle.eq_ignore_ascii_case(name))
+}
+
+/// Classify an event relative to the local player. Anything the local player
+/// killed, anything that killed or destroyed them, and game crashes are
+/// `Critical`; other combat is `Notable`; everything else is `Info`.
+pub fn classify_severity(event: &LogEvent, local_handle: Option<&str>) -> Severity {
+    match &event.kind {
+        EventKind::ActorDeath { victim, killer, .. } => {
+            if is_local(victim, local_handle) || is_local(killer, local_handle) {
+                Severity::Critical
+            } else {
+                Severity::Notable
+            }
+        }
+        EventKind::VehicleDestruction { driver, cause, .. } => {
+            let involved = [driver, cause]
+                .into_iter()
+                .flatten()
+                .any(|name| is_local(name, local_handle));
+            if involved {
+                Severity::Critical
+            } else {
+                Severity::Notable
+            }
+        }
+        EventKind::GameCrash { .. } => Severity::Critical,
+        EventKind::PlayerIdentified { .. }
+        | EventKind::VehicleControl { .. }
+        | EventKind::LocationChange { .. }
+        | EventKind::ShardConnected { .. }
+        | EventKind::SystemQuit
+        | EventKind::DamageTick { .. }
+        | EventKind::LocationTransition { .. }
+        | EventKind::LoadoutChange { .. } => Severity::Info,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TestDeath;
+
+    fn death(victim: &str, killer: &str) -> LogEvent {
+        LogEvent::test(TestDeath::new(victim, killer).into()).at(0)
+    }
+
+    #[test]
+    fn local_deaths_and_kills_are_critical() {
+        let me = Some("Me");
+        assert_eq!(
+            classify_severity(&death("me", "Enemy"), me),
+            Severity::Critical
+        );
+        assert_eq!(
+            classify_severity(&death("Enemy", "Me"), me),
+            Severity::Critical
+        );
+        assert_eq!(classify_severity(&death("A", "B"), me), Severity::Notable);
+    }
+
+    #[test]
+    fn deaths_without_a_known_local_player_are_notable() {
+        assert_eq!(
+            classify_severity(&death("Me", "Enemy"), None),
+            Severity::Notable
+        );
+    }
+
+    #[test]
+    fn destroying_or_losing_a_ship_is_critical() {
+        let destruction = |driver: Option<&str>, cause: &str| {
+            LogEvent::test(EventKind::VehicleDestruction {
+                vehicle: "AEGS_Gladius_1".into(),
+                vehicle_id: None,
+                vehicle_name: None,
+                zone: None,
+                driver: driver.map(Into::into),
+                cause: Some(cause.into()),
+                destroy_level_from: None,
+                destroy_level_to: None,
+                damage_type: None,
+            })
+            .at(0)
+        };
+
+        let me = Some("Me");
+        assert_eq!(
+            classify_severity(&destruction(Some("Me"), "Enemy"), me),
+            Severity::Critical
+        );
+        assert_eq!(
+            classify_severity(&destruction(None, "Me"), me),
+            Severity::Critical
+        );
+        assert_eq!(
+            classify_severity(&destruction(None, "Enemy"), me),
+            Severity::Notable
+        );
+    }
+
+    #[test]
+    fn non_combat_events_are_info() {
+        let event = LogEvent::test(EventKind::SystemQuit).at(0);
+        assert_eq!(classify_severity(&event, Some("Me")), Severity::Info);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::event::{EventKind, LogEvent};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const SHARD_CHANGED_EVENT: &str = "shard-changed";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shard {
+    pub shard: String,
+    pub region: Option<String>,
+    pub connected_at_ms: Option<i64>,
+}
+
+/// The shard the local player most recently joined, across all watches.
+#[derive(Default)]
+pub struct ShardState(Mutex<Option<Shard>>);
+
+/// Track any `ShardConnected` events in a batch, emitting `shard-changed`
+/// when the player lands on a different shard.
+pub fn observe_batch(app: &AppHandle, events: &[LogEvent]) {
+    let Some((event, shard, region)) = events.iter().rev().find_map(|event| match &event.kind {
+        EventKind::ShardConnected { shard, region } => Some((event, shard, region)),
+        _ => None,
+    }) else {
+        return;
+    };
+
+    let state = app.state::<ShardState>();
+    let mut current = state.0.lock().unwrap();
+    if current.as_ref().is_some_and(|c| &c.shard == shard) {
+        return;
+    }
+
+    let next = Shard {
+        shard: shard.clone(),
+        region: region.clone(),
+        connected_at_ms: event.timestamp_ms,
+    };
+    *current = Some(next.clone());
+    drop(current);
+
+    if let Err(e) = app.emit(SHARD_CHANGED_EVENT, &next) {
+        tracing::warn!("Failed to emit {}: {}", SHARD_CHANGED_EVENT, e);
+    }
+}
+
+/// Put back the shard from a restored session, unless one was joined since.
+pub fn restore(app: &AppHandle, shard: Shard) {
+    app.state::<ShardState>()
+        .0
+        .lock()
+        .unwrap()
+        .get_or_insert(shard);
+}
+
+#[tauri::command]
+pub fn get_current_shard(state: State<'_, ShardState>) -> Option<Shard> {
+    state.0.lock().unwrap().clone()
+}
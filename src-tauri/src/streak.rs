@@ -0,0 +1,180 @@
+use crate::event::{EventKind, LogEvent};
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub const KILL_STREAK_EVENT: &str = "kill-streak";
+
+/// Store key for the best streak ever reached.
+const BEST_STREAK_KEY: &str = "bestKillStreak";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreakRecords {
+    pub current: u32,
+    /// Best streak since the app started.
+    pub session_best: u32,
+    pub all_time_best: u32,
+}
+
+/// Counts the local player's kills since they last died. Kills only count
+/// once a `PlayerIdentified` line has said who the local player is.
+#[derive(Debug, Default)]
+pub struct StreakTracker {
+    local_handle: Option<String>,
+    records: StreakRecords,
+}
+
+impl StreakTracker {
+    /// Update the streak from one event. Returns the records when the
+    /// streak changed.
+    pub fn observe(&mut self, event: &LogEvent) -> Option<StreakRecords> {
+        match &event.kind {
+            EventKind::PlayerIdentified { handle, .. } => {
+                let same = self
+                    .local_handle
+                    .as_deref()
+                    .is_some_and(|local| local.eq_ignore_ascii_case(handle));
+                self.local_handle = Some(handle.clone());
+                // Someone else's kills aren't part of this streak
+                if same {
+                    None
+                } else {
+                    self.reset()
+                }
+            }
+            EventKind::ActorDeath { victim, killer, .. } => {
+                let local = self.local_handle.as_deref()?;
+                if victim.eq_ignore_ascii_case(local) {
+                    self.reset()
+                } else if killer.eq_ignore_ascii_case(local) {
+                    let records = &mut self.records;
+                    records.current += 1;
+                    records.session_best = records.session_best.max(records.current);
+                    records.all_time_best = records.all_time_best.max(records.current);
+                    Some(*records)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn reset(&mut self) -> Option<StreakRecords> {
+        if self.records.current == 0 {
+            return None;
+        }
+        self.records.current = 0;
+        Some(self.records)
+    }
+
+    pub fn records(&self) -> StreakRecords {
+        self.records
+    }
+}
+
+/// Carry on a restored session's streak, unless a kill or death was already
+/// seen since it was saved.
+pub fn restore(app: &AppHandle, records: StreakRecords, local_handle: Option<String>) {
+    let state = app.state::<StreakState>();
+    let mut tracker = state.0.lock().unwrap();
+    if tracker.records.current == 0 && tracker.records.session_best == 0 {
+        tracker.records.current = records.current;
+        tracker.records.session_best = records.session_best;
+    }
+    if tracker.local_handle.is_none() {
+        tracker.local_handle = local_handle;
+    }
+}
+
+#[derive(Default)]
+pub struct StreakState(Mutex<StreakTracker>);
+
+/// Load the all-time best. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    if let Some(best) = settings::get::<u32>(app, BEST_STREAK_KEY).ok().flatten() {
+        app.state::<StreakState>()
+            .0
+            .lock()
+            .unwrap()
+            .records
+            .all_time_best = best;
+    }
+}
+
+/// Track the streak through a batch, emitting `kill-streak` whenever it
+/// changes and persisting a new all-time best.
+pub fn observe_batch(app: &AppHandle, events: &[LogEvent]) {
+    let state = app.state::<StreakState>();
+    let mut tracker = state.0.lock().unwrap();
+    let best_before = tracker.records.all_time_best;
+    let changes: Vec<StreakRecords> = events
+        .iter()
+        .filter_map(|event| tracker.observe(event))
+        .collect();
+    let best = tracker.records.all_time_best;
+    drop(tracker);
+
+    if best > best_before {
+        if let Err(e) = settings::set(app, BEST_STREAK_KEY, &best) {
+            tracing::warn!("Failed to persist {}: {}", BEST_STREAK_KEY, e);
+        }
+    }
+    for records in changes {
+        if let Err(e) = app.emit(KILL_STREAK_EVENT, records) {
+            tracing::warn!("Failed to emit {}: {}", KILL_STREAK_EVENT, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_current_streak(state: State<'_, StreakState>) -> u32 {
+    state.0.lock().unwrap().records().current
+}
+
+#[tauri::command]
+pub fn get_streak_records(state: State<'_, StreakState>) -> StreakRecords {
+    state.0.lock().unwrap().records()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TestDeath;
+
+    fn identified(handle: &str) -> LogEvent {
+        LogEvent::test(EventKind::PlayerIdentified {
+            handle: handle.into(),
+            geid: None,
+        })
+    }
+
+    fn death(victim: &str, killer: &str) -> LogEvent {
+        LogEvent::test(TestDeath::new(victim, killer).into())
+    }
+
+    #[test]
+    fn counts_kills_until_the_local_player_dies() {
+        let events = [
+            // Nobody is local yet
+            death("Enemy", "Pilot"),
+            identified("Pilot"),
+            death("Enemy", "pilot"),
+            death("Enemy", "Pilot"),
+            death("Enemy", "Bystander"),
+            death("Pilot", "Enemy"),
+            death("Enemy", "Pilot"),
+            identified("Pilot"),
+            identified("Alt"),
+        ];
+        let mut tracker = StreakTracker::default();
+        let streaks: Vec<(u32, u32)> = events
+            .iter()
+            .filter_map(|event| tracker.observe(event))
+            .map(|records| (records.current, records.session_best))
+            .collect();
+        assert_eq!(streaks, [(1, 1), (2, 2), (0, 2), (1, 2), (0, 2)]);
+        assert_eq!(tracker.records().all_time_best, 2);
+    }
+}
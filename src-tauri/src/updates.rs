@@ -0,0 +1,122 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+use tauri_plugin_updater::{Error as UpdaterError, Update, UpdaterExt};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingUpdate {
+    pub version: String,
+    pub current_version: String,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+    pub download_size: Option<u64>,
+    /// Whether the downloaded package matched the signature the release was
+    /// published with. Only verified updates can be installed.
+    pub signature_verified: bool,
+    /// Why verification failed, when it did.
+    pub error: Option<String>,
+}
+
+struct Downloaded {
+    info: PendingUpdate,
+    update: Update,
+    /// The verified package. `None` when verification failed.
+    bytes: Option<Vec<u8>>,
+}
+
+/// The update found by the last check, if any, downloaded and verified.
+#[derive(Default)]
+pub struct UpdateState(Mutex<Option<Downloaded>>);
+
+fn is_signature_error(error: &UpdaterError) -> bool {
+    matches!(
+        error,
+        UpdaterError::Minisign(_) | UpdaterError::Base64(_) | UpdaterError::SignatureUtf8(_)
+    )
+}
+
+/// Check for an update and, if there is one, download it and verify its
+/// signature up front so the user can see exactly what would be installed, and
+/// whether it's genuine, before agreeing to it.
+#[tauri::command]
+pub async fn check_for_update(
+    app: AppHandle,
+    state: State<'_, UpdateState>,
+) -> Result<Option<PendingUpdate>, String> {
+    let updater = app.updater().map_err(|e| e.to_string())?;
+    let Some(update) = updater.check().await.map_err(|e| e.to_string())? else {
+        *state.0.lock().unwrap() = None;
+        return Ok(None);
+    };
+
+    let mut content_length = None;
+    let downloaded = update
+        .download(|_, total| content_length = content_length.or(total), || {})
+        .await;
+    let (bytes, error) = match downloaded {
+        Ok(bytes) => (Some(bytes), None),
+        Err(e) if is_signature_error(&e) => {
+            tracing::warn!(
+                "Update {} failed signature verification: {}",
+                update.version,
+                e
+            );
+            (None, Some(format!("Signature verification failed: {}", e)))
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let info = PendingUpdate {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        notes: update.body.clone(),
+        date: update.date.map(|date| date.to_string()),
+        download_size: bytes
+            .as_ref()
+            .map(|bytes| bytes.len() as u64)
+            .or(content_length),
+        signature_verified: bytes.is_some(),
+        error,
+    };
+    *state.0.lock().unwrap() = Some(Downloaded {
+        info: info.clone(),
+        update,
+        bytes,
+    });
+    Ok(Some(info))
+}
+
+#[tauri::command]
+pub fn get_pending_update(state: State<'_, UpdateState>) -> Option<PendingUpdate> {
+    state
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|pending| pending.info.clone())
+}
+
+/// Install the update found by `check_for_update` and restart. Refused unless
+/// its signature verified.
+#[tauri::command]
+pub fn install_update(app: AppHandle, state: State<'_, UpdateState>) -> Result<(), String> {
+    let pending = state
+        .0
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| "No update has been downloaded".to_string())?;
+    let Some(bytes) = &pending.bytes else {
+        let version = pending.info.version.clone();
+        // Keep it around so the UI can still show why it was refused
+        *state.0.lock().unwrap() = Some(pending);
+        return Err(format!(
+            "Refusing to install {}: its signature did not verify",
+            version
+        ));
+    };
+
+    tracing::info!("Installing update {}", pending.info.version);
+    pending.update.install(bytes).map_err(|e| e.to_string())?;
+    app.restart()
+}
@@ -0,0 +1,106 @@
+use super::OFFSETS_KEY;
+use crate::settings;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const INTERVAL_KEY: &str = "checkpointIntervalMs";
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+const MIN_INTERVAL: Duration = Duration::from_millis(250);
+const MAX_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Latest offset per path not yet written to the store. Watches record here on
+/// every poll and a background thread folds everything pending into one store
+/// write per interval, so write frequency doesn't follow log activity.
+pub struct CheckpointState {
+    pending: Mutex<HashMap<String, u64>>,
+    interval: Mutex<Duration>,
+}
+
+impl Default for CheckpointState {
+    fn default() -> Self {
+        CheckpointState {
+            pending: Mutex::new(HashMap::new()),
+            interval: Mutex::new(DEFAULT_INTERVAL),
+        }
+    }
+}
+
+fn clamp_interval(ms: u64) -> Duration {
+    Duration::from_millis(ms).clamp(MIN_INTERVAL, MAX_INTERVAL)
+}
+
+/// Load the configured interval and start the flush thread.
+pub fn init(app: &AppHandle) {
+    let state = app.state::<CheckpointState>();
+    if let Ok(Some(ms)) = settings::get::<u64>(app, INTERVAL_KEY) {
+        *state.interval.lock().unwrap() = clamp_interval(ms);
+    }
+
+    let app = app.clone();
+    thread::spawn(move || loop {
+        let interval = *app.state::<CheckpointState>().interval.lock().unwrap();
+        thread::sleep(interval);
+        if let Err(e) = flush(&app) {
+            tracing::warn!("Failed to flush checkpoints: {}", e);
+        }
+    });
+}
+
+pub fn record(app: &AppHandle, path: &str, offset: u64) {
+    app.state::<CheckpointState>()
+        .pending
+        .lock()
+        .unwrap()
+        .insert(path.to_string(), offset);
+}
+
+/// Write every pending offset to the store in a single save. Returns how many
+/// paths were written.
+pub fn flush(app: &AppHandle) -> Result<usize, String> {
+    let pending = std::mem::take(&mut *app.state::<CheckpointState>().pending.lock().unwrap());
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let mut offsets: HashMap<String, u64> = settings::get(app, OFFSETS_KEY)?.unwrap_or_default();
+    let written = pending.len();
+    offsets.extend(pending);
+    settings::set(app, OFFSETS_KEY, &offsets)?;
+    Ok(written)
+}
+
+/// Force pending offsets to disk now rather than at the next interval.
+#[tauri::command]
+pub fn flush_checkpoints(app: AppHandle) -> Result<usize, String> {
+    flush(&app)
+}
+
+/// How often offsets are written to the store, clamped to 250ms..60s. This is
+/// also the most progress that can be lost if the app is killed.
+#[tauri::command]
+pub fn set_checkpoint_interval(
+    app: AppHandle,
+    state: State<'_, CheckpointState>,
+    interval_ms: u64,
+) -> Result<u64, String> {
+    let interval = clamp_interval(interval_ms);
+    *state.interval.lock().unwrap() = interval;
+    let ms = interval.as_millis() as u64;
+    settings::set(&app, INTERVAL_KEY, &ms)?;
+    Ok(ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_is_clamped() {
+        assert_eq!(clamp_interval(0), MIN_INTERVAL);
+        assert_eq!(clamp_interval(2_000), Duration::from_secs(2));
+        assert_eq!(clamp_interval(u64::MAX), MAX_INTERVAL);
+    }
+}
@@ -0,0 +1,1210 @@
+pub mod checkpoint;
+
+use crate::budget::{Priority, WorkBudget};
+use crate::clock;
+use crate::event::{EventKind, LogEvent, KIND_NAMES};
+use crate::fnv::Fnv1a;
+use crate::game;
+use crate::parser::{self, LineParser};
+use crate::pipeline;
+use crate::recents;
+use crate::settings;
+use crate::severity::classify_severity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+const ADAPTIVE_POLLING_KEY: &str = "adaptivePolling";
+pub const POLL_ADAPTED_EVENT: &str = "poll-adapted";
+/// Default bounds for adaptive polling.
+const DEFAULT_MIN_POLL_MS: u64 = 200;
+const DEFAULT_MAX_POLL_MS: u64 = 3000;
+/// A poll that reads at least this much is a burst and tightens straight to
+/// the minimum interval.
+const BURST_BYTES: u64 = 4096;
+
+const OFFSETS_KEY: &str = "logOffsets";
+const SIGNATURES_KEY: &str = "logSignatures";
+const WATCHED_PATHS_KEY: &str = "watchedPaths";
+const PRUNE_ON_STARTUP_KEY: &str = "pruneStaleStateOnStartup";
+const MAX_BACKFILL_KEY: &str = "maxBackfillEvents";
+/// Whether watches parse `DamageTick` events. Off by default.
+const DAMAGE_TICKS_KEY: &str = "parseDamageTicks";
+/// Whether watches emit `LocationTransition` events. Off by default.
+const LOCATION_TRANSITIONS_KEY: &str = "parseLocationTransitions";
+/// Whether watches emit `LoadoutChange` events. Off by default.
+const LOADOUT_CHANGES_KEY: &str = "parseLoadoutChanges";
+/// The parser profile chosen by hand, when auto-detection shouldn't pick.
+const PARSER_PROFILE_KEY: &str = "parserProfile";
+
+pub const BACKFILL_TRUNCATED_EVENT: &str = "backfill-truncated";
+pub const WATCH_ALREADY_ACTIVE_EVENT: &str = "watch-already-active";
+pub const OFFSET_RESET_EVENT: &str = "offset-reset";
+pub const BETTER_LOG_AVAILABLE_EVENT: &str = "better-log-available";
+
+/// How much more recently another Game.log must have been written than a
+/// watched one before it's suggested instead.
+const STALE_LOG_THRESHOLD_KEY: &str = "staleLogThresholdHours";
+const DEFAULT_STALE_LOG_THRESHOLD_HOURS: u64 = 24;
+
+/// How many historical events a watch emits while catching up, unless
+/// configured otherwise. The most recent ones are kept.
+const DEFAULT_MAX_BACKFILL_EVENTS: usize = 2000;
+
+/// How much of the start of a log goes into its signature. The header
+/// includes the session start time, so a new session gets a new signature.
+const SIGNATURE_BYTES: u64 = 1024;
+
+/// A running tail of one Game.log.
+pub struct WatchHandle {
+    pub path: PathBuf,
+    stop: AtomicBool,
+    /// Byte offset of the first line not yet parsed.
+    offset: AtomicU64,
+    /// Events parsed from the current session, indexed like `KIND_NAMES`.
+    kind_counts: [AtomicU64; KIND_NAMES.len()],
+    /// Handle from the most recent `PlayerIdentified` line in this log.
+    local_handle: Mutex<Option<String>>,
+    /// Line state carried across polls, reset when the log rotates.
+    parser: Mutex<LineParser>,
+    /// Cap on events emitted by the catch-up read; 0 means no cap.
+    max_backfill_events: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackfillTruncated {
+    pub path: String,
+    pub skipped: usize,
+    pub kept: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchStatus {
+    pub path: String,
+    pub offset: u64,
+    pub local_handle: Option<String>,
+}
+
+impl WatchHandle {
+    fn new(path: PathBuf, offset: u64, parser: LineParser, max_backfill_events: usize) -> Self {
+        WatchHandle {
+            path,
+            stop: AtomicBool::new(false),
+            offset: AtomicU64::new(offset),
+            kind_counts: Default::default(),
+            local_handle: Mutex::new(None),
+            parser: Mutex::new(parser),
+            max_backfill_events,
+        }
+    }
+
+    fn count(&self, event: &LogEvent) {
+        if let Some(slot) = KIND_NAMES
+            .iter()
+            .position(|name| *name == event.kind_name())
+        {
+            self.kind_counts[slot].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn kind_counts(&self) -> HashMap<String, u64> {
+        KIND_NAMES
+            .iter()
+            .zip(&self.kind_counts)
+            .map(|(name, count)| (name.to_string(), count.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    fn status(&self) -> WatchStatus {
+        WatchStatus {
+            path: self.path.to_string_lossy().to_string(),
+            offset: self.offset.load(Ordering::Relaxed),
+            local_handle: self.local_handle.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// All active watches, keyed by the path they were started with.
+#[derive(Default)]
+pub struct WatchRegistry {
+    watches: Mutex<HashMap<String, Arc<WatchHandle>>>,
+    polling: Mutex<PollSettings>,
+}
+
+/// How often watches poll. With `adaptive` off they poll every 500 ms;
+/// with it on, between `min_ms` and `max_ms` depending on how fast the log
+/// is being written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PollSettings {
+    pub adaptive: bool,
+    pub min_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Default for PollSettings {
+    fn default() -> Self {
+        PollSettings {
+            adaptive: false,
+            min_ms: DEFAULT_MIN_POLL_MS,
+            max_ms: DEFAULT_MAX_POLL_MS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PollAdapted {
+    pub path: String,
+    pub interval_ms: u64,
+    /// Bytes read by the poll that caused the change.
+    pub bytes: u64,
+}
+
+/// The interval after a poll that read `bytes`: straight to the minimum on a
+/// burst, halving while writes trickle in, doubling towards the maximum
+/// while the log is quiet.
+fn next_interval(current: Duration, bytes: u64, settings: &PollSettings) -> Duration {
+    let min = Duration::from_millis(settings.min_ms);
+    let max = Duration::from_millis(settings.max_ms);
+    let next = match bytes {
+        0 => current * 2,
+        bytes if bytes >= BURST_BYTES => min,
+        _ => current / 2,
+    };
+    next.clamp(min, max)
+}
+
+impl WatchRegistry {
+    fn paths(&self) -> Vec<String> {
+        self.watches.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn statuses(&self) -> Vec<WatchStatus> {
+        self.watches
+            .lock()
+            .unwrap()
+            .values()
+            .map(|handle| handle.status())
+            .collect()
+    }
+
+    pub fn is_watching(&self, path: &str) -> bool {
+        self.watches.lock().unwrap().contains_key(path)
+    }
+
+    /// Register `handle` as the watch on `path`. An existing watch is stopped
+    /// and replaced if `replace` is set, and otherwise kept and returned, in
+    /// which case `handle` must not be run.
+    fn claim(
+        &self,
+        path: &str,
+        handle: Arc<WatchHandle>,
+        replace: bool,
+    ) -> Option<Arc<WatchHandle>> {
+        let mut watches = self.watches.lock().unwrap();
+        match watches.get(path) {
+            Some(existing) if !replace => Some(existing.clone()),
+            _ => {
+                if let Some(previous) = watches.insert(path.to_string(), handle) {
+                    previous.stop.store(true, Ordering::Relaxed);
+                }
+                None
+            }
+        }
+    }
+
+    /// Claim `path` for `handle` and pass it to `spawn` to be run. If the path
+    /// is already watched and `replace` isn't set, nothing is spawned and the
+    /// running watch is returned instead. Returns the watch now running and
+    /// whether it was already running.
+    fn start_with(
+        &self,
+        path: &str,
+        handle: Arc<WatchHandle>,
+        replace: bool,
+        spawn: impl FnOnce(Arc<WatchHandle>),
+    ) -> (Arc<WatchHandle>, bool) {
+        if let Some(existing) = self.claim(path, handle.clone(), replace) {
+            return (existing, true);
+        }
+        spawn(handle.clone());
+        (handle, false)
+    }
+}
+
+/// Hash of the first few hundred bytes of a log, used to tell whether a path
+/// still holds the same log a persisted offset was recorded against.
+pub fn file_signature(path: &Path) -> io::Result<String> {
+    let mut head = Vec::new();
+    File::open(path)?
+        .take(SIGNATURE_BYTES)
+        .read_to_end(&mut head)?;
+
+    let mut hasher = Fnv1a::new();
+    hasher.write(&head);
+    Ok(hasher.finish_hex())
+}
+
+fn update_map<T: Serialize + serde::de::DeserializeOwned>(
+    app: &AppHandle,
+    key: &str,
+    path: &str,
+    value: T,
+) {
+    let mut map: HashMap<String, T> = settings::get(app, key).ok().flatten().unwrap_or_default();
+    map.insert(path.to_string(), value);
+    if let Err(e) = settings::set(app, key, &map) {
+        tracing::warn!("Failed to persist {}: {}", key, e);
+    }
+}
+
+fn persist_watched_paths(app: &AppHandle, registry: &WatchRegistry) {
+    if let Err(e) = settings::set(app, WATCHED_PATHS_KEY, &registry.paths()) {
+        tracing::warn!("Failed to persist {}: {}", WATCHED_PATHS_KEY, e);
+    }
+}
+
+fn damage_ticks(app: &AppHandle) -> bool {
+    settings::get(app, DAMAGE_TICKS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+}
+
+fn location_transitions(app: &AppHandle) -> bool {
+    settings::get(app, LOCATION_TRANSITIONS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+}
+
+fn loadout_changes(app: &AppHandle) -> bool {
+    settings::get(app, LOADOUT_CHANGES_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+}
+
+fn line_parser(app: &AppHandle) -> LineParser {
+    let mut parser = LineParser::with_damage_ticks(damage_ticks(app));
+    parser.set_location_transitions(location_transitions(app));
+    parser.set_loadout_changes(loadout_changes(app));
+    parser
+}
+
+fn max_backfill_events(app: &AppHandle) -> usize {
+    settings::get(app, MAX_BACKFILL_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or(DEFAULT_MAX_BACKFILL_EVENTS)
+}
+
+/// Start tailing a Game.log from `offset`, replacing any existing watch on the
+/// same path. `max_backfill_events` overrides the configured catch-up cap.
+pub fn start(
+    app: &AppHandle,
+    registry: &WatchRegistry,
+    path: String,
+    offset: u64,
+    max_backfill_events: Option<usize>,
+) -> Result<WatchStatus, String> {
+    launch(app, registry, path, offset, max_backfill_events, true).map(|(status, _)| status)
+}
+
+/// `start`, leaving an existing watch on the same path running unless
+/// `replace` is set. Returns the status of the watch now running and whether
+/// it was already running.
+pub(crate) fn launch(
+    app: &AppHandle,
+    registry: &WatchRegistry,
+    path: String,
+    offset: u64,
+    max_backfill_events: Option<usize>,
+    replace: bool,
+) -> Result<(WatchStatus, bool), String> {
+    let file_path = PathBuf::from(&path);
+    let signature = file_signature(&file_path).map_err(|e| e.to_string())?;
+
+    let handle = Arc::new(WatchHandle::new(
+        file_path,
+        offset,
+        line_parser(app),
+        max_backfill_events.unwrap_or_else(|| self::max_backfill_events(app)),
+    ));
+
+    let (running, already_active) = registry.start_with(&path, handle, replace, |handle| {
+        update_map(app, SIGNATURES_KEY, &path, signature);
+        persist_watched_paths(app, registry);
+
+        tracing::info!("Watching {} from offset {}", path, offset);
+        let app = app.clone();
+        thread::spawn(move || run(app, handle));
+    });
+    Ok((running.status(), already_active))
+}
+
+/// Stop the watch on `path`. Returns whether one was running.
+pub fn stop(app: &AppHandle, registry: &WatchRegistry, path: &str) -> bool {
+    let Some(handle) = registry.watches.lock().unwrap().remove(path) else {
+        return false;
+    };
+    handle.stop.store(true, Ordering::Relaxed);
+    persist_watched_paths(app, registry);
+    true
+}
+
+/// Load the polling settings. Called once from `setup`, before `restore`.
+pub fn init(app: &AppHandle) {
+    if let Some(polling) = settings::get(app, ADAPTIVE_POLLING_KEY).ok().flatten() {
+        *app.state::<WatchRegistry>().polling.lock().unwrap() = polling;
+    }
+}
+
+/// Resume every watch that was running when the app last exited, from its
+/// persisted offset. Called once from `setup`.
+pub fn restore(app: &AppHandle) {
+    if settings::get::<bool>(app, PRUNE_ON_STARTUP_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+    {
+        if let Err(e) = prune(app) {
+            tracing::warn!("Failed to prune stale watch state: {}", e);
+        }
+    }
+
+    let watched: Vec<String> = settings::get(app, WATCHED_PATHS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    match repair_offsets(app) {
+        Ok(resets) => {
+            for reset in resets {
+                if let Err(e) = app.emit(OFFSET_RESET_EVENT, reset) {
+                    tracing::warn!("Failed to emit {}: {}", OFFSET_RESET_EVENT, e);
+                }
+            }
+        }
+        Err(e) => tracing::warn!("Failed to check persisted offsets: {}", e),
+    }
+    let offsets: HashMap<String, u64> = settings::get(app, OFFSETS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let registry = app.state::<WatchRegistry>();
+
+    for path in watched {
+        let offset = offsets.get(&path).copied().unwrap_or(0);
+        match start(app, &registry, path.clone(), offset, None) {
+            Ok(_) => advise_better_log(app, &path),
+            Err(e) => tracing::warn!("Failed to restore watch on {}: {}", path, e),
+        }
+    }
+}
+
+/// Suggests a log written much more recently than the watched one, as after
+/// the game was reinstalled somewhere else and the old log left behind.
+#[derive(Debug, Clone, Serialize)]
+pub struct BetterLogAvailable {
+    pub path: String,
+    pub modified_ms: i64,
+    pub better_path: String,
+    pub better_modified_ms: i64,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as i64)
+}
+
+/// Whether `newest` is a different log written more than `threshold` after
+/// `watched`.
+fn is_better_log(
+    watched: (&Path, SystemTime),
+    newest: (&Path, SystemTime),
+    threshold: Duration,
+) -> bool {
+    let same = match (fs::canonicalize(watched.0), fs::canonicalize(newest.0)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => watched.0 == newest.0,
+    };
+    !same
+        && newest
+            .1
+            .duration_since(watched.1)
+            .is_ok_and(|newer_by| newer_by > threshold)
+}
+
+/// Emit `better-log-available` if another install's Game.log is much newer
+/// than `path`. The watch itself is left alone.
+fn advise_better_log(app: &AppHandle, path: &str) {
+    let hours: u64 = settings::get(app, STALE_LOG_THRESHOLD_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or(DEFAULT_STALE_LOG_THRESHOLD_HOURS);
+    let threshold = Duration::from_secs(hours * 3600);
+    let watched = Path::new(path);
+    let (Some(watched_at), Some(newest)) = (modified(watched), game::newest_log()) else {
+        return;
+    };
+    let Some(newest_at) = modified(&newest) else {
+        return;
+    };
+    if !is_better_log((watched, watched_at), (&newest, newest_at), threshold) {
+        return;
+    }
+
+    tracing::info!(
+        "{} is much newer than the watched {}",
+        newest.display(),
+        path
+    );
+    let advice = BetterLogAvailable {
+        path: path.to_string(),
+        modified_ms: millis(watched_at),
+        better_path: newest.to_string_lossy().to_string(),
+        better_modified_ms: millis(newest_at),
+    };
+    if let Err(e) = app.emit(BETTER_LOG_AVAILABLE_EVENT, advice) {
+        tracing::warn!("Failed to emit {}: {}", BETTER_LOG_AVAILABLE_EVENT, e);
+    }
+}
+
+/// How many hours newer another Game.log must be than a watched one before
+/// `better-log-available` suggests it.
+#[tauri::command]
+pub fn set_stale_log_threshold(app: AppHandle, hours: u64) -> Result<(), String> {
+    settings::set(&app, STALE_LOG_THRESHOLD_KEY, &hours)
+}
+
+/// Start tailing a Game.log. By default the whole file is backfilled first,
+/// keeping only the most recent `max_backfill_events` (or the configured cap);
+/// pass `from_end` to only pick up lines written from now on. If the path is
+/// already being watched, that watch is left running and its status returned
+/// (with `watch-already-active` emitted) unless `force` restarts it.
+#[tauri::command]
+pub fn start_log_watch(
+    app: AppHandle,
+    registry: State<'_, WatchRegistry>,
+    path: String,
+    from_end: Option<bool>,
+    max_backfill_events: Option<usize>,
+    force: Option<bool>,
+) -> Result<WatchStatus, String> {
+    let offset = if from_end.unwrap_or(false) {
+        fs::metadata(&path).map_err(|e| e.to_string())?.len()
+    } else {
+        0
+    };
+    let (status, already_active) = launch(
+        &app,
+        &registry,
+        path,
+        offset,
+        max_backfill_events,
+        force.unwrap_or(false),
+    )?;
+    recents::record(&app, &status.path);
+    advise_better_log(&app, &status.path);
+    if already_active {
+        tracing::debug!("{} is already being watched", status.path);
+        if let Err(e) = app.emit(WATCH_ALREADY_ACTIVE_EVENT, status.clone()) {
+            tracing::warn!("Failed to emit {}: {}", WATCH_ALREADY_ACTIVE_EVENT, e);
+        }
+    }
+    Ok(status)
+}
+
+/// Turn adaptive polling on or off, optionally changing its bounds. Applies to
+/// running watches from their next poll.
+#[tauri::command]
+pub fn set_adaptive_polling(
+    app: AppHandle,
+    registry: State<'_, WatchRegistry>,
+    enabled: bool,
+    min_ms: Option<u64>,
+    max_ms: Option<u64>,
+) -> Result<PollSettings, String> {
+    let mut polling = *registry.polling.lock().unwrap();
+    polling.adaptive = enabled;
+    polling.min_ms = min_ms.unwrap_or(polling.min_ms);
+    polling.max_ms = max_ms.unwrap_or(polling.max_ms);
+    if polling.min_ms == 0 || polling.min_ms > polling.max_ms {
+        return Err(format!(
+            "Invalid polling bounds: {} to {} ms",
+            polling.min_ms, polling.max_ms
+        ));
+    }
+
+    settings::set(&app, ADAPTIVE_POLLING_KEY, &polling)?;
+    *registry.polling.lock().unwrap() = polling;
+    Ok(polling)
+}
+
+#[tauri::command]
+pub fn get_poll_settings(registry: State<'_, WatchRegistry>) -> PollSettings {
+    *registry.polling.lock().unwrap()
+}
+
+/// Set the default catch-up cap for new watches. 0 turns the cap off.
+#[tauri::command]
+pub fn set_max_backfill_events(app: AppHandle, max_events: usize) -> Result<(), String> {
+    settings::set(&app, MAX_BACKFILL_KEY, &max_events)
+}
+
+/// Turn `DamageTick` parsing on or off, for running watches too. They count
+/// towards the catch-up cap like any other event.
+#[tauri::command]
+pub fn set_parse_damage_ticks(
+    app: AppHandle,
+    registry: State<'_, WatchRegistry>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings::set(&app, DAMAGE_TICKS_KEY, &enabled)?;
+    for handle in registry.watches.lock().unwrap().values() {
+        handle.parser.lock().unwrap().set_damage_ticks(enabled);
+    }
+    Ok(())
+}
+
+/// Turn `LocationTransition` events on or off, for running watches too.
+/// Deaths are placed in the victim's interior either way.
+#[tauri::command]
+pub fn set_parse_location_transitions(
+    app: AppHandle,
+    registry: State<'_, WatchRegistry>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings::set(&app, LOCATION_TRANSITIONS_KEY, &enabled)?;
+    for handle in registry.watches.lock().unwrap().values() {
+        handle
+            .parser
+            .lock()
+            .unwrap()
+            .set_location_transitions(enabled);
+    }
+    Ok(())
+}
+
+/// Turn `LoadoutChange` events on or off, for running watches too. The
+/// local player's deaths list what they were carrying either way.
+#[tauri::command]
+pub fn set_parse_loadout_changes(
+    app: AppHandle,
+    registry: State<'_, WatchRegistry>,
+    enabled: bool,
+) -> Result<(), String> {
+    settings::set(&app, LOADOUT_CHANGES_KEY, &enabled)?;
+    for handle in registry.watches.lock().unwrap().values() {
+        handle.parser.lock().unwrap().set_loadout_changes(enabled);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParserProfile {
+    pub name: String,
+    /// Whether `name` was chosen with `set_parser_profile` rather than
+    /// detected.
+    pub overridden: bool,
+}
+
+fn parser_profile(app: &AppHandle) -> ParserProfile {
+    let chosen = settings::get::<String>(app, PARSER_PROFILE_KEY)
+        .ok()
+        .flatten()
+        .filter(|name| parser::check_profile(name).is_ok());
+    ParserProfile {
+        overridden: chosen.is_some(),
+        name: chosen.unwrap_or_else(|| parser::DEFAULT_PROFILE.to_string()),
+    }
+}
+
+/// The profile watches parse with. With a single known profile, detection
+/// always picks `default`.
+#[tauri::command]
+pub fn get_active_parser_profile(app: AppHandle) -> ParserProfile {
+    parser_profile(&app)
+}
+
+/// Force a parser profile, or go back to detecting it with `None`. The name
+/// must be one of `parser::PROFILES`. Every profile reads logs the same way
+/// for now, so running watches carry on as they are.
+#[tauri::command]
+pub fn set_parser_profile(app: AppHandle, name: Option<String>) -> Result<ParserProfile, String> {
+    match name {
+        Some(name) => {
+            parser::check_profile(&name)?;
+            settings::set(&app, PARSER_PROFILE_KEY, &name)?;
+        }
+        None => settings::remove(&app, PARSER_PROFILE_KEY)?,
+    }
+    Ok(parser_profile(&app))
+}
+
+/// Returns whether a watch was running for that path.
+#[tauri::command]
+pub fn stop_log_watch(app: AppHandle, registry: State<'_, WatchRegistry>, path: String) -> bool {
+    stop(&app, &registry, &path)
+}
+
+#[tauri::command]
+pub fn list_log_watches(registry: State<'_, WatchRegistry>) -> Vec<WatchStatus> {
+    registry.statuses()
+}
+
+/// How many events of each kind the watch on `path` has parsed since the log
+/// last rotated.
+#[tauri::command]
+pub fn get_watch_kind_counts(
+    registry: State<'_, WatchRegistry>,
+    path: String,
+) -> Result<HashMap<String, u64>, String> {
+    let watches = registry.watches.lock().unwrap();
+    let handle = watches
+        .get(&path)
+        .ok_or_else(|| format!("{} is not being watched", path))?;
+    Ok(handle.kind_counts())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OffsetResetReason {
+    /// The log's header changed, so it was replaced by a new session.
+    SignatureChanged,
+    /// The log is shorter than the offset.
+    PastEnd,
+}
+
+/// A persisted offset that no longer fit its log and was set back to 0.
+#[derive(Debug, Clone, Serialize)]
+pub struct OffsetReset {
+    pub path: String,
+    pub previous_offset: u64,
+    pub reason: OffsetResetReason,
+}
+
+/// Why a persisted offset can't be resumed from, if it can't.
+fn check_offset(
+    offset: u64,
+    stored_signature: Option<&str>,
+    signature: &str,
+    len: u64,
+) -> Option<OffsetResetReason> {
+    if stored_signature != Some(signature) {
+        Some(OffsetResetReason::SignatureChanged)
+    } else if offset > len {
+        Some(OffsetResetReason::PastEnd)
+    } else {
+        None
+    }
+}
+
+/// Check every persisted offset against its log as it is now, resetting any
+/// that no longer fit to 0 and recording the log's current signature. Logs
+/// that can't be read are left alone. Returns the offsets that were reset.
+fn repair_offsets(app: &AppHandle) -> Result<Vec<OffsetReset>, String> {
+    checkpoint::flush(app)?;
+    let mut offsets: HashMap<String, u64> = settings::get(app, OFFSETS_KEY)?.unwrap_or_default();
+    let mut signatures: HashMap<String, String> =
+        settings::get(app, SIGNATURES_KEY)?.unwrap_or_default();
+
+    let mut resets = Vec::new();
+    for (path, offset) in offsets.iter_mut() {
+        let file = Path::new(path);
+        let (Ok(signature), Ok(metadata)) = (file_signature(file), fs::metadata(file)) else {
+            continue;
+        };
+        let stored = signatures.get(path).map(String::as_str);
+        if let Some(reason) = check_offset(*offset, stored, &signature, metadata.len()) {
+            tracing::info!("Resetting the offset of {}: {:?}", path, reason);
+            resets.push(OffsetReset {
+                path: path.clone(),
+                previous_offset: *offset,
+                reason,
+            });
+            *offset = 0;
+            signatures.insert(path.clone(), signature);
+        }
+    }
+
+    if !resets.is_empty() {
+        settings::set(app, OFFSETS_KEY, &offsets)?;
+        settings::set(app, SIGNATURES_KEY, &signatures)?;
+    }
+    Ok(resets)
+}
+
+/// Check persisted offsets against their logs now, as is done on startup,
+/// and reset any that no longer fit. Returns the ones that were reset.
+#[tauri::command]
+pub fn verify_offsets(app: AppHandle) -> Result<Vec<OffsetReset>, String> {
+    repair_offsets(&app)
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PruneReport {
+    pub offsets_removed: usize,
+    pub signatures_removed: usize,
+    pub watched_paths_removed: usize,
+}
+
+fn prune_map<T: Serialize + serde::de::DeserializeOwned>(
+    app: &AppHandle,
+    key: &str,
+) -> Result<usize, String> {
+    let mut map: HashMap<String, T> = settings::get(app, key)?.unwrap_or_default();
+    let before = map.len();
+    map.retain(|path, _| Path::new(path).exists());
+
+    let removed = before - map.len();
+    if removed > 0 {
+        settings::set(app, key, &map)?;
+    }
+    Ok(removed)
+}
+
+fn prune(app: &AppHandle) -> Result<PruneReport, String> {
+    let mut watched: Vec<String> = settings::get(app, WATCHED_PATHS_KEY)?.unwrap_or_default();
+    let before = watched.len();
+    watched.retain(|path| Path::new(path).exists());
+    if watched.len() != before {
+        settings::set(app, WATCHED_PATHS_KEY, &watched)?;
+    }
+
+    Ok(PruneReport {
+        offsets_removed: prune_map::<u64>(app, OFFSETS_KEY)?,
+        signatures_removed: prune_map::<String>(app, SIGNATURES_KEY)?,
+        watched_paths_removed: before - watched.len(),
+    })
+}
+
+/// Drop persisted offsets, signatures and watched paths for logs that no
+/// longer exist on disk.
+#[tauri::command]
+pub fn prune_stale_state(app: AppHandle) -> Result<PruneReport, String> {
+    prune(&app)
+}
+
+#[tauri::command]
+pub fn set_prune_stale_state_on_startup(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, PRUNE_ON_STARTUP_KEY, &enabled)
+}
+
+/// Keep only the last `max` events (all of them if `max` is 0). Returns how
+/// many were dropped.
+fn cap_backfill(events: &mut Vec<LogEvent>, max: usize) -> usize {
+    if max == 0 || events.len() <= max {
+        return 0;
+    }
+    let skipped = events.len() - max;
+    events.drain(..skipped);
+    skipped
+}
+
+fn run(app: AppHandle, handle: Arc<WatchHandle>) {
+    let path = handle.path.to_string_lossy().to_string();
+    let mut recorded_offset = None;
+    let mut catching_up = true;
+    let mut interval = POLL_INTERVAL;
+
+    while !handle.stop.load(Ordering::Relaxed) {
+        let mut bytes = 0;
+        let polled = {
+            let priority = if catching_up {
+                Priority::Background
+            } else {
+                Priority::Live
+            };
+            let budget = app.state::<WorkBudget>();
+            let _permit = budget.acquire(priority);
+            poll(&handle)
+        };
+        match polled {
+            Ok(mut poll) => {
+                bytes = poll.bytes;
+                // A truncated log is read again from the start, which is history too
+                let backfill = catching_up || poll.new_signature.is_some();
+                if let Some(signature) = poll.new_signature {
+                    tracing::info!("{} was truncated, reading again from the start", path);
+                    update_map(&app, SIGNATURES_KEY, &path, signature);
+                }
+                // Only the first read is history; after that it's live tailing
+                if std::mem::take(&mut catching_up) {
+                    pipeline::apply_cutoff(&app, &mut poll.events);
+                    let skipped = cap_backfill(&mut poll.events, handle.max_backfill_events);
+                    if skipped > 0 {
+                        tracing::info!(
+                            "Skipped {} old events while catching up on {}",
+                            skipped,
+                            path
+                        );
+                        let notice = BackfillTruncated {
+                            path: path.clone(),
+                            skipped,
+                            kept: poll.events.len(),
+                        };
+                        if let Err(e) = app.emit(BACKFILL_TRUNCATED_EVENT, notice) {
+                            tracing::warn!("Failed to emit {}: {}", BACKFILL_TRUNCATED_EVENT, e);
+                        }
+                    }
+                } else if !backfill {
+                    clock::observe_live(&app, &poll.events);
+                }
+                if backfill {
+                    pipeline::emit_backfill_batch(&app, poll.events);
+                } else {
+                    pipeline::emit_batch(&app, poll.events);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to read {}: {}", handle.path.display(), e),
+        }
+
+        let offset = handle.offset.load(Ordering::Relaxed);
+        if recorded_offset != Some(offset) {
+            checkpoint::record(&app, &path, offset);
+            recorded_offset = Some(offset);
+        }
+
+        let polling = *app.state::<WatchRegistry>().polling.lock().unwrap();
+        let next = if polling.adaptive {
+            next_interval(interval, bytes, &polling)
+        } else {
+            POLL_INTERVAL
+        };
+        if next != interval && polling.adaptive {
+            tracing::debug!("Polling {} every {:?}", path, next);
+            let adapted = PollAdapted {
+                path: path.clone(),
+                interval_ms: next.as_millis() as u64,
+                bytes,
+            };
+            if let Err(e) = app.emit(POLL_ADAPTED_EVENT, adapted) {
+                tracing::warn!("Failed to emit {}: {}", POLL_ADAPTED_EVENT, e);
+            }
+        }
+        interval = next;
+        thread::sleep(interval);
+    }
+}
+
+struct Poll {
+    events: Vec<LogEvent>,
+    /// How much of the log this poll read.
+    bytes: u64,
+    /// Set when the log was truncated and restarted since the last poll.
+    new_signature: Option<String>,
+}
+
+/// Read and parse every complete line appended since the last poll.
+fn poll(handle: &WatchHandle) -> io::Result<Poll> {
+    let len = fs::metadata(&handle.path)?.len();
+    let mut offset = handle.offset.load(Ordering::Relaxed);
+    let mut new_signature = None;
+
+    // The game truncates Game.log when a new session starts
+    if len < offset {
+        offset = 0;
+        for count in &handle.kind_counts {
+            count.store(0, Ordering::Relaxed);
+        }
+        handle.parser.lock().unwrap().reset();
+        new_signature = Some(file_signature(&handle.path)?);
+    }
+    let start = offset;
+    if len == offset {
+        handle.offset.store(offset, Ordering::Relaxed);
+        return Ok(Poll {
+            events: Vec::new(),
+            bytes: 0,
+            new_signature,
+        });
+    }
+
+    let mut file = File::open(&handle.path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(file.take(len - offset));
+    let mut events = Vec::new();
+    let mut buf = Vec::new();
+    let mut local_handle = handle.local_handle.lock().unwrap();
+    let mut parser = handle.parser.lock().unwrap();
+
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        // A lone `\r` ends a line too, for logs with old Mac line endings.
+        // Whatever follows the last line ending is still being written, so
+        // it's left for the next poll. A `\r` that turns out to be half of a
+        // `\r\n` just leaves an empty line for the next poll to skip.
+        let complete = if buf.ends_with(b"\n") {
+            buf.len()
+        } else {
+            match buf.iter().rposition(|byte| *byte == b'\r') {
+                Some(end) => end + 1,
+                None => break,
+            }
+        };
+        offset += complete as u64;
+
+        for (_, line) in parser::split_lines(&buf[..complete]) {
+            for mut event in parser.parse(&String::from_utf8_lossy(line)) {
+                if let EventKind::PlayerIdentified { handle, .. } = &event.kind {
+                    *local_handle = Some(handle.clone());
+                }
+                event.severity = Some(classify_severity(&event, local_handle.as_deref()));
+                handle.count(&event);
+                events.push(event);
+            }
+        }
+        if complete < buf.len() {
+            break;
+        }
+    }
+
+    handle.offset.store(offset, Ordering::Relaxed);
+    Ok(Poll {
+        events,
+        bytes: offset - start,
+        new_signature,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn signature_ignores_lines_past_the_header() {
+        let path = std::env::temp_dir().join(format!("picologs-sig-{}.log", std::process::id()));
+        let header = "x".repeat(SIGNATURE_BYTES as usize);
+        fs::write(&path, &header).unwrap();
+        let original = file_signature(&path).unwrap();
+
+        fs::write(&path, format!("{}\nmore lines", header)).unwrap();
+        assert_eq!(file_signature(&path).unwrap(), original);
+
+        fs::write(&path, "a new session").unwrap();
+        assert_ne!(file_signature(&path).unwrap(), original);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tails_logs_with_carriage_return_line_endings() {
+        let path = std::env::temp_dir().join(format!("picologs-cr-{}.log", std::process::id()));
+        let quit = "<2024-06-07T12:34:56.789Z> [Notice] <SystemQuit> CSystem::Quit invoked";
+        fs::write(&path, format!("{quit}\r{quit}\r{quit}")).unwrap();
+        let handle = handle(&path);
+
+        // The last line has no ending yet, so it waits
+        let first = poll(&handle).unwrap();
+        assert_eq!(first.events.len(), 2);
+        assert_eq!(first.bytes as usize, 2 * (quit.len() + 1));
+
+        // A `\r\n` split across polls still counts as one line ending
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"\r").unwrap();
+        assert_eq!(poll(&handle).unwrap().events.len(), 1);
+        file.write_all(format!("\n{quit}\r\n").as_bytes()).unwrap();
+        assert_eq!(poll(&handle).unwrap().events.len(), 1);
+        assert_eq!(
+            handle.offset.load(Ordering::Relaxed),
+            fs::metadata(&path).unwrap().len()
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn backfill_keeps_the_most_recent_events() {
+        let mut events: Vec<LogEvent> = (0..5)
+            .map(|i| LogEvent::test(EventKind::SystemQuit).at(i))
+            .collect();
+        assert_eq!(cap_backfill(&mut events, 0), 0);
+        assert_eq!(cap_backfill(&mut events, 10), 0);
+        assert_eq!(cap_backfill(&mut events, 2), 3);
+        let kept: Vec<_> = events.iter().map(|e| e.timestamp_ms).collect();
+        assert_eq!(kept, vec![Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn kind_counts_reset_when_the_log_rotates() {
+        let path = std::env::temp_dir().join(format!("picologs-counts-{}.log", std::process::id()));
+        let quit = "<2024-06-07T12:34:56.789Z> [Notice] <SystemQuit> CSystem::Quit invoked\n";
+        fs::write(&path, quit.repeat(3)).unwrap();
+
+        let handle = WatchHandle {
+            path: path.clone(),
+            stop: AtomicBool::new(false),
+            offset: AtomicU64::new(0),
+            kind_counts: Default::default(),
+            local_handle: Mutex::new(None),
+            parser: Mutex::default(),
+            max_backfill_events: 0,
+        };
+        poll(&handle).unwrap();
+        assert_eq!(handle.kind_counts()["system_quit"], 3);
+        assert_eq!(handle.kind_counts()["actor_death"], 0);
+
+        fs::write(&path, quit).unwrap();
+        poll(&handle).unwrap();
+        assert_eq!(handle.kind_counts()["system_quit"], 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    fn handle(path: &Path) -> Arc<WatchHandle> {
+        Arc::new(WatchHandle::new(
+            path.to_path_buf(),
+            0,
+            LineParser::default(),
+            0,
+        ))
+    }
+
+    #[test]
+    fn starting_a_watched_path_again_keeps_one_watch() {
+        let path = std::env::temp_dir().join(format!("picologs-dup-{}.log", std::process::id()));
+        let quit = "<2024-06-07T12:34:56.789Z> [Notice] <SystemQuit> CSystem::Quit invoked\n";
+        fs::write(&path, quit.repeat(2)).unwrap();
+        let key = path.to_string_lossy().to_string();
+        let registry = WatchRegistry::default();
+
+        let mut tailers = Vec::new();
+        let mut start = |handle, replace| {
+            registry.start_with(&key, handle, replace, |handle| tailers.push(handle))
+        };
+
+        let first = handle(&path);
+        let (running, already_active) = start(first.clone(), false);
+        assert!(Arc::ptr_eq(&running, &first));
+        assert!(!already_active);
+
+        // The second start spawns nothing and hands back the first watch
+        let (running, already_active) = start(handle(&path), false);
+        assert!(Arc::ptr_eq(&running, &first));
+        assert!(already_active);
+        assert_eq!(running.status(), first.status());
+        assert_eq!(tailers.len(), 1);
+        assert!(Arc::ptr_eq(&tailers[0], &first));
+        assert_eq!(registry.paths(), vec![key.clone()]);
+        assert!(!first.stop.load(Ordering::Relaxed));
+
+        // Only the registered watch is run, so each line is emitted once
+        let running: Vec<_> = registry.watches.lock().unwrap().values().cloned().collect();
+        let events: usize = running.iter().map(|h| poll(h).unwrap().events.len()).sum();
+        assert_eq!(events, 2);
+
+        let forced = handle(&path);
+        let (running, already_active) =
+            registry.start_with(&key, forced.clone(), true, |handle| tailers.push(handle));
+        assert!(Arc::ptr_eq(&running, &forced));
+        assert!(!already_active);
+        assert_eq!(tailers.len(), 2);
+        assert!(first.stop.load(Ordering::Relaxed));
+        assert!(Arc::ptr_eq(
+            &registry.watches.lock().unwrap()[&key],
+            &forced
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tails_logs_with_a_bom_and_mixed_line_endings() {
+        let path = std::env::temp_dir().join(format!("picologs-bom-{}.log", std::process::id()));
+        let quit = "<2024-06-07T12:34:56.789Z> [Notice] <SystemQuit> CSystem::Quit invoked";
+        let contents = format!("\u{feff}{quit}\r\n{quit}\r{quit}\n{quit}");
+        fs::write(&path, &contents).unwrap();
+
+        let watch = handle(&path);
+        let polled = poll(&watch).unwrap();
+        assert_eq!(polled.events.len(), 3);
+        assert!(polled
+            .events
+            .iter()
+            .all(|event| event.timestamp_ms.is_some()));
+        // The last line has no ending yet, so it waits for the next poll
+        let unread = watch.offset.load(Ordering::Relaxed) as usize;
+        assert_eq!(&contents[unread..], quit);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_much_newer_log_elsewhere_is_better() {
+        let hour = Duration::from_secs(3600);
+        let watched = Path::new("D:/Old/StarCitizen/LIVE/Game.log");
+        let newest = Path::new("E:/New/StarCitizen/LIVE/Game.log");
+        let then = UNIX_EPOCH + 1000 * hour;
+
+        assert!(is_better_log(
+            (watched, then),
+            (newest, then + 25 * hour),
+            24 * hour
+        ));
+        assert!(!is_better_log(
+            (watched, then),
+            (newest, then + 23 * hour),
+            24 * hour
+        ));
+        assert!(!is_better_log(
+            (watched, then + 25 * hour),
+            (newest, then),
+            24 * hour
+        ));
+        assert!(!is_better_log(
+            (watched, then),
+            (watched, then + 25 * hour),
+            24 * hour
+        ));
+    }
+
+    #[test]
+    fn offsets_reset_when_the_log_was_replaced_or_shrank() {
+        assert_eq!(check_offset(100, Some("abc"), "abc", 100), None);
+        assert_eq!(
+            check_offset(100, Some("abc"), "def", 500),
+            Some(OffsetResetReason::SignatureChanged)
+        );
+        assert_eq!(
+            check_offset(100, None, "abc", 500),
+            Some(OffsetResetReason::SignatureChanged)
+        );
+        assert_eq!(
+            check_offset(101, Some("abc"), "abc", 100),
+            Some(OffsetResetReason::PastEnd)
+        );
+    }
+
+    #[test]
+    fn adaptive_polling_backs_off_when_idle_and_tightens_on_bursts() {
+        let settings = PollSettings {
+            adaptive: true,
+            min_ms: 200,
+            max_ms: 3000,
+        };
+        let ms = Duration::from_millis;
+
+        assert_eq!(next_interval(ms(500), 0, &settings), ms(1000));
+        assert_eq!(next_interval(ms(2000), 0, &settings), ms(3000));
+        assert_eq!(next_interval(ms(3000), 80, &settings), ms(1500));
+        assert_eq!(next_interval(ms(300), 80, &settings), ms(200));
+        assert_eq!(next_interval(ms(3000), BURST_BYTES, &settings), ms(200));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::event::{LogEvent, KIND_NAMES};
+use crate::fnv::Fnv1a;
+use crate::format;
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_http::reqwest;
+
+pub const WEBHOOK_ERROR_EVENT: &str = "webhook-error";
+
+const WEBHOOKS_KEY: &str = "webhooks";
+
+/// Each webhook may send a burst of this many events, then one every
+/// `REFILL_INTERVAL`. Events over the limit are dropped.
+const BURST: u32 = 5;
+const REFILL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Attempts per event, doubling the wait after each transient failure.
+const MAX_ATTEMPTS: u32 = 3;
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Events in a row that failed every attempt before `webhook-error` is sent.
+const FAILURES_BEFORE_ERROR: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// Event kinds posted to `url`.
+    pub kinds: Vec<String>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// The JSON body posted for one event. `content` is what Discord shows;
+/// other endpoints can read `event` instead.
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    content: String,
+    event: &'a LogEvent,
+}
+
+fn payload(event: &LogEvent) -> Payload<'_> {
+    Payload {
+        content: format::summarize(event),
+        event,
+    }
+}
+
+/// Sent once a webhook has failed `FAILURES_BEFORE_ERROR` events in a row,
+/// and for every failure after that until one gets through.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookError {
+    pub id: String,
+    pub url: String,
+    pub failures: u32,
+    pub message: String,
+}
+
+/// Token bucket limiting how fast one webhook posts.
+#[derive(Debug)]
+struct RateLimit {
+    tokens: u32,
+    refilled_at: Instant,
+}
+
+impl RateLimit {
+    fn new(now: Instant) -> Self {
+        RateLimit {
+            tokens: BURST,
+            refilled_at: now,
+        }
+    }
+
+    /// Whether an event may be sent now, using up a token if so.
+    fn take(&mut self, now: Instant) -> bool {
+        let earned =
+            (now.duration_since(self.refilled_at).as_millis() / REFILL_INTERVAL.as_millis()) as u32;
+        if earned > 0 {
+            self.tokens = (self.tokens + earned).min(BURST);
+            self.refilled_at += REFILL_INTERVAL * earned;
+        }
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct WebhookState {
+    hooks: Mutex<Vec<Webhook>>,
+    limits: Mutex<HashMap<String, RateLimit>>,
+    /// Events in a row each webhook has failed to deliver.
+    failures: Mutex<HashMap<String, u32>>,
+}
+
+/// Load the registered webhooks. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let hooks: Vec<Webhook> = settings::get(app, WEBHOOKS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    *app.state::<WebhookState>().hooks.lock().unwrap() = hooks;
+}
+
+fn save(app: &AppHandle, hooks: &[Webhook]) -> Result<(), String> {
+    settings::set(app, WEBHOOKS_KEY, &hooks)
+}
+
+fn validate_url(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    let lower = url.to_ascii_lowercase();
+    let rest = lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"))
+        .ok_or_else(|| format!("{:?} is not an http:// or https:// URL", url))?;
+    if rest.is_empty() || rest.starts_with('/') {
+        return Err(format!("{:?} has no host", url));
+    }
+    Ok(url.to_string())
+}
+
+/// Whether a failed attempt is worth retrying: the server couldn't be
+/// reached, was overloaded, or asked us to slow down.
+fn is_transient(status: Option<reqwest::StatusCode>) -> bool {
+    status.is_none_or(|status| {
+        status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+    })
+}
+
+async fn post(client: &reqwest::Client, url: &str, body: &[u8]) -> Result<(), String> {
+    let mut delay = FIRST_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let result = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .body(body.to_vec())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        let error = match result {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+        if attempt == MAX_ATTEMPTS || !is_transient(error.status()) {
+            return Err(error.to_string());
+        }
+        tracing::debug!("Webhook post failed, retrying in {:?}: {}", delay, error);
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+fn record_result(app: &AppHandle, hook: &Webhook, result: Result<(), String>) {
+    let state = app.state::<WebhookState>();
+    let mut failures = state.failures.lock().unwrap();
+    let message = match result {
+        Ok(()) => {
+            failures.remove(&hook.id);
+            return;
+        }
+        Err(message) => message,
+    };
+    let count = failures.entry(hook.id.clone()).or_default();
+    *count += 1;
+    tracing::warn!("Failed to post to webhook {}: {}", hook.url, message);
+    if *count >= FAILURES_BEFORE_ERROR {
+        let error = WebhookError {
+            id: hook.id.clone(),
+            url: hook.url.clone(),
+            failures: *count,
+            message,
+        };
+        if let Err(e) = app.emit(WEBHOOK_ERROR_EVENT, error) {
+            tracing::warn!("Failed to emit {}: {}", WEBHOOK_ERROR_EVENT, e);
+        }
+    }
+}
+
+/// Post each event to every enabled webhook registered for its kind, within
+/// the webhook's rate limit. Delivery happens in the background.
+pub fn fire_batch(app: &AppHandle, events: &[LogEvent]) {
+    let state = app.state::<WebhookState>();
+    let hooks = state.hooks.lock().unwrap().clone();
+    if hooks.iter().all(|hook| !hook.enabled) {
+        return;
+    }
+    let now = Instant::now();
+    let mut limits = state.limits.lock().unwrap();
+    let mut deliveries = Vec::new();
+    for hook in hooks.iter().filter(|hook| hook.enabled) {
+        let limit = limits
+            .entry(hook.id.clone())
+            .or_insert_with(|| RateLimit::new(now));
+        for event in events
+            .iter()
+            .filter(|event| hook.kinds.iter().any(|kind| kind == event.kind_name()))
+        {
+            if !limit.take(now) {
+                tracing::debug!("Webhook {} is rate limited, dropping an event", hook.url);
+                continue;
+            }
+            match serde_json::to_vec(&payload(event)) {
+                Ok(body) => deliveries.push((hook.clone(), body)),
+                Err(e) => tracing::warn!("Failed to encode webhook payload: {}", e),
+            }
+        }
+    }
+    drop(limits);
+
+    for (hook, body) in deliveries {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let result = post(&reqwest::Client::new(), &hook.url, &body).await;
+            record_result(&app, &hook, result);
+        });
+    }
+}
+
+/// Post events of `kinds` to `url` as they're parsed, e.g. to a Discord
+/// webhook. Returns the registered webhook.
+#[tauri::command]
+pub fn register_webhook(
+    app: AppHandle,
+    state: State<'_, WebhookState>,
+    kinds: Vec<String>,
+    url: String,
+) -> Result<Webhook, String> {
+    let url = validate_url(&url)?;
+    if kinds.is_empty() {
+        return Err("Choose at least one event kind".to_string());
+    }
+    if let Some(unknown) = kinds
+        .iter()
+        .find(|kind| !KIND_NAMES.contains(&kind.as_str()))
+    {
+        return Err(format!("Unknown event kind: {}", unknown));
+    }
+
+    let mut hasher = Fnv1a::new();
+    hasher.write(url.as_bytes());
+    hasher.write(&chrono::Utc::now().timestamp_millis().to_le_bytes());
+    let hook = Webhook {
+        id: hasher.finish_hex(),
+        url,
+        kinds,
+        enabled: true,
+    };
+    let mut hooks = state.hooks.lock().unwrap();
+    let mut updated = hooks.clone();
+    updated.push(hook.clone());
+    save(&app, &updated)?;
+    *hooks = updated;
+    Ok(hook)
+}
+
+#[tauri::command]
+pub fn list_webhooks(state: State<'_, WebhookState>) -> Vec<Webhook> {
+    state.hooks.lock().unwrap().clone()
+}
+
+/// Pause or resume a webhook without forgetting it. Returns whether it
+/// existed.
+#[tauri::command]
+pub fn set_webhook_enabled(
+    app: AppHandle,
+    state: State<'_, WebhookState>,
+    id: String,
+    enabled: bool,
+) -> Result<bool, String> {
+    let mut hooks = state.hooks.lock().unwrap();
+    let mut updated = hooks.clone();
+    let Some(hook) = updated.iter_mut().find(|hook| hook.id == id) else {
+        return Ok(false);
+    };
+    hook.enabled = enabled;
+    save(&app, &updated)?;
+    *hooks = updated;
+    state.failures.lock().unwrap().remove(&id);
+    Ok(true)
+}
+
+/// Returns whether a webhook with that id existed.
+#[tauri::command]
+pub fn remove_webhook(
+    app: AppHandle,
+    state: State<'_, WebhookState>,
+    id: String,
+) -> Result<bool, String> {
+    let mut hooks = state.hooks.lock().unwrap();
+    let mut updated = hooks.clone();
+    updated.retain(|hook| hook.id != id);
+    if updated.len() == hooks.len() {
+        return Ok(false);
+    }
+    save(&app, &updated)?;
+    *hooks = updated;
+    state.limits.lock().unwrap().remove(&id);
+    state.failures.lock().unwrap().remove(&id);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventKind;
+
+    #[test]
+    fn payload_has_discord_content_and_the_event() {
+        let event = LogEvent::test(EventKind::SystemQuit).at(1_717_763_696_789);
+        assert_eq!(
+            serde_json::to_value(payload(&event)).unwrap(),
+            serde_json::json!({
+                "content": "Quit the game",
+                "event": {"timestamp_ms": 1_717_763_696_789_i64, "kind": "system_quit"},
+            })
+        );
+    }
+
+    #[test]
+    fn rate_limit_allows_a_burst_then_refills_slowly() {
+        let start = Instant::now();
+        let mut limit = RateLimit::new(start);
+        assert_eq!(
+            (0..BURST + 1).filter(|_| limit.take(start)).count(),
+            BURST as usize
+        );
+
+        assert!(!limit.take(start + REFILL_INTERVAL / 2));
+        assert!(limit.take(start + REFILL_INTERVAL));
+        assert!(!limit.take(start + REFILL_INTERVAL));
+        // A long quiet spell refills only up to the burst
+        let later = start + REFILL_INTERVAL * 100;
+        assert_eq!(
+            (0..BURST + 1).filter(|_| limit.take(later)).count(),
+            BURST as usize
+        );
+    }
+
+    #[test]
+    fn only_http_urls_with_a_host_are_accepted() {
+        assert_eq!(
+            validate_url(" https://discord.com/api/webhooks/1/abc ").unwrap(),
+            "https://discord.com/api/webhooks/1/abc"
+        );
+        assert!(validate_url("HTTP://localhost:8080/hook").is_ok());
+        assert!(validate_url("ftp://example.com").is_err());
+        assert!(validate_url("https://").is_err());
+        assert!(validate_url("discord.com/api/webhooks").is_err());
+    }
+}
@@ -0,0 +1,319 @@
+use crate::event::LogEvent;
+use crate::pipeline::LOG_BATCH_EVENT;
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::menu::{CheckMenuItem, Menu};
+use tauri::tray::TrayIconBuilder;
+use tauri::{
+    AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, State, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder, Window, Wry,
+};
+
+pub const FEED_WINDOW_LABEL: &str = "feed";
+pub const OVERLAY_OPACITY_EVENT: &str = "overlay-opacity";
+
+const OVERLAY_MODE_KEY: &str = "overlayMode";
+const OVERLAY_BOUNDS_KEY: &str = "overlayBounds";
+const OVERLAY_OPACITY_KEY: &str = "overlayOpacity";
+const OVERLAY_CLICK_THROUGH_KEY: &str = "overlayClickThrough";
+const MIN_OPACITY: f64 = 0.2;
+
+const CLICK_THROUGH_MENU_ID: &str = "overlay-click-through";
+
+/// Per-window kind filters for `log-batch` and `room-event`, keyed by window
+/// label. Windows without an entry get every event.
+#[derive(Default)]
+pub struct WindowFilters(Mutex<HashMap<String, Vec<String>>>);
+
+/// Send `events` to one window as `log-batch`, after its own filter. Nothing is
+/// sent if the filter leaves the batch empty.
+pub fn emit_batch_to(app: &AppHandle, label: &str, events: &[LogEvent]) -> tauri::Result<()> {
+    let filters = app.state::<WindowFilters>();
+    let kinds = filters.0.lock().unwrap().get(label).cloned();
+    if kinds.is_none() {
+        return app.emit_to(label, LOG_BATCH_EVENT, events);
+    }
+
+    let filtered: Vec<&LogEvent> = events
+        .iter()
+        .filter(|event| event.matches_kinds(kinds.as_deref()))
+        .collect();
+    if filtered.is_empty() {
+        return Ok(());
+    }
+    app.emit_to(label, LOG_BATCH_EVENT, filtered)
+}
+
+/// Send `events` to every open window as `log-batch`, each after its own filter.
+pub fn emit_batch(app: &AppHandle, events: &[LogEvent]) {
+    for label in app.webview_windows().into_keys() {
+        if let Err(e) = emit_batch_to(app, &label, events) {
+            tracing::warn!("Failed to emit {} to {}: {}", LOG_BATCH_EVENT, label, e);
+        }
+    }
+}
+
+/// Send `payload` as `name` to every open window whose filter lets `event`'s
+/// kind through. Used for messages carrying a single event, like `room-event`.
+pub fn emit_for_event<S: Serialize + Clone>(
+    app: &AppHandle,
+    name: &str,
+    event: &LogEvent,
+    payload: S,
+) {
+    let filters = app.state::<WindowFilters>();
+    for label in app.webview_windows().into_keys() {
+        let kinds = filters.0.lock().unwrap().get(&label).cloned();
+        if !event.matches_kinds(kinds.as_deref()) {
+            continue;
+        }
+        if let Err(e) = app.emit_to(&label, name, payload.clone()) {
+            tracing::warn!("Failed to emit {} to {}: {}", name, label, e);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Bounds {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverlaySettings {
+    pub enabled: bool,
+    pub opacity: f64,
+    /// Whether clicks pass through the overlay to the game. Off by default.
+    pub click_through: bool,
+}
+
+/// The tray's click-through item, kept so it can be ticked when the setting
+/// changes from elsewhere.
+#[derive(Default)]
+pub struct OverlayTray(Mutex<Option<CheckMenuItem<Wry>>>);
+
+fn overlay_settings(app: &AppHandle) -> OverlaySettings {
+    OverlaySettings {
+        enabled: settings::get(app, OVERLAY_MODE_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or(false),
+        opacity: settings::get(app, OVERLAY_OPACITY_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or(1.0),
+        click_through: settings::get(app, OVERLAY_CLICK_THROUGH_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or(false),
+    }
+}
+
+/// Add the tray icon, whose menu can turn click-through off while the
+/// overlay itself can't be clicked. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    if let Err(e) = build_tray(app) {
+        tracing::warn!("Failed to create the tray icon: {}", e);
+    }
+}
+
+fn build_tray(app: &AppHandle) -> tauri::Result<()> {
+    let click_through = CheckMenuItem::with_id(
+        app,
+        CLICK_THROUGH_MENU_ID,
+        "Click-through overlay",
+        true,
+        overlay_settings(app).click_through,
+        None::<&str>,
+    )?;
+    let menu = Menu::with_items(app, &[&click_through])?;
+    let mut tray = TrayIconBuilder::new()
+        .tooltip("Picologs")
+        .menu(&menu)
+        .on_menu_event(|app, event| {
+            if event.id() == CLICK_THROUGH_MENU_ID {
+                let enabled = !overlay_settings(app).click_through;
+                if let Err(e) = set_overlay_click_through(app.clone(), enabled) {
+                    tracing::warn!("Failed to toggle click-through: {}", e);
+                }
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+    *app.state::<OverlayTray>().0.lock().unwrap() = Some(click_through);
+    Ok(())
+}
+
+fn set_click_through(window: &WebviewWindow, enabled: bool) {
+    if let Err(e) = window.set_ignore_cursor_events(enabled) {
+        tracing::debug!("Click-through isn't available here: {}", e);
+    }
+}
+
+/// Tell the feed window what opacity to draw at: the overlay's while overlay
+/// mode is on, fully opaque otherwise.
+fn emit_opacity(app: &AppHandle) -> tauri::Result<()> {
+    let settings = overlay_settings(app);
+    let opacity = if settings.enabled {
+        settings.opacity
+    } else {
+        1.0
+    };
+    app.emit_to(FEED_WINDOW_LABEL, OVERLAY_OPACITY_EVENT, opacity)
+}
+
+fn save_bounds(app: &AppHandle, window: &WebviewWindow) {
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.outer_size()) else {
+        return;
+    };
+    let bounds = Bounds {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    };
+    if let Err(e) = settings::set(app, OVERLAY_BOUNDS_KEY, &bounds) {
+        tracing::warn!("Failed to persist {}: {}", OVERLAY_BOUNDS_KEY, e);
+    }
+}
+
+/// Float the window over the game (always on top, no decorations, and clicks
+/// passing through to the game if click-through is on and the platform allows
+/// it), or turn it back into a normal window.
+fn apply_overlay(app: &AppHandle, window: &WebviewWindow, enabled: bool) -> tauri::Result<()> {
+    if enabled {
+        if let Ok(Some(bounds)) = settings::get::<Bounds>(app, OVERLAY_BOUNDS_KEY) {
+            window.set_position(PhysicalPosition::new(bounds.x, bounds.y))?;
+            window.set_size(PhysicalSize::new(bounds.width, bounds.height))?;
+        }
+    } else {
+        save_bounds(app, window);
+    }
+
+    window.set_always_on_top(enabled)?;
+    window.set_decorations(!enabled)?;
+    window.set_skip_taskbar(enabled)?;
+    set_click_through(window, enabled && overlay_settings(app).click_through);
+    Ok(())
+}
+
+/// Called as a window is about to close, while it can still be queried.
+pub fn on_close_requested(window: &Window) {
+    if window.label() == FEED_WINDOW_LABEL {
+        save_overlay_bounds(window.app_handle());
+    }
+}
+
+/// Remember where the overlay is, if the feed is open as one.
+pub fn save_overlay_bounds(app: &AppHandle) {
+    if let (true, Some(feed)) = (
+        overlay_settings(app).enabled,
+        app.get_webview_window(FEED_WINDOW_LABEL),
+    ) {
+        save_bounds(app, &feed);
+    }
+}
+
+/// Drop a closed window's filter.
+pub fn forget(window: &Window) {
+    window
+        .state::<WindowFilters>()
+        .0
+        .lock()
+        .unwrap()
+        .remove(window.label());
+}
+
+/// Open the compact feed window, or focus it if it's already open.
+#[tauri::command]
+pub fn open_feed_window(app: AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(FEED_WINDOW_LABEL) {
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    let builder =
+        WebviewWindowBuilder::new(&app, FEED_WINDOW_LABEL, WebviewUrl::App("feed".into()))
+            .title("Picologs feed")
+            .inner_size(380.0, 600.0)
+            .min_inner_size(280.0, 200.0);
+    // So the page's opacity lets the game show through
+    #[cfg(not(target_os = "macos"))]
+    let builder = builder.transparent(true);
+    let window = builder.build().map_err(|e| e.to_string())?;
+
+    if overlay_settings(&app).enabled {
+        apply_overlay(&app, &window, true).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Turn overlay mode on or off for the feed window, opening it if needed. The
+/// choice is persisted and reapplied whenever the feed window opens.
+#[tauri::command]
+pub fn set_overlay_mode(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, OVERLAY_MODE_KEY, &enabled)?;
+    match app.get_webview_window(FEED_WINDOW_LABEL) {
+        Some(window) => {
+            apply_overlay(&app, &window, enabled).map_err(|e| e.to_string())?;
+            emit_opacity(&app).map_err(|e| e.to_string())
+        }
+        None if enabled => open_feed_window(app),
+        None => Ok(()),
+    }
+}
+
+/// Set the overlay's opacity, clamped to 0.2..1.0. Native windows can't be
+/// made translucent everywhere, so the feed window, which is transparent,
+/// draws its page at this opacity from `overlay-opacity`.
+#[tauri::command]
+pub fn set_overlay_opacity(app: AppHandle, opacity: f64) -> Result<f64, String> {
+    let opacity = opacity.clamp(MIN_OPACITY, 1.0);
+    settings::set(&app, OVERLAY_OPACITY_KEY, &opacity)?;
+    emit_opacity(&app).map_err(|e| e.to_string())?;
+    Ok(opacity)
+}
+
+/// Let clicks through the overlay to the game, or catch them again. Only
+/// takes effect in overlay mode. The tray's "Click-through overlay" item
+/// does the same, as the overlay can't be clicked while this is on.
+#[tauri::command]
+pub fn set_overlay_click_through(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, OVERLAY_CLICK_THROUGH_KEY, &enabled)?;
+    if let Some(item) = app.state::<OverlayTray>().0.lock().unwrap().as_ref() {
+        item.set_checked(enabled).map_err(|e| e.to_string())?;
+    }
+    if let (true, Some(window)) = (
+        overlay_settings(&app).enabled,
+        app.get_webview_window(FEED_WINDOW_LABEL),
+    ) {
+        set_click_through(&window, enabled);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_overlay_settings(app: AppHandle) -> OverlaySettings {
+    overlay_settings(&app)
+}
+
+/// Limit the `log-batch` and `room-event` events the calling window receives
+/// to some kinds.
+/// `None` clears the filter.
+#[tauri::command]
+pub fn set_window_filter(
+    window: Window,
+    filters: State<'_, WindowFilters>,
+    kinds: Option<Vec<String>>,
+) {
+    let mut filters = filters.0.lock().unwrap();
+    match kinds {
+        Some(kinds) => filters.insert(window.label().to_string(), kinds),
+        None => filters.remove(window.label()),
+    };
+}
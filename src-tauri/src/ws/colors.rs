@@ -0,0 +1,101 @@
+use crate::fnv::Fnv1a;
+use crate::settings;
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+/// Colors picked by hand, by lowercased handle.
+const MEMBER_COLORS_KEY: &str = "memberColors";
+
+/// Colors handed out to members nobody has picked one for.
+const PALETTE: [&str; 12] = [
+    "#e6194b", "#3cb44b", "#ffe119", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6",
+    "#bfef45", "#fabed4", "#469990", "#dcbeff",
+];
+
+fn key(handle: &str) -> String {
+    handle.trim().to_lowercase()
+}
+
+/// A color for `handle` that stays the same across sessions and clients.
+fn default_color(handle: &str) -> &'static str {
+    let mut hasher = Fnv1a::new();
+    hasher.write(key(handle).as_bytes());
+    let hash = u64::from_str_radix(&hasher.finish_hex(), 16).unwrap_or_default();
+    PALETTE[(hash % PALETTE.len() as u64) as usize]
+}
+
+/// Check a color is `#rrggbb` and lowercase it.
+fn validate_color(color: &str) -> Result<String, String> {
+    let color = color.trim();
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            Ok(color.to_lowercase())
+        }
+        _ => Err(format!("{:?} is not a #rrggbb color", color)),
+    }
+}
+
+fn colors(app: &AppHandle) -> BTreeMap<String, String> {
+    settings::get(app, MEMBER_COLORS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// The color picked for `handle`, or its default one.
+pub fn member_color(app: &AppHandle, handle: &str) -> String {
+    colors(app)
+        .remove(&key(handle))
+        .unwrap_or_else(|| default_color(handle).to_string())
+}
+
+/// Pick the color for a member's events. Pass no color to go back to the
+/// default one.
+#[tauri::command]
+pub fn set_member_color(
+    app: AppHandle,
+    handle: String,
+    color: Option<String>,
+) -> Result<String, String> {
+    let member = key(&handle);
+    if member.is_empty() {
+        return Err("Handle is empty".to_string());
+    }
+    let mut colors = colors(&app);
+    let color = match color {
+        Some(color) => {
+            let color = validate_color(&color)?;
+            colors.insert(member, color.clone());
+            color
+        }
+        None => {
+            colors.remove(&member);
+            default_color(&handle).to_string()
+        }
+    };
+    settings::set(&app, MEMBER_COLORS_KEY, &colors)?;
+    Ok(color)
+}
+
+/// Colors picked by hand, by lowercased handle. Anyone missing gets the
+/// color from `member_color`.
+#[tauri::command]
+pub fn get_member_colors(app: AppHandle) -> BTreeMap<String, String> {
+    colors(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_colors_depend_only_on_the_handle() {
+        assert_eq!(default_color("Pilot"), default_color(" pilot "));
+        assert!(PALETTE.contains(&default_color("Someone-Else")));
+
+        assert_eq!(validate_color(" #A1B2C3 ").unwrap(), "#a1b2c3");
+        assert!(validate_color("a1b2c3").is_err());
+        assert!(validate_color("#a1b2c").is_err());
+        assert!(validate_color("#ghijkl").is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use serde::Serialize;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use tokio_tungstenite::tungstenite::http::Uri;
+use tokio_tungstenite::tungstenite::Error as WsError;
+
+/// How long each stage may take before it's reported as failed.
+const STAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Dns,
+    Tcp,
+    Tls,
+    Handshake,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageReport {
+    pub stage: Stage,
+    pub ok: bool,
+    pub elapsed_ms: u64,
+    /// What the stage found (resolved addresses, HTTP status, ...) or why it
+    /// failed.
+    pub detail: String,
+}
+
+/// The outcome of each stage up to and including the first one that failed.
+/// Stages after a failure aren't attempted, so `stages` can be shorter than
+/// four entries; `ws://` URLs never have a TLS stage.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionDiagnosis {
+    pub url: String,
+    pub ok: bool,
+    pub stages: Vec<StageReport>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Target {
+    host: String,
+    port: u16,
+    tls: bool,
+}
+
+pub(super) fn parse_target(url: &str) -> Result<Target, String> {
+    let uri: Uri = url.parse().map_err(|e| format!("Invalid URL: {}", e))?;
+    let tls = match uri.scheme_str() {
+        Some("wss") => true,
+        Some("ws") => false,
+        _ => return Err("URL must start with ws:// or wss://".to_string()),
+    };
+    let host = uri
+        .host()
+        .ok_or_else(|| "URL has no host".to_string())?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = uri.port_u16().unwrap_or(if tls { 443 } else { 80 });
+    Ok(Target { host, port, tls })
+}
+
+/// Run one stage under `STAGE_TIMEOUT`, recording how it went. `describe`
+/// turns a success into the stage's detail line.
+async fn stage<T>(
+    stages: &mut Vec<StageReport>,
+    stage: Stage,
+    work: impl Future<Output = Result<T, String>>,
+    describe: impl FnOnce(&T) -> String,
+) -> Option<T> {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(STAGE_TIMEOUT, work).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("Timed out after {}s", STAGE_TIMEOUT.as_secs())),
+    };
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+
+    let (ok, detail, value) = match result {
+        Ok(value) => (true, describe(&value), Some(value)),
+        Err(e) => (false, e, None),
+    };
+    stages.push(StageReport {
+        stage,
+        ok,
+        elapsed_ms,
+        detail,
+    });
+    value
+}
+
+fn tls_connector() -> Result<TlsConnector, String> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+async fn handshake<S>(url: &str, stream: S) -> Result<String, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match tokio_tungstenite::client_async(url, stream).await {
+        Ok((mut socket, response)) => {
+            let _ = socket.close(None).await;
+            Ok(format!("Server answered {}", response.status()))
+        }
+        Err(WsError::Http(response)) => Err(format!(
+            "Server refused the upgrade with {}",
+            response.status()
+        )),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Walk through everything a room connection needs, one stage at a time, on a
+/// throwaway connection that never touches the joined room.
+pub async fn diagnose(url: &str) -> ConnectionDiagnosis {
+    let mut stages = Vec::new();
+    run_stages(url, &mut stages).await;
+    ConnectionDiagnosis {
+        url: url.to_string(),
+        ok: stages.last().is_some_and(|stage| stage.ok),
+        stages,
+    }
+}
+
+async fn run_stages(url: &str, stages: &mut Vec<StageReport>) -> Option<()> {
+    let target = match parse_target(url) {
+        Ok(target) => target,
+        Err(e) => {
+            stages.push(StageReport {
+                stage: Stage::Dns,
+                ok: false,
+                elapsed_ms: 0,
+                detail: e,
+            });
+            return None;
+        }
+    };
+
+    let addrs: Vec<SocketAddr> = stage(
+        stages,
+        Stage::Dns,
+        async {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((target.host.as_str(), target.port))
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no addresses", target.host));
+            }
+            Ok(addrs)
+        },
+        |addrs| {
+            let ips: Vec<String> = addrs.iter().map(|addr| addr.ip().to_string()).collect();
+            format!("Resolved to {}", ips.join(", "))
+        },
+    )
+    .await?;
+
+    let tcp = stage(
+        stages,
+        Stage::Tcp,
+        async {
+            TcpStream::connect(addrs.as_slice())
+                .await
+                .map_err(|e| e.to_string())
+        },
+        |tcp| match tcp.peer_addr() {
+            Ok(addr) => format!("Connected to {}", addr),
+            Err(_) => "Connected".to_string(),
+        },
+    )
+    .await?;
+
+    if !target.tls {
+        stage(stages, Stage::Handshake, handshake(url, tcp), String::clone).await?;
+        return Some(());
+    }
+
+    let tls = stage(
+        stages,
+        Stage::Tls,
+        async {
+            let name = ServerName::try_from(target.host.clone()).map_err(|e| e.to_string())?;
+            tls_connector()?
+                .connect(name, tcp)
+                .await
+                .map_err(|e| e.to_string())
+        },
+        |tls| {
+            let (_, session) = tls.get_ref();
+            match session.protocol_version() {
+                Some(version) => format!("Negotiated {:?}", version),
+                None => "Negotiated".to_string(),
+            }
+        },
+    )
+    .await?;
+
+    stage(stages, Stage::Handshake, handshake(url, tls), String::clone).await?;
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn parses_schemes_and_default_ports() {
+        assert_eq!(
+            parse_target("wss://picologs-server.fly.dev/ws"),
+            Ok(Target {
+                host: "picologs-server.fly.dev".into(),
+                port: 443,
+                tls: true,
+            })
+        );
+        assert_eq!(
+            parse_target("ws://[::1]:8080/ws"),
+            Ok(Target {
+                host: "::1".into(),
+                port: 8080,
+                tls: false,
+            })
+        );
+        assert!(parse_target("https://example.com").is_err());
+    }
+
+    #[test]
+    fn stops_at_the_handshake_when_the_upgrade_is_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            stream
+                .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        });
+
+        let url = format!("ws://127.0.0.1:{}/ws", port);
+        let diagnosis = tauri::async_runtime::block_on(diagnose(&url));
+        server.join().unwrap();
+
+        let stages: Vec<(Stage, bool)> = diagnosis.stages.iter().map(|s| (s.stage, s.ok)).collect();
+        assert_eq!(
+            stages,
+            vec![
+                (Stage::Dns, true),
+                (Stage::Tcp, true),
+                (Stage::Handshake, false),
+            ]
+        );
+        assert!(!diagnosis.ok);
+        assert!(diagnosis.stages[2].detail.contains("404"));
+    }
+}
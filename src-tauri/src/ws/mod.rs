@@ -0,0 +1,592 @@
+pub mod colors;
+mod diagnose;
+pub mod presets;
+pub mod protocol;
+
+use crate::buffer::EventBuffer;
+use crate::event::{LogEvent, KIND_NAMES};
+use crate::settings;
+use crate::windows;
+use futures_util::stream::SplitStream;
+use futures_util::{SinkExt, StreamExt};
+use protocol::{ClientMessage, PresenceStatus, RoomMode, ServerMessage};
+use serde::Serialize;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// Store key for the relay server rooms connect to. There is no default: the
+/// picologs server doesn't speak the room protocol.
+const SERVER_URL_KEY: &str = "serverUrl";
+/// Event kinds to receive from the room; empty means all.
+const ROOM_SUBSCRIPTION_KEY: &str = "roomSubscription";
+
+/// How long a room probe waits for the server before giving up.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const ROOM_STATE_EVENT: &str = "room-state";
+pub const ROOM_EVENT: &str = "room-event";
+pub const ROOM_PRESENCE_EVENT: &str = "room-presence";
+pub const ROOM_ERROR_EVENT: &str = "room-error";
+pub const ROOM_CHAT_EVENT: &str = "room-chat";
+pub const ROOM_SUBSCRIPTION_EVENT: &str = "room-subscription";
+
+/// Longest chat message that can be sent, in characters.
+const MAX_CHAT_CHARS: usize = 280;
+
+/// Where the frontend keeps the session JWT after signing in.
+const AUTH_STORE_FILE: &str = "auth.json";
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConnectionState {
+    Connecting,
+    Joined,
+    JoinedSpectator,
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomStatus {
+    pub state: ConnectionState,
+    pub code: Option<String>,
+    pub members: Option<u32>,
+}
+
+impl RoomStatus {
+    fn disconnected() -> Self {
+        RoomStatus {
+            state: ConnectionState::Disconnected,
+            code: None,
+            members: None,
+        }
+    }
+}
+
+/// An event another member shared to the room. `color` is the one picked
+/// for `from`, so each member's events look the same everywhere.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomEvent {
+    pub from: Option<String>,
+    pub color: Option<String>,
+    pub event: LogEvent,
+}
+
+/// A chat message from another member, with their color like `RoomEvent`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomChat {
+    pub from: Option<String>,
+    pub color: Option<String>,
+    pub text: String,
+}
+
+/// The event kinds received from the room. Empty means every kind.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomSubscription {
+    pub kinds: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomPresence {
+    pub handle: String,
+    pub status: PresenceStatus,
+}
+
+struct RoomConnection {
+    code: String,
+    mode: RoomMode,
+    outbound: mpsc::UnboundedSender<ClientMessage>,
+    status: Mutex<RoomStatus>,
+    /// Kinds asked of the server, also applied here in case it forwards more.
+    subscription: Mutex<Vec<String>>,
+}
+
+impl RoomConnection {
+    /// Queue a message for the server. Anything the connection's mode doesn't
+    /// allow is dropped here, so no caller can leak events from a spectator.
+    fn send(&self, message: ClientMessage) {
+        if self.mode.allows(&message) {
+            let _ = self.outbound.send(message);
+        }
+    }
+}
+
+/// The room connection, if any. Only one room is joined at a time.
+#[derive(Default)]
+pub struct RoomState {
+    current: Mutex<Option<Arc<RoomConnection>>>,
+}
+
+fn auth_token(app: &AppHandle) -> Option<String> {
+    let store = app.store(AUTH_STORE_FILE).ok()?;
+    store.get("jwtToken")?.as_str().map(String::from)
+}
+
+fn configured_server_url(app: &AppHandle) -> Option<String> {
+    settings::get::<String>(app, SERVER_URL_KEY).ok().flatten()
+}
+
+/// The relay server to connect to.
+fn server_url(app: &AppHandle) -> Result<String, String> {
+    configured_server_url(app)
+        .ok_or_else(|| "No room server is configured; set one with set_server_url".to_string())
+}
+
+fn subscription(app: &AppHandle) -> Vec<String> {
+    settings::get(app, ROOM_SUBSCRIPTION_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Whether a room event is one of the subscribed kinds.
+fn subscribed(kinds: &[String], event: &LogEvent) -> bool {
+    kinds.is_empty() || event.matches_kinds(Some(kinds))
+}
+
+fn emit_subscription(app: &AppHandle, kinds: Vec<String>) {
+    if let Err(e) = app.emit(ROOM_SUBSCRIPTION_EVENT, RoomSubscription { kinds }) {
+        tracing::warn!("Failed to emit {}: {}", ROOM_SUBSCRIPTION_EVENT, e);
+    }
+}
+
+fn emit_status(app: &AppHandle, status: &RoomStatus) {
+    if let Err(e) = app.emit(ROOM_STATE_EVENT, status) {
+        tracing::warn!("Failed to emit {}: {}", ROOM_STATE_EVENT, e);
+    }
+}
+
+fn is_current(app: &AppHandle, connection: &Arc<RoomConnection>) -> bool {
+    app.state::<RoomState>()
+        .current
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|current| Arc::ptr_eq(current, connection))
+}
+
+/// Leave the current room, if any. Returns whether one was joined.
+fn leave(app: &AppHandle) -> bool {
+    let Some(previous) = app.state::<RoomState>().current.lock().unwrap().take() else {
+        return false;
+    };
+    previous.send(ClientMessage::Presence {
+        status: PresenceStatus::Offline,
+    });
+    previous.send(ClientMessage::LeaveRoom);
+    // Dropping the last handle to the outbound queue lets the writer flush
+    // these and close the socket
+    drop(previous);
+
+    emit_status(app, &RoomStatus::disconnected());
+    true
+}
+
+async fn join(app: AppHandle, code: String, mode: RoomMode) -> Result<RoomStatus, String> {
+    let code = code.trim().to_string();
+    if code.is_empty() {
+        return Err("Room code is empty".to_string());
+    }
+
+    let url = server_url(&app)?;
+    leave(&app);
+    emit_status(
+        &app,
+        &RoomStatus {
+            state: ConnectionState::Connecting,
+            code: Some(code.clone()),
+            members: None,
+        },
+    );
+
+    let (socket, _) = match tokio_tungstenite::connect_async(url).await {
+        Ok(connected) => connected,
+        Err(e) => {
+            emit_status(&app, &RoomStatus::disconnected());
+            return Err(e.to_string());
+        }
+    };
+    let (mut sink, stream) = socket.split();
+    let (outbound, mut queue) = mpsc::unbounded_channel::<ClientMessage>();
+    let kinds = subscription(&app);
+
+    let connection = Arc::new(RoomConnection {
+        code: code.clone(),
+        mode,
+        outbound,
+        status: Mutex::new(RoomStatus {
+            state: ConnectionState::Connecting,
+            code: Some(code.clone()),
+            members: None,
+        }),
+        subscription: Mutex::new(kinds.clone()),
+    });
+    connection.send(ClientMessage::JoinRoom {
+        code,
+        token: auth_token(&app),
+        spectator: mode == RoomMode::Spectator,
+        probe: false,
+        subscribe_filter: kinds.clone(),
+    });
+    connection.send(ClientMessage::Presence {
+        status: PresenceStatus::Online,
+    });
+    *app.state::<RoomState>().current.lock().unwrap() = Some(connection.clone());
+    emit_subscription(&app, kinds);
+
+    tauri::async_runtime::spawn(async move {
+        while let Some(message) = queue.recv().await {
+            let Ok(text) = serde_json::to_string(&message) else {
+                continue;
+            };
+            if sink.send(Message::text(text)).await.is_err() {
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    let status = connection.status.lock().unwrap().clone();
+    tauri::async_runtime::spawn(read_loop(app, Arc::downgrade(&connection), stream));
+    Ok(status)
+}
+
+async fn read_loop(
+    app: AppHandle,
+    connection: Weak<RoomConnection>,
+    mut stream: SplitStream<Socket>,
+) {
+    while let Some(Ok(frame)) = stream.next().await {
+        let Some(connection) = connection.upgrade() else {
+            return;
+        };
+        let Message::Text(text) = frame else {
+            continue;
+        };
+        match serde_json::from_str::<ServerMessage>(text.as_str()) {
+            Ok(message) => handle_message(&app, &connection, message),
+            Err(e) => tracing::warn!("Ignoring unrecognised room message: {}", e),
+        }
+    }
+
+    // The server went away while this was still the active room
+    if let Some(connection) = connection.upgrade() {
+        if is_current(&app, &connection) {
+            tracing::warn!("Lost connection to room {}", connection.code);
+            app.state::<RoomState>().current.lock().unwrap().take();
+            emit_status(&app, &RoomStatus::disconnected());
+        }
+    }
+}
+
+fn handle_message(app: &AppHandle, connection: &RoomConnection, message: ServerMessage) {
+    let emitted = match message {
+        ServerMessage::Joined { code, members } => {
+            tracing::info!("Joined room {} with {} members", code, members);
+            let status = RoomStatus {
+                state: match connection.mode {
+                    RoomMode::Member => ConnectionState::Joined,
+                    RoomMode::Spectator => ConnectionState::JoinedSpectator,
+                },
+                code: Some(code),
+                members: Some(members),
+            };
+            *connection.status.lock().unwrap() = status.clone();
+            app.emit(ROOM_STATE_EVENT, status)
+        }
+        ServerMessage::RoomEvent { from, mut event } => {
+            if !subscribed(&connection.subscription.lock().unwrap(), &event) {
+                return;
+            }
+            event.reporter = from.clone();
+            let color = from
+                .as_deref()
+                .map(|handle| colors::member_color(app, handle));
+            // Buffered so a window opened later, like the feed, can backfill
+            app.state::<EventBuffer>()
+                .push_batch(std::slice::from_ref(&*event));
+            let room_event = RoomEvent {
+                from,
+                color,
+                event: *event,
+            };
+            // Each window gets it only if its own filter allows the kind
+            windows::emit_for_event(app, ROOM_EVENT, &room_event.event, &room_event);
+            Ok(())
+        }
+        ServerMessage::Presence { handle, status } => {
+            app.emit(ROOM_PRESENCE_EVENT, RoomPresence { handle, status })
+        }
+        ServerMessage::Chat { from, text } => {
+            let color = from
+                .as_deref()
+                .map(|handle| colors::member_color(app, handle));
+            app.emit(ROOM_CHAT_EVENT, RoomChat { from, color, text })
+        }
+        ServerMessage::Error { message } => {
+            tracing::warn!("Room {} reported an error: {}", connection.code, message);
+            app.emit(ROOM_ERROR_EVENT, message)
+        }
+    };
+    if let Err(e) = emitted {
+        tracing::warn!("Failed to emit room message: {}", e);
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RoomProbe {
+    pub exists: bool,
+    pub members: Option<u32>,
+    pub error: Option<String>,
+}
+
+async fn probe(app: &AppHandle, code: String) -> Result<RoomProbe, String> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(server_url(app)?)
+        .await
+        .map_err(|e| e.to_string())?;
+    let join = ClientMessage::JoinRoom {
+        code,
+        token: auth_token(app),
+        spectator: false,
+        probe: true,
+        subscribe_filter: Vec::new(),
+    };
+    let text = serde_json::to_string(&join).map_err(|e| e.to_string())?;
+    socket
+        .send(Message::text(text))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut result = Err("Connection closed before the server answered".to_string());
+    while let Some(frame) = socket.next().await {
+        let Message::Text(text) = frame.map_err(|e| e.to_string())? else {
+            continue;
+        };
+        match serde_json::from_str::<ServerMessage>(text.as_str()) {
+            Ok(ServerMessage::Joined { members, .. }) => {
+                result = Ok(RoomProbe {
+                    exists: true,
+                    members: Some(members),
+                    error: None,
+                });
+                break;
+            }
+            Ok(ServerMessage::Error { message }) => {
+                result = Ok(RoomProbe {
+                    error: Some(message),
+                    ..RoomProbe::default()
+                });
+                break;
+            }
+            // Anything else is feed traffic the probe didn't ask for
+            _ => continue,
+        }
+    }
+
+    let _ = socket.close(None).await;
+    result
+}
+
+/// Check whether a room code points at a live room, and how many members it
+/// has, over a short-lived connection separate from the joined room.
+#[tauri::command]
+pub async fn probe_room(app: AppHandle, code: String) -> Result<RoomProbe, String> {
+    let code = code.trim().to_string();
+    if code.is_empty() {
+        return Err("Room code is empty".to_string());
+    }
+    tokio::time::timeout(PROBE_TIMEOUT, probe(&app, code))
+        .await
+        .map_err(|_| "Timed out waiting for the server".to_string())?
+}
+
+/// Check DNS, TCP, TLS and the websocket upgrade in turn against `url` (the
+/// configured room server by default), reporting each stage's result and timing, so a
+/// failed join can be narrowed down to the stage that broke.
+#[tauri::command]
+pub async fn diagnose_connection(
+    app: AppHandle,
+    url: Option<String>,
+) -> Result<diagnose::ConnectionDiagnosis, String> {
+    let url = match url {
+        Some(url) => url,
+        None => server_url(&app)?,
+    };
+    Ok(diagnose::diagnose(url.trim()).await)
+}
+
+/// Forward freshly parsed local events to the joined room. Spectators never
+/// forward anything.
+pub fn share_batch(app: &AppHandle, events: &[LogEvent]) {
+    let state = app.state::<RoomState>();
+    let Some(connection) = state.current.lock().unwrap().clone() else {
+        return;
+    };
+    for event in events {
+        connection.send(ClientMessage::ShareEvent {
+            event: Box::new(event.clone()),
+        });
+    }
+}
+
+/// Trim a chat message and check it's fit to send.
+fn validate_chat(text: &str) -> Result<String, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Chat message is empty".to_string());
+    }
+    let chars = text.chars().count();
+    if chars > MAX_CHAT_CHARS {
+        return Err(format!(
+            "Chat message is {} characters, the limit is {}",
+            chars, MAX_CHAT_CHARS
+        ));
+    }
+    Ok(text.to_string())
+}
+
+/// Send a short note to the joined room. Spectators can read chat but not
+/// send it.
+#[tauri::command]
+pub fn send_chat(state: State<'_, RoomState>, text: String) -> Result<(), String> {
+    let text = validate_chat(&text)?;
+    let Some(connection) = state.current.lock().unwrap().clone() else {
+        return Err("Not in a room".to_string());
+    };
+    if connection.mode == RoomMode::Spectator {
+        return Err("Spectators can't send chat messages".to_string());
+    }
+    connection.send(ClientMessage::Chat { text });
+    Ok(())
+}
+
+/// Only receive these event kinds from rooms (all of them if empty). The
+/// server is told straight away if a room is joined, so it stops forwarding
+/// the rest; the choice is kept for later joins.
+#[tauri::command]
+pub fn set_room_subscription(
+    app: AppHandle,
+    state: State<'_, RoomState>,
+    kinds: Vec<String>,
+) -> Result<(), String> {
+    if let Some(unknown) = kinds
+        .iter()
+        .find(|kind| !KIND_NAMES.contains(&kind.as_str()))
+    {
+        return Err(format!("Unknown event kind: {}", unknown));
+    }
+    settings::set(&app, ROOM_SUBSCRIPTION_KEY, &kinds)?;
+    if let Some(connection) = state.current.lock().unwrap().clone() {
+        *connection.subscription.lock().unwrap() = kinds.clone();
+        connection.send(ClientMessage::Subscribe {
+            subscribe_filter: kinds.clone(),
+        });
+    }
+    emit_subscription(&app, kinds);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_room_subscription(app: AppHandle) -> Vec<String> {
+    subscription(&app)
+}
+
+/// Join a room as a full member, sharing local events with it.
+#[tauri::command]
+pub async fn join_room(app: AppHandle, code: String) -> Result<RoomStatus, String> {
+    join(app, code, RoomMode::Member).await
+}
+
+/// Join a room as a read-only observer: the feed is received, but nothing is
+/// shared and the client never announces itself online.
+#[tauri::command]
+pub async fn join_as_spectator(app: AppHandle, code: String) -> Result<RoomStatus, String> {
+    join(app, code, RoomMode::Spectator).await
+}
+
+#[tauri::command]
+pub fn leave_room(app: AppHandle) -> bool {
+    leave(&app)
+}
+
+#[tauri::command]
+pub fn get_room_status(state: State<'_, RoomState>) -> RoomStatus {
+    state
+        .current
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|connection| connection.status.lock().unwrap().clone())
+        .unwrap_or_else(RoomStatus::disconnected)
+}
+
+/// Rejoin the current room, if any, so a server change takes effect now
+/// rather than on the next join.
+async fn reconnect(app: AppHandle) -> Result<Option<RoomStatus>, String> {
+    let current = app.state::<RoomState>().current.lock().unwrap().clone();
+    match current {
+        Some(connection) => join(app, connection.code.clone(), connection.mode)
+            .await
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Point the app at a different relay server (`ws://` or `wss://`). The URL is
+/// persisted, and a joined room is rejoined on the new server. Returns the
+/// room status after reconnecting, if a room was joined.
+#[tauri::command]
+pub async fn set_server_url(app: AppHandle, url: String) -> Result<Option<RoomStatus>, String> {
+    let url = url.trim().to_string();
+    diagnose::parse_target(&url)?;
+    settings::set(&app, SERVER_URL_KEY, &url)?;
+    tracing::info!("Room server set to {}", url);
+    reconnect(app).await
+}
+
+/// The configured relay server, if any.
+#[tauri::command]
+pub fn get_server_url(app: AppHandle) -> Option<String> {
+    configured_server_url(&app)
+}
+
+/// Clear the configured relay server, leaving a joined room since there's no
+/// server left to stay on. There is no default server to fall back to; rooms
+/// can't be joined until one is set again.
+#[tauri::command]
+pub fn clear_server_url(app: AppHandle) -> Result<(), String> {
+    settings::remove(&app, SERVER_URL_KEY)?;
+    leave(&app);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_is_trimmed_and_length_limited() {
+        assert_eq!(
+            validate_chat("  forming up at Grim HEX \n").unwrap(),
+            "forming up at Grim HEX"
+        );
+        assert!(validate_chat(" \t ").is_err());
+        assert!(validate_chat(&"é".repeat(MAX_CHAT_CHARS)).is_ok());
+        assert!(validate_chat(&"a".repeat(MAX_CHAT_CHARS + 1)).is_err());
+    }
+
+    #[test]
+    fn room_events_outside_the_subscription_are_dropped() {
+        let quit = LogEvent::test(crate::event::EventKind::SystemQuit);
+        assert!(subscribed(&[], &quit));
+        assert!(subscribed(&["system_quit".to_string()], &quit));
+        assert!(!subscribed(&["actor_death".to_string()], &quit));
+    }
+}
@@ -0,0 +1,128 @@
+use super::{join, RoomMode, RoomStatus};
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+const PRESETS_KEY: &str = "roomPresets";
+const LAST_PRESET_KEY: &str = "lastRoomPreset";
+const REJOIN_ON_STARTUP_KEY: &str = "rejoinLastPresetOnStartup";
+
+const MAX_CODE_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomPreset {
+    pub name: String,
+    pub code: String,
+}
+
+fn presets(app: &AppHandle) -> BTreeMap<String, String> {
+    settings::get(app, PRESETS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Trim a room code and check it's something the server could have issued.
+fn validate_code(code: &str) -> Result<String, String> {
+    let code = code.trim();
+    if code.is_empty() {
+        return Err("Room code is empty".to_string());
+    }
+    if code.len() > MAX_CODE_LEN
+        || !code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("{:?} is not a valid room code", code));
+    }
+    Ok(code.to_string())
+}
+
+/// Rejoin the last joined preset, if that's turned on. Called once from
+/// `setup`; the join itself happens in the background.
+pub fn restore(app: &AppHandle) {
+    let enabled = settings::get::<bool>(app, REJOIN_ON_STARTUP_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    if !enabled {
+        return;
+    }
+    let Some(name) = settings::get::<String>(app, LAST_PRESET_KEY).ok().flatten() else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = join_preset(app, name.clone()).await {
+            tracing::warn!("Failed to rejoin room preset {}: {}", name, e);
+        }
+    });
+}
+
+/// Save a room code under a name, replacing any preset with that name.
+#[tauri::command]
+pub fn save_room_preset(app: AppHandle, name: String, code: String) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Preset name is empty".to_string());
+    }
+    let mut presets = presets(&app);
+    presets.insert(name, validate_code(&code)?);
+    settings::set(&app, PRESETS_KEY, &presets)
+}
+
+/// Every saved preset, sorted by name.
+#[tauri::command]
+pub fn list_room_presets(app: AppHandle) -> Vec<RoomPreset> {
+    presets(&app)
+        .into_iter()
+        .map(|(name, code)| RoomPreset { name, code })
+        .collect()
+}
+
+/// Returns whether a preset with that name existed.
+#[tauri::command]
+pub fn delete_room_preset(app: AppHandle, name: String) -> Result<bool, String> {
+    let mut presets = presets(&app);
+    if presets.remove(&name).is_none() {
+        return Ok(false);
+    }
+    settings::set(&app, PRESETS_KEY, &presets)?;
+    if settings::get::<String>(&app, LAST_PRESET_KEY)?.as_ref() == Some(&name) {
+        settings::remove(&app, LAST_PRESET_KEY)?;
+    }
+    Ok(true)
+}
+
+/// Leave the current room, if any, and join the room saved under `name`.
+#[tauri::command]
+pub async fn join_preset(app: AppHandle, name: String) -> Result<RoomStatus, String> {
+    let code = presets(&app)
+        .remove(&name)
+        .ok_or_else(|| format!("No room preset named {}", name))?;
+    let code = validate_code(&code)?;
+    let status = join(app.clone(), code, RoomMode::Member).await?;
+    settings::set(&app, LAST_PRESET_KEY, &name)?;
+    Ok(status)
+}
+
+/// Whether to rejoin the last joined preset when the app starts.
+#[tauri::command]
+pub fn set_rejoin_last_preset(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, REJOIN_ON_STARTUP_KEY, &enabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_room_codes() {
+        assert_eq!(validate_code("  ORG-ops_1 "), Ok("ORG-ops_1".to_string()));
+        assert!(validate_code("   ").is_err());
+        assert!(validate_code("has space").is_err());
+        assert!(validate_code("../../etc").is_err());
+        assert!(validate_code(&"a".repeat(MAX_CODE_LEN + 1)).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! Messages exchanged with a room relay server.
+//!
+//! This is a proposed protocol: no deployed server speaks it yet. The
+//! picologs server the frontend talks to uses its own messages (`register`,
+//! `batch_logs`, `sync_logs`, ...), so rooms only work against a relay set
+//! with `set_server_url`.
+//!
+//! ```
+//! use picologs_lib::protocol::{ClientMessage, ServerMessage};
+//!
+//! let leave = serde_json::to_string(&ClientMessage::LeaveRoom).unwrap();
+//! assert_eq!(leave, r#"{"type":"leave_room"}"#);
+//!
+//! let joined: ServerMessage =
+//!     serde_json::from_str(r#"{"type":"joined","code":"ABC123","members":2}"#).unwrap();
+//! assert!(matches!(joined, ServerMessage::Joined { members: 2, .. }));
+//! ```
+
+use crate::event::LogEvent;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    Online,
+    Offline,
+}
+
+/// Sent to the room relay as JSON text frames, tagged by `type`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    JoinRoom {
+        code: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+        /// Asks the server to list us as a read-only observer.
+        spectator: bool,
+        /// Only asks whether the room exists, without subscribing to its feed.
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        probe: bool,
+        /// Event kinds the server should forward to us; empty means all.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        subscribe_filter: Vec<String>,
+    },
+    /// Replaces the `subscribe_filter` sent when joining.
+    Subscribe {
+        subscribe_filter: Vec<String>,
+    },
+    LeaveRoom,
+    ShareEvent {
+        event: Box<LogEvent>,
+    },
+    Presence {
+        status: PresenceStatus,
+    },
+    /// A short note to the other members.
+    Chat {
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Joined {
+        code: String,
+        members: u32,
+    },
+    RoomEvent {
+        #[serde(default)]
+        from: Option<String>,
+        event: Box<LogEvent>,
+    },
+    Presence {
+        handle: String,
+        status: PresenceStatus,
+    },
+    Chat {
+        #[serde(default)]
+        from: Option<String>,
+        text: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// How this client takes part in a room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomMode {
+    Member,
+    /// Receives the feed but never shares events, chats or announces itself
+    /// online.
+    Spectator,
+}
+
+impl RoomMode {
+    /// Whether a client in this mode may send `message`.
+    pub fn allows(self, message: &ClientMessage) -> bool {
+        match (self, message) {
+            (RoomMode::Member, _) => true,
+            (
+                RoomMode::Spectator,
+                ClientMessage::ShareEvent { .. } | ClientMessage::Chat { .. },
+            ) => false,
+            (
+                RoomMode::Spectator,
+                ClientMessage::Presence {
+                    status: PresenceStatus::Online,
+                },
+            ) => false,
+            (RoomMode::Spectator, _) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::EventKind;
+
+    #[test]
+    fn spectators_cannot_share_or_announce_themselves() {
+        let share = ClientMessage::ShareEvent {
+            event: Box::new(LogEvent::test(EventKind::SystemQuit)),
+        };
+        let online = ClientMessage::Presence {
+            status: PresenceStatus::Online,
+        };
+        let offline = ClientMessage::Presence {
+            status: PresenceStatus::Offline,
+        };
+
+        assert!(RoomMode::Member.allows(&share));
+        assert!(RoomMode::Member.allows(&online));
+        assert!(!RoomMode::Spectator.allows(&share));
+        assert!(!RoomMode::Spectator.allows(&online));
+        assert!(RoomMode::Spectator.allows(&offline));
+        assert!(RoomMode::Spectator.allows(&ClientMessage::LeaveRoom));
+
+        let chat = ClientMessage::Chat {
+            text: "forming up".to_string(),
+        };
+        assert!(RoomMode::Member.allows(&chat));
+        assert!(!RoomMode::Spectator.allows(&chat));
+    }
+
+    #[test]
+    fn messages_are_tagged_by_type() {
+        let join = ClientMessage::JoinRoom {
+            code: "ABC123".to_string(),
+            token: None,
+            spectator: true,
+            probe: false,
+            subscribe_filter: Vec::new(),
+        };
+        assert_eq!(
+            serde_json::to_string(&join).unwrap(),
+            r#"{"type":"join_room","code":"ABC123","spectator":true}"#
+        );
+
+        let probe = ClientMessage::JoinRoom {
+            code: "ABC123".to_string(),
+            token: None,
+            spectator: false,
+            probe: true,
+            subscribe_filter: Vec::new(),
+        };
+        assert!(serde_json::to_string(&probe)
+            .unwrap()
+            .ends_with(r#""probe":true}"#));
+
+        let subscribe = ClientMessage::Subscribe {
+            subscribe_filter: vec!["actor_death".to_string()],
+        };
+        assert_eq!(
+            serde_json::to_string(&subscribe).unwrap(),
+            r#"{"type":"subscribe","subscribe_filter":["actor_death"]}"#
+        );
+
+        let joined: ServerMessage =
+            serde_json::from_str(r#"{"type":"joined","code":"ABC123","members":3}"#).unwrap();
+        assert!(matches!(joined, ServerMessage::Joined { members: 3, .. }));
+    }
+}